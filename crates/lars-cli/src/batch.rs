@@ -0,0 +1,17 @@
+//! Command-line options shared by the batch commands
+
+use clap::Args;
+use lars_core::batch::DEFAULT_JOBS;
+
+/// Concurrency of the commands that act on several services at once
+#[derive(Args, Debug)]
+pub struct JobsArgs {
+    /// Number of services to handle concurrently
+    #[arg(
+        short,
+        long,
+        default_value_t = DEFAULT_JOBS,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=256)
+    )]
+    pub jobs: usize,
+}
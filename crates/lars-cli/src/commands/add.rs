@@ -1,25 +1,144 @@
 //! Add command implementation
 
 use anyhow::Result;
+use clap::Args;
 use lars_core::{
-    generate_service_name, validate_service_name, ConfigManager, RunnerType, Service,
+    generate_service_name, validate_service_name, ConfigManager, Hook, LogFormat, LogMode,
+    OverlapPolicy, PortSpec, RunnerType, Schedule, ScheduleConfig, Service, ServiceKind,
+    WatchConfig,
 };
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
-pub fn run(
-    config: &ConfigManager,
-    command: String,
-    name: Option<String>,
-    workdir: Option<String>,
-    env: Vec<String>,
-    disabled: bool,
-    runner: String,
-    ctx: &OutputContext,
-) -> Result<ExitCode> {
+/// Definition of a new service
+#[derive(Args, Debug)]
+pub struct AddArgs {
+    /// The command to run
+    pub command: String,
+
+    /// Service name (auto-generated if not provided)
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Working directory
+    #[arg(short = 'd', long)]
+    pub workdir: Option<String>,
+
+    /// Environment variables (KEY=VALUE)
+    #[arg(short, long = "env", value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+
+    /// Tags for selecting groups of services (repeatable)
+    #[arg(short, long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Port the service listens on: a fixed port checked for conflicts on start, or a
+    /// named slot allocated a free port (e.g. 8080, 5353/udp, http, http=8080; repeatable)
+    #[arg(short, long = "port", value_name = "PORT")]
+    pub ports: Vec<String>,
+
+    /// Add in disabled state
+    #[arg(long)]
+    pub disabled: bool,
+
+    /// Runner type
+    #[arg(short, long, default_value = "tmux")]
+    pub runner: String,
+
+    /// Service kind: a long-running service, or a task that runs to completion
+    #[arg(short, long, default_value = "service")]
+    pub kind: String,
+
+    #[command(flatten)]
+    pub hooks: HookArgs,
+
+    #[command(flatten)]
+    pub watch: WatchArgs,
+
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+
+    #[command(flatten)]
+    pub logs: LogArgs,
+}
+
+/// Lifecycle hook commands for a new service
+#[derive(Args, Debug, Default)]
+pub struct HookArgs {
+    /// Command to run before starting (e.g. migrations); failure aborts the start
+    #[arg(long, value_name = "CMD")]
+    pub pre_start: Option<String>,
+
+    /// Command to run after starting
+    #[arg(long, value_name = "CMD")]
+    pub post_start: Option<String>,
+
+    /// Command to run before stopping
+    #[arg(long, value_name = "CMD")]
+    pub pre_stop: Option<String>,
+
+    /// Command to run after the service has stopped (e.g. cleanup)
+    #[arg(long, value_name = "CMD")]
+    pub post_stop: Option<String>,
+}
+
+/// Files whose changes restart a new service under `lars watch`
+#[derive(Args, Debug, Default)]
+pub struct WatchArgs {
+    /// Restart on changes to files matching this glob (relative to the workdir)
+    #[arg(long = "watch", value_name = "GLOB")]
+    pub paths: Vec<String>,
+
+    /// Ignore changes to files matching this glob when watching
+    #[arg(long = "watch-ignore", value_name = "GLOB")]
+    pub ignore: Vec<String>,
+}
+
+/// Periodic schedule for a new service
+#[derive(Args, Debug, Default)]
+pub struct ScheduleArgs {
+    /// Run on a schedule via `lars daemon`: a cron expression, @hourly/@daily/..., or "every 10m"
+    #[arg(long, value_name = "SPEC")]
+    pub schedule: Option<String>,
+
+    /// What to do when a run is due while the previous one is still going (skip, queue, kill)
+    #[arg(long, value_name = "POLICY", requires = "schedule")]
+    pub overlap: Option<String>,
+}
+
+/// How the output of a new service is logged
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    /// On start, archive the previous log and start fresh (truncate) or keep appending (append)
+    #[arg(long, default_value = "truncate")]
+    pub log_mode: String,
+
+    /// Log output raw, or as JSON records with timestamps and stream tags (json)
+    #[arg(long, default_value = "raw")]
+    pub log_format: String,
+}
+
+pub fn run(config: &ConfigManager, args: AddArgs, ctx: &OutputContext) -> Result<ExitCode> {
+    let AddArgs {
+        command,
+        name,
+        workdir,
+        env,
+        tags,
+        ports,
+        disabled,
+        runner,
+        kind,
+        hooks,
+        watch,
+        schedule,
+        logs,
+    } = args;
+
     let name = match name {
         Some(n) => {
             validate_service_name(&n)?;
@@ -44,6 +163,17 @@ pub fn run(
         .parse()
         .map_err(|e: String| anyhow::anyhow!(e))?;
 
+    let kind: ServiceKind = kind.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+
+    let log_mode: LogMode = logs
+        .log_mode
+        .parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+    let log_format: LogFormat = logs
+        .log_format
+        .parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+
     let mut env_map = HashMap::new();
     for e in env {
         if let Some((key, value)) = e.split_once('=') {
@@ -56,6 +186,12 @@ pub fn run(
         }
     }
 
+    let ports = ports
+        .iter()
+        .map(|p| p.parse::<PortSpec>())
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| anyhow::anyhow!(e))?;
+
     let cwd = workdir.map(PathBuf::from);
     if let Some(ref dir) = cwd {
         if !dir.exists() {
@@ -71,11 +207,53 @@ pub fn run(
         }
     }
 
+    let watch = if watch.paths.is_empty() && watch.ignore.is_empty() {
+        None
+    } else {
+        if cwd.is_none() {
+            return Err(anyhow::anyhow!(
+                "Watching files requires a working directory (--workdir)"
+            ));
+        }
+        Some(WatchConfig {
+            paths: watch.paths,
+            ignore: watch.ignore,
+            ..Default::default()
+        })
+    };
+
+    let schedule = match schedule.schedule {
+        Some(spec) => {
+            spec.parse::<Schedule>()
+                .map_err(|e: String| anyhow::anyhow!(e))?;
+            let overlap: OverlapPolicy = schedule
+                .overlap
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .map_err(|e: String| anyhow::anyhow!(e))?
+                .unwrap_or_default();
+            Some(ScheduleConfig { spec, overlap })
+        }
+        None => None,
+    };
+
     let mut service = Service::new(name.clone(), command);
     service.cwd = cwd;
     service.env = env_map;
+    service.tags = tags;
+    service.ports = ports;
     service.enabled = !disabled;
     service.runner_type = runner_type;
+    service.kind = kind;
+    service.pre_start = hooks.pre_start.map(Hook::new);
+    service.post_start = hooks.post_start.map(Hook::new);
+    service.pre_stop = hooks.pre_stop.map(Hook::new);
+    service.post_stop = hooks.post_stop.map(Hook::new);
+    service.watch = watch;
+    service.schedule = schedule;
+    service.log_mode = log_mode;
+    service.log_format = log_format;
 
     config.add_service(service)?;
 
@@ -87,6 +265,27 @@ pub fn run(
     } else {
         ctx.success(&format!("Added service '{}'", name));
     }
+    warn_runner(runner_type, ctx);
 
     Ok(ExitCode::Success)
 }
+
+/// Warn up front when the chosen runner can't run the service or lacks
+/// features other commands rely on
+fn warn_runner(runner_type: RunnerType, ctx: &OutputContext) {
+    let missing = runner_type.capabilities().missing();
+    if !missing.is_empty() {
+        ctx.warn(&format!(
+            "Runner '{}' does not support: {}",
+            runner_type,
+            missing.join(", ")
+        ));
+    }
+
+    if let Err(e) = runners::create_runner(runner_type) {
+        ctx.warn(&format!(
+            "{}; the service won't start until the runner is available",
+            e
+        ));
+    }
+}
@@ -1,14 +1,19 @@
 //! Attach command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager, LarsError};
+use lars_core::{ConfigManager, LarsError, Service};
 
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
 pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<ExitCode> {
     let service = config.get_service(name)?;
-    let runner = create_runner(service.runner_type)?;
+    let runner = runners::create_runner(service.runner_type)?;
+
+    if !runner.capabilities().attach {
+        return not_supported(&service, ctx);
+    }
 
     // Check if running
     if !runner.is_running(&service)? {
@@ -32,45 +37,48 @@ pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<Ex
                 ctx.info("Attaching to session...");
             }
 
-            // Use exec to replace current process
-            #[cfg(unix)]
-            {
-                use std::os::unix::process::CommandExt;
-                let mut command = std::process::Command::new(&args[0]);
-                command.args(&args[1..]);
-                let err = command.exec();
-                return Err(anyhow::anyhow!("Failed to exec: {}", err));
-            }
+            exec(&args)
+        }
+        None => not_supported(&service, ctx),
+    }
+}
 
-            #[cfg(not(unix))]
-            {
-                let status = std::process::Command::new(&args[0])
-                    .args(&args[1..])
-                    .status()?;
+fn not_supported(service: &Service, ctx: &OutputContext) -> Result<ExitCode> {
+    let err = LarsError::OperationNotSupported(format!(
+        "Runner '{}' does not support attach",
+        service.runner_type
+    ));
 
-                if status.success() {
-                    return Ok(ExitCode::Success);
-                } else {
-                    return Err(anyhow::anyhow!("Attach command failed"));
-                }
-            }
-        }
-        None => {
-            let err = LarsError::OperationNotSupported(format!(
-                "Runner '{}' does not support attach",
-                service.runner_type
-            ));
+    if ctx.json {
+        ctx.json(&serde_json::json!({
+            "error": "not_supported",
+            "message": err.to_string()
+        }))?;
+    } else {
+        ctx.error(&err.to_string());
+    }
 
-            if ctx.json {
-                ctx.json(&serde_json::json!({
-                    "error": "not_supported",
-                    "message": err.to_string()
-                }))?;
-            } else {
-                ctx.error(&err.to_string());
-            }
+    Ok(ExitCode::RunnerUnavailable)
+}
 
-            Ok(ExitCode::RunnerUnavailable)
-        }
+/// Run an attach command, replacing the current process where possible
+#[cfg(unix)]
+pub(crate) fn exec(args: &[String]) -> Result<ExitCode> {
+    use std::os::unix::process::CommandExt;
+    let err = std::process::Command::new(&args[0]).args(&args[1..]).exec();
+    Err(anyhow::anyhow!("Failed to exec: {}", err))
+}
+
+/// Run an attach command, replacing the current process where possible
+#[cfg(not(unix))]
+pub(crate) fn exec(args: &[String]) -> Result<ExitCode> {
+    let status = std::process::Command::new(&args[0])
+        .args(&args[1..])
+        .status()?;
+
+    if status.success() {
+        Ok(ExitCode::Success)
+    } else {
+        Err(anyhow::anyhow!("Attach command failed"))
     }
 }
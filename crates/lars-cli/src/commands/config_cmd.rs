@@ -1,7 +1,7 @@
 //! Config command implementation
 
 use anyhow::Result;
-use lars_core::{ConfigManager, RunnerType, ShutdownBehavior};
+use lars_core::{ConfigManager, Event, EventKind, RunnerType, ShutdownBehavior};
 
 use crate::output::OutputContext;
 use crate::ExitCode;
@@ -23,6 +23,25 @@ pub fn show(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
             "  restart_timeout_secs: {}",
             app_config.settings.restart_timeout_secs
         );
+        println!(
+            "  task_timeout_secs: {}",
+            app_config.settings.task_timeout_secs
+        );
+        println!("  log_max_size_mb: {}", app_config.settings.log_max_size_mb);
+        println!(
+            "  log_max_age_hours: {}",
+            app_config.settings.log_max_age_hours
+        );
+        println!("  log_retain: {}", app_config.settings.log_retain);
+        println!("  notify_desktop: {}", app_config.settings.notify_desktop);
+        println!(
+            "  notify_command: {}",
+            app_config.settings.notify_command.as_deref().unwrap_or("-")
+        );
+        println!(
+            "  notify_webhook: {}",
+            app_config.settings.notify_webhook.as_deref().unwrap_or("-")
+        );
         println!();
         println!("Services: {}", app_config.services.len());
     }
@@ -55,15 +74,56 @@ pub fn set(config: &ConfigManager, key: &str, value: &str, ctx: &OutputContext)
             }
             app_config.settings.restart_timeout_secs = timeout;
         }
+        "task_timeout_secs" => {
+            app_config.settings.task_timeout_secs = value.parse().map_err(|_| {
+                anyhow::anyhow!("Invalid timeout value: must be a whole number of seconds")
+            })?;
+        }
+        "log_max_size_mb" => {
+            app_config.settings.log_max_size_mb = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid size: must be a whole number of MB"))?;
+        }
+        "log_max_age_hours" => {
+            app_config.settings.log_max_age_hours = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid age: must be a whole number of hours"))?;
+        }
+        "log_retain" => {
+            app_config.settings.log_retain = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid count: must be a non-negative integer"))?;
+        }
+        "notify_desktop" => {
+            app_config.settings.notify_desktop = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid value: must be true or false"))?;
+        }
+        // An empty value turns the sink off
+        "notify_command" => {
+            app_config.settings.notify_command = Some(value.to_string()).filter(|v| !v.is_empty());
+        }
+        "notify_webhook" => {
+            if !value.is_empty() && !value.starts_with("http://") && !value.starts_with("https://")
+            {
+                return Err(anyhow::anyhow!(
+                    "Invalid webhook URL: must start with http:// or https://"
+                ));
+            }
+            app_config.settings.notify_webhook = Some(value.to_string()).filter(|v| !v.is_empty());
+        }
         _ => {
             return Err(anyhow::anyhow!(
-                "Unknown config key: {}. Valid keys: default_runner, shutdown_behavior, restart_timeout_secs",
+                "Unknown config key: {}. Valid keys: default_runner, shutdown_behavior, restart_timeout_secs, task_timeout_secs, log_max_size_mb, log_max_age_hours, log_retain, notify_desktop, notify_command, notify_webhook",
                 key
             ));
         }
     }
 
     config.save(&app_config)?;
+    let _ = config.record_event(
+        Event::new(EventKind::Configured).with_detail(format!("{} = {}", key, value)),
+    );
 
     if ctx.json {
         ctx.json(&serde_json::json!({
@@ -0,0 +1,296 @@
+//! Daemon command implementation
+//!
+//! Runs in the foreground, starts scheduled services when they are due,
+//! rotates service logs once they exceed the configured size or age,
+//! records exits and health changes in the event history, sends the
+//! notifications they call for and answers the control API on a Unix socket
+//! (see [`crate::control`]). With `--metrics` it also serves Prometheus
+//! metrics over HTTP.
+//! The configuration is reloaded on every tick, so services can be added,
+//! edited or disabled while the daemon is running.
+
+use anyhow::Result;
+use chrono::Utc;
+use lars_core::{metrics, notify, rotation};
+use lars_core::{
+    ConfigManager, MetricsCollector, Notifier, RotationPolicy, ScheduledAction, Scheduler, Service,
+    StartOutcome, StatusTracker,
+};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+use tiny_http::{Header, Response, Server};
+use uuid::Uuid;
+
+use crate::output::OutputContext;
+use crate::runners;
+use crate::ExitCode;
+
+/// How often schedules and log sizes are checked
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn run(config: &ConfigManager, metrics: Option<&str>, ctx: &OutputContext) -> Result<ExitCode> {
+    let mut scheduler = Scheduler::new();
+    // Scheduled tasks are followed across ticks rather than waited for
+    let manager = runners::manager(config.clone()).wait_for_tasks(false);
+    let mut tracker = StatusTracker::new().recording(true);
+    // Services started by the daemon that have not finished yet
+    let mut active: HashSet<Uuid> = HashSet::new();
+    // Services whose invalid schedule has already been reported
+    let mut invalid: HashSet<Uuid> = HashSet::new();
+    // Services whose missing runner has already been reported
+    let mut unavailable: HashSet<Uuid> = HashSet::new();
+
+    #[cfg(unix)]
+    let socket = crate::control::spawn(config)?;
+    spawn_notifier(config);
+    let metrics = metrics
+        .map(|addr| serve_metrics(config, addr))
+        .transpose()?;
+
+    if !ctx.json {
+        let scheduled = config
+            .list_services()?
+            .iter()
+            .filter(|s| s.enabled && s.schedule.is_some())
+            .count();
+        ctx.info(&format!(
+            "Daemon running with {} scheduled service(s)",
+            scheduled
+        ));
+        #[cfg(unix)]
+        ctx.info(&format!("Control API listening on {}", socket.display()));
+        if let Some(addr) = metrics {
+            ctx.info(&format!("Metrics served at http://{}/metrics", addr));
+        }
+        ctx.info("Press Ctrl+C to stop");
+    }
+
+    loop {
+        let app_config = match config.load() {
+            Ok(app_config) => app_config,
+            Err(e) => {
+                ctx.error(&format!("Failed to load configuration: {}", e));
+                std::thread::sleep(TICK_INTERVAL);
+                continue;
+            }
+        };
+        let state = config.load_state();
+
+        // Note exits and health changes in the event history
+        tracker.poll(&manager, &app_config.services);
+
+        let policy = RotationPolicy::from_settings(&app_config.settings);
+        for service in &app_config.services {
+            let log_path = config.log_path_for_service(&service.id);
+            if policy.should_rotate(&log_path, SystemTime::now()) {
+                match rotation::rotate(&log_path, policy.retain) {
+                    Ok(_) => report(ctx, "rotated", service, None)?,
+                    Err(e) => report(ctx, "rotate_failed", service, Some(&e.to_string()))?,
+                }
+            }
+        }
+
+        for service in &app_config.services {
+            if !service.enabled || service.schedule.is_none() {
+                scheduler.forget(&service.id);
+                continue;
+            }
+
+            let runner = match manager.runner(service) {
+                Ok(runner) => runner,
+                Err(e) => {
+                    if unavailable.insert(service.id) {
+                        report(ctx, "failed", service, Some(&e.to_string()))?;
+                    }
+                    continue;
+                }
+            };
+            unavailable.remove(&service.id);
+            let running = runner.is_running(service).unwrap_or(false);
+
+            if !running && active.remove(&service.id) {
+                let code = config.last_exit(&service.id).map(|e| e.code);
+                report_finished(ctx, service, code)?;
+            }
+
+            let last_started = state.service(&service.id).and_then(|s| s.last_started_at);
+            let action = match scheduler.poll(service, running, last_started, Utc::now()) {
+                Ok(Some(action)) => action,
+                Ok(None) => continue,
+                Err(e) => {
+                    if invalid.insert(service.id) {
+                        report(ctx, "invalid_schedule", service, Some(&e))?;
+                    }
+                    continue;
+                }
+            };
+            invalid.remove(&service.id);
+
+            let result = match action {
+                ScheduledAction::Start => manager.start(service),
+                ScheduledAction::Restart => manager.restart(service),
+                ScheduledAction::Skip => {
+                    report(ctx, "skipped", service, None)?;
+                    continue;
+                }
+                ScheduledAction::Queue => {
+                    report(ctx, "queued", service, None)?;
+                    continue;
+                }
+            };
+
+            match result {
+                // Started elsewhere since it was checked; that run is not
+                // the daemon's to report on
+                Ok(StartOutcome::AlreadyRunning) => report(ctx, "skipped", service, None)?,
+                Ok(outcome) => {
+                    active.insert(service.id);
+                    for warning in outcome.warnings() {
+                        ctx.warn(warning);
+                    }
+                    report(ctx, "started", service, None)?;
+                }
+                Err(e) => report(ctx, "failed", service, Some(&e.to_string()))?,
+            }
+        }
+
+        std::thread::sleep(TICK_INTERVAL);
+    }
+}
+
+/// Send notifications about events recorded from now on, by the daemon or
+/// any other command, on a thread of their own so that slow sinks never
+/// hold up the daemon
+fn spawn_notifier(config: &ConfigManager) {
+    // Created before the first tick, so no event the daemon records is missed
+    let mut notifier = Notifier::new(config);
+    let config = config.clone();
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK_INTERVAL);
+        let notifications = match notifier.poll(&config) {
+            Ok(notifications) if !notifications.is_empty() => notifications,
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::warn!("Failed to read the event history: {}", e);
+                continue;
+            }
+        };
+        let Ok(app_config) = config.load() else {
+            continue;
+        };
+        for notification in notifications {
+            for error in notify::send(&app_config.settings, &notification) {
+                tracing::warn!(
+                    "Failed to notify about '{}': {}",
+                    notification.service,
+                    error
+                );
+            }
+        }
+    });
+}
+
+/// Serve Prometheus metrics on `addr` (a port or host:port) in the
+/// background, returning the address listened on
+fn serve_metrics(config: &ConfigManager, addr: &str) -> Result<SocketAddr> {
+    let server = match addr.parse::<u16>() {
+        Ok(port) => Server::http(("127.0.0.1", port)),
+        Err(_) => Server::http(addr),
+    }
+    .map_err(|e| anyhow::anyhow!("Cannot serve metrics on {}: {}", addr, e))?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| anyhow::anyhow!("Cannot serve metrics on {}", addr))?;
+
+    let manager = runners::manager(config.clone());
+    std::thread::spawn(move || {
+        // Scrapes are answered in turn, measuring CPU since the last scrape
+        let mut collector = MetricsCollector::new();
+        for request in server.incoming_requests() {
+            if request.url() != "/metrics" {
+                let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+                continue;
+            }
+
+            let response = match collector.collect(&manager) {
+                Ok(services) => {
+                    let content_type = Header::from_bytes("Content-Type", metrics::CONTENT_TYPE)
+                        .expect("valid header");
+                    Response::from_string(metrics::render(&services)).with_header(content_type)
+                }
+                Err(e) => Response::from_string(e.to_string()).with_status_code(500),
+            };
+            let _ = request.respond(response);
+        }
+    });
+
+    Ok(addr)
+}
+
+/// Report a daemon event as an NDJSON line or a status message
+fn report(ctx: &OutputContext, event: &str, service: &Service, error: Option<&str>) -> Result<()> {
+    if ctx.json {
+        return ctx.json_line(&serde_json::json!({
+            "event": event,
+            "name": service.name,
+            "time": Utc::now().to_rfc3339(),
+            "error": error,
+        }));
+    }
+
+    let name = &service.name;
+    match event {
+        "started" => ctx.success(&format!("Started scheduled run of '{}'", name)),
+        "skipped" => ctx.warn(&format!(
+            "Skipped run of '{}': previous run is still going",
+            name
+        )),
+        "queued" => ctx.info(&format!(
+            "Queued run of '{}' until the previous run finishes",
+            name
+        )),
+        "rotated" => ctx.info(&format!("Rotated log of '{}'", name)),
+        "rotate_failed" => ctx.error(&format!(
+            "Failed to rotate log of '{}': {}",
+            name,
+            error.unwrap_or_default()
+        )),
+        "invalid_schedule" => ctx.error(&format!(
+            "Invalid schedule for '{}': {}",
+            name,
+            error.unwrap_or_default()
+        )),
+        _ => ctx.error(&format!(
+            "Failed to start '{}': {}",
+            name,
+            error.unwrap_or_default()
+        )),
+    }
+
+    Ok(())
+}
+
+fn report_finished(ctx: &OutputContext, service: &Service, code: Option<i32>) -> Result<()> {
+    if ctx.json {
+        return ctx.json_line(&serde_json::json!({
+            "event": "finished",
+            "name": service.name,
+            "time": Utc::now().to_rfc3339(),
+            "exit_code": code,
+        }));
+    }
+
+    match code {
+        Some(0) => ctx.success(&format!("Run of '{}' finished", service.name)),
+        Some(code) => ctx.error(&format!(
+            "Run of '{}' exited with status {}",
+            service.name, code
+        )),
+        None => ctx.warn(&format!("Run of '{}' was stopped", service.name)),
+    }
+
+    Ok(())
+}
@@ -1,11 +1,13 @@
 //! Doctor command implementation
 
 use anyhow::Result;
-use lars_core::{ConfigManager, TmuxRunner};
+use lars_core::runner::{parse_tmux_version, MIN_TMUX_VERSION};
+use lars_core::{ConfigManager, RunnerType, TmuxRunner};
 use serde::Serialize;
 use std::process::Command;
 
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
 #[derive(Serialize)]
@@ -51,6 +53,9 @@ pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
     }
     checks.push(shell_check);
 
+    // Check the runners services use (optional, tmux is checked above)
+    checks.extend(check_runners(config));
+
     if ctx.json {
         ctx.json(&serde_json::json!({
             "checks": checks,
@@ -102,13 +107,22 @@ pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
 }
 
 fn check_tmux() -> Check {
-    if TmuxRunner::is_available() {
-        let version = TmuxRunner::version().unwrap_or_else(|| "unknown".to_string());
-        Check {
-            name: "tmux".to_string(),
-            status: "pass".to_string(),
-            message: version,
-            required: true,
+    if let Some(version) = TmuxRunner::new().version() {
+        // Older tmux can't pass environment variables to new sessions
+        let (major, minor) = MIN_TMUX_VERSION;
+        match parse_tmux_version(&version) {
+            Some(v) if v < MIN_TMUX_VERSION => Check {
+                name: "tmux".to_string(),
+                status: "fail".to_string(),
+                message: format!("{} ({}.{} or later required)", version, major, minor),
+                required: true,
+            },
+            _ => Check {
+                name: "tmux".to_string(),
+                status: "pass".to_string(),
+                message: version,
+                required: true,
+            },
         }
     } else {
         Check {
@@ -197,3 +211,48 @@ fn check_shell() -> Check {
         },
     }
 }
+
+fn check_runners(config: &ConfigManager) -> Vec<Check> {
+    let services = config.list_services().unwrap_or_default();
+    let mut runner_types = vec![RunnerType::default()];
+    for service in &services {
+        if !runner_types.contains(&service.runner_type) {
+            runner_types.push(service.runner_type);
+        }
+    }
+
+    runner_types
+        .into_iter()
+        .map(|runner_type| {
+            let users: Vec<&str> = services
+                .iter()
+                .filter(|s| s.runner_type == runner_type)
+                .map(|s| s.name.as_str())
+                .collect();
+            let used_by = if users.is_empty() {
+                String::new()
+            } else {
+                format!(" (used by {})", users.join(", "))
+            };
+
+            match runners::create_runner(runner_type) {
+                Ok(_) => Check {
+                    name: format!("{} runner", runner_type),
+                    status: "pass".to_string(),
+                    message: format!(
+                        "supports {}{}",
+                        runner_type.capabilities().supported().join(", "),
+                        used_by
+                    ),
+                    required: false,
+                },
+                Err(e) => Check {
+                    name: format!("{} runner", runner_type),
+                    status: "fail".to_string(),
+                    message: format!("{}{}", e, used_by),
+                    required: false,
+                },
+            }
+        })
+        .collect()
+}
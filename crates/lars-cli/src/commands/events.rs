@@ -0,0 +1,157 @@
+//! Events command implementation
+//!
+//! With `--follow`, events are printed one per line as they are appended,
+//! as NDJSON in JSON mode. Events are only read: exits and health changes
+//! show up once the daemon or another command records them.
+
+use anyhow::Result;
+use chrono::Utc;
+use comfy_table::Cell;
+use lars_core::duration::parse_time;
+use lars_core::{ConfigManager, Event, EventFilter, LogFollower};
+use std::time::Duration;
+
+use crate::output::{OutputContext, TableBuilder};
+use crate::ExitCode;
+
+/// How often followed events are checked for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn run(
+    config: &ConfigManager,
+    name: Option<&str>,
+    since: Option<&str>,
+    limit: usize,
+    follow: bool,
+    ctx: &OutputContext,
+) -> Result<ExitCode> {
+    let since = since
+        .map(|s| parse_time(s, Utc::now()).map_err(|e| anyhow::anyhow!(e)))
+        .transpose()?;
+
+    // Follow an existing service across renames; removed services can still
+    // be looked up by the name they had
+    let mut filter = EventFilter {
+        since,
+        ..Default::default()
+    };
+    if let Some(name) = name {
+        match config.get_service(name) {
+            Ok(service) => filter.service_id = Some(service.id),
+            Err(_) => filter.service = Some(name.to_string()),
+        }
+    }
+
+    if follow {
+        return follow_events(config, &filter, limit, ctx);
+    }
+
+    let events = config.events(&filter, limit)?;
+
+    if ctx.json {
+        ctx.json(&events)?;
+        return Ok(ExitCode::Success);
+    }
+
+    if events.is_empty() {
+        ctx.info("No events recorded");
+        return Ok(ExitCode::Success);
+    }
+
+    let mut table = TableBuilder::new(vec!["Time", "Service", "Event", "Details", "By"]);
+    for event in &events {
+        table.add_row(vec![
+            Cell::new(
+                event
+                    .time
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+            ),
+            Cell::new(event.service.as_deref().unwrap_or("-")),
+            ctx.event_cell(event),
+            Cell::new(details(event)),
+            Cell::new(by(event)),
+        ]);
+    }
+    println!("{}", table.build());
+
+    Ok(ExitCode::Success)
+}
+
+/// Print recent events, then new events as they are recorded
+fn follow_events(
+    config: &ConfigManager,
+    filter: &EventFilter,
+    limit: usize,
+    ctx: &OutputContext,
+) -> Result<ExitCode> {
+    // Following starts first, so no event falls between the history and
+    // the first poll
+    let mut follower = LogFollower::new(vec![config.events_path()]);
+    for event in config.events(filter, limit)? {
+        print_line(&event, ctx)?;
+    }
+
+    loop {
+        for line in follower.poll()? {
+            let Ok(event) = serde_json::from_str::<Event>(&line.text) else {
+                continue;
+            };
+            if filter.matches(&event) {
+                print_line(&event, ctx)?;
+            }
+        }
+
+        std::thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+/// Print an event as an NDJSON line or as a line of text
+fn print_line(event: &Event, ctx: &OutputContext) -> Result<()> {
+    if ctx.json {
+        return ctx.json_line(event);
+    }
+
+    let mut line = format!(
+        "{}  {}  {}",
+        event
+            .time
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        event.service.as_deref().unwrap_or("-"),
+        event.kind
+    );
+    let details = details(event);
+    if !details.is_empty() {
+        line.push_str(&format!(": {}", details));
+    }
+    if event.actor.is_some() || event.user.is_some() {
+        line.push_str(&format!(" (by {})", by(event)));
+    }
+    println!("{}", line);
+    Ok(())
+}
+
+fn details(event: &Event) -> String {
+    let mut parts = Vec::new();
+    if let Some(code) = event.exit_code {
+        parts.push(format!("status {}", code));
+    }
+    if let Some(error) = &event.error {
+        parts.push(error.clone());
+    }
+    if let Some(detail) = &event.detail {
+        parts.push(detail.clone());
+    }
+
+    parts.join("; ")
+}
+
+fn by(event: &Event) -> String {
+    match (&event.actor, &event.user) {
+        (Some(actor), Some(user)) => format!("{} ({})", actor, user),
+        (Some(actor), None) => actor.clone(),
+        (None, Some(user)) => user.clone(),
+        (None, None) => "-".to_string(),
+    }
+}
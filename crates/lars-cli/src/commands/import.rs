@@ -1,7 +1,7 @@
 //! Import command implementation
 
 use anyhow::Result;
-use lars_core::{AppConfig, ConfigManager};
+use lars_core::{AppConfig, ConfigManager, Event, EventKind};
 use std::fs;
 use uuid::Uuid;
 
@@ -31,6 +31,9 @@ pub fn run(config: &ConfigManager, file: &str, merge: bool, ctx: &OutputContext)
         }
 
         config.save(&existing)?;
+        let _ = config.record_event(
+            Event::new(EventKind::Imported).with_detail(format!("merged from {}", file)),
+        );
 
         if ctx.json {
             ctx.json(&serde_json::json!({
@@ -57,6 +60,9 @@ pub fn run(config: &ConfigManager, file: &str, merge: bool, ctx: &OutputContext)
         }
 
         config.save(&imported)?;
+        let _ = config.record_event(
+            Event::new(EventKind::Imported).with_detail(format!("replaced from {}", file)),
+        );
 
         if ctx.json {
             ctx.json(&serde_json::json!({
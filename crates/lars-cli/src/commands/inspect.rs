@@ -1,10 +1,12 @@
 //! Inspect command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager};
+use lars_core::{ConfigManager, HookStage};
 use serde::Serialize;
 
+use crate::commands::list;
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
 #[derive(Serialize)]
@@ -14,11 +16,30 @@ struct InspectInfo {
     command: String,
     cwd: Option<String>,
     env: std::collections::HashMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<lars_core::PortSpec>,
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    assigned_ports: std::collections::BTreeMap<String, u16>,
     enabled: bool,
     autostart: bool,
     runner: String,
+    kind: String,
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    hooks: std::collections::BTreeMap<String, lars_core::Hook>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    watch: Option<lars_core::WatchConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<lars_core::ScheduleConfig>,
+    log_mode: String,
+    log_format: String,
     status: String,
     pid: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    listening: Vec<lars_core::ListeningPort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_exit: Option<lars_core::ExitRecord>,
     log_path: String,
     created_at: String,
     updated_at: String,
@@ -28,7 +49,7 @@ pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<Ex
     let service = config.get_service(name)?;
     let log_path = config.log_path_for_service(&service.id);
 
-    let (running, pid) = if let Ok(runner) = create_runner(service.runner_type) {
+    let (running, pid) = if let Ok(runner) = runners::create_runner(service.runner_type) {
         let running = runner.is_running(&service).unwrap_or(false);
         let pid = runner.get_pid(&service).unwrap_or(None);
         (running, pid)
@@ -36,17 +57,44 @@ pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<Ex
         (false, None)
     };
 
+    let hooks = [
+        HookStage::PreStart,
+        HookStage::PostStart,
+        HookStage::PreStop,
+        HookStage::PostStop,
+    ]
+    .into_iter()
+    .filter_map(|stage| stage.hook(&service).map(|h| (stage.to_string(), h.clone())))
+    .collect();
+
     let info = InspectInfo {
         id: service.id.to_string(),
         name: service.name.clone(),
         command: service.command.clone(),
         cwd: service.cwd.as_ref().map(|p| p.to_string_lossy().to_string()),
+        tags: service.tags.clone(),
+        ports: service.ports.clone(),
+        assigned_ports: config
+            .load_state()
+            .service(&service.id)
+            .map(|s| s.ports.clone())
+            .unwrap_or_default(),
         env: service.env.clone(),
         enabled: service.enabled,
         autostart: service.autostart,
         runner: service.runner_type.to_string(),
+        kind: service.kind.to_string(),
+        hooks,
+        watch: service.watch.clone(),
+        schedule: service.schedule.clone(),
+        log_mode: service.log_mode.to_string(),
+        log_format: service.log_format.to_string(),
         status: if running { "running" } else { "stopped" }.to_string(),
         pid,
+        listening: list::listening_ports(std::slice::from_ref(&service))
+            .remove(&service.id)
+            .unwrap_or_default(),
+        last_exit: config.last_exit(&service.id),
         log_path: log_path.to_string_lossy().to_string(),
         created_at: service.created_at.to_rfc3339(),
         updated_at: service.updated_at.to_rfc3339(),
@@ -67,17 +115,77 @@ pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<Ex
                 println!("  {}={}", key, value);
             }
         }
+        if !info.tags.is_empty() {
+            println!("Tags:    {}", info.tags.join(", "));
+        }
+        if !info.ports.is_empty() {
+            println!("Ports:   {}", join(&info.ports));
+        }
+        if !info.assigned_ports.is_empty() {
+            let assigned: Vec<String> = info
+                .assigned_ports
+                .iter()
+                .map(|(name, port)| format!("{}={}", name, port))
+                .collect();
+            println!("Assigned: {}", assigned.join(", "));
+        }
         println!("Enabled: {}", ctx.enabled_indicator(info.enabled));
         println!("Autostart: {}", info.autostart);
         println!("Runner:  {}", info.runner);
+        println!("Kind:    {}", info.kind);
+        for (stage, hook) in &info.hooks {
+            let optional = if hook.optional { ", optional" } else { "" };
+            println!(
+                "Hook:    {} = {} (timeout {}s{})",
+                stage, hook.command, hook.timeout_secs, optional
+            );
+        }
+        if let Some(watch) = &info.watch {
+            let paths = if watch.paths.is_empty() {
+                "all files".to_string()
+            } else {
+                watch.paths.join(", ")
+            };
+            println!("Watch:   {} (debounce {}ms)", paths, watch.debounce_ms);
+            if !watch.ignore.is_empty() {
+                println!("Ignore:  {}", watch.ignore.join(", "));
+            }
+        }
+        if let Some(schedule) = &info.schedule {
+            println!(
+                "Schedule: {} (overlap: {})",
+                schedule.spec, schedule.overlap
+            );
+        }
         println!("Status:  {}", ctx.status_indicator(running));
         if let Some(p) = info.pid {
             println!("PID:     {}", p);
         }
-        println!("Log:     {}", info.log_path);
+        if !info.listening.is_empty() {
+            println!("Listening: {}", join(&info.listening));
+        }
+        if let Some(exit) = &info.last_exit {
+            println!(
+                "Last exit: {} at {}",
+                exit.code,
+                exit.finished_at.to_rfc3339()
+            );
+        }
+        println!(
+            "Log:     {} ({}, {})",
+            info.log_path, info.log_mode, info.log_format
+        );
         println!("Created: {}", info.created_at);
         println!("Updated: {}", info.updated_at);
     }
 
     Ok(ExitCode::Success)
 }
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,23 +1,54 @@
 //! List command implementation
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use comfy_table::Cell;
-use lars_core::{create_runner, ConfigManager};
+use lars_core::duration::format_duration;
+use lars_core::{
+    ConfigManager, ListeningPort, PortSpec, ProcessStats, ProcessTable, Schedule, Service,
+    ServiceStatus, SocketTable, StatusReport,
+};
 use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
 
+use crate::commands::top;
 use crate::output::{OutputContext, TableBuilder};
+use crate::runners;
 use crate::ExitCode;
 
 #[derive(Serialize)]
 struct ServiceInfo {
     name: String,
-    status: String,
+    status: ServiceStatus,
     enabled: bool,
     runner: String,
+    kind: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_run: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_run: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<PortSpec>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    listening: Vec<ListeningPort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<ProcessStats>,
     command: String,
 }
 
-pub fn run(config: &ConfigManager, all: bool, ctx: &OutputContext) -> Result<ExitCode> {
+pub fn run(
+    config: &ConfigManager,
+    all: bool,
+    stats: bool,
+    ctx: &OutputContext,
+) -> Result<ExitCode> {
     let services = config.list_services()?;
 
     // Filter services
@@ -27,23 +58,37 @@ pub fn run(config: &ConfigManager, all: bool, ctx: &OutputContext) -> Result<Exi
         services.into_iter().filter(|s| s.enabled).collect()
     };
 
+    let manager = runners::manager(config.clone());
+    let state = config.load_state();
+    let now = Utc::now();
+    let last_run = |s: &Service| state.service(&s.id).and_then(|st| st.last_started_at);
+    let mut usage = if stats {
+        top::sample(&services)
+    } else {
+        Default::default()
+    };
+    let mut listening = listening_ports(&services);
+
     if ctx.json {
         let infos: Vec<ServiceInfo> = services
             .iter()
             .map(|s| {
-                let running = create_runner(s.runner_type)
-                    .map(|r| r.is_running(s).unwrap_or(false))
-                    .unwrap_or(false);
+                let report = StatusReport::check(&manager, s, &state);
 
                 ServiceInfo {
                     name: s.name.clone(),
-                    status: if running {
-                        "running".to_string()
-                    } else {
-                        "stopped".to_string()
-                    },
+                    status: report.status,
                     enabled: s.enabled,
                     runner: s.runner_type.to_string(),
+                    kind: s.kind.to_string(),
+                    tags: s.tags.clone(),
+                    exit_code: report.exit_code,
+                    schedule: s.schedule.as_ref().map(|sc| sc.spec.clone()),
+                    last_run: s.schedule.as_ref().and(last_run(s)),
+                    next_run: next_run(s, last_run(s), now),
+                    ports: s.ports.clone(),
+                    listening: listening.remove(&s.id).unwrap_or_default(),
+                    stats: usage.remove(&s.id),
                     command: s.command.clone(),
                 }
             })
@@ -59,20 +104,56 @@ pub fn run(config: &ConfigManager, all: bool, ctx: &OutputContext) -> Result<Exi
             return Ok(ExitCode::Success);
         }
 
-        let mut table = TableBuilder::new(vec!["Name", "Status", "Enabled", "Runner", "Command"]);
+        let scheduled = services.iter().any(|s| s.schedule.is_some());
+        let mut headers = vec!["Name", "Status", "Enabled", "Runner"];
+        if scheduled {
+            headers.extend(["Last run", "Next run"]);
+        }
+        if stats {
+            headers.extend(top::STATS_HEADERS);
+        }
+        let ports = !listening.is_empty();
+        if ports {
+            headers.push("Ports");
+        }
+        headers.push("Command");
+        let mut table = TableBuilder::new(headers);
 
         for service in &services {
-            let running = create_runner(service.runner_type)
-                .map(|r| r.is_running(service).unwrap_or(false))
-                .unwrap_or(false);
+            let report = StatusReport::check(&manager, service, &state);
+            let status = ctx.report_cell(&report);
 
-            table.add_row(vec![
+            let mut row = vec![
                 Cell::new(&service.name),
-                ctx.status_cell(running),
+                status,
                 ctx.enabled_cell(service.enabled),
                 Cell::new(service.runner_type.to_string()),
-                Cell::new(&service.command),
-            ]);
+            ];
+            if scheduled {
+                let last = service.schedule.as_ref().and(last_run(service));
+                let next = next_run(service, last, now);
+                row.push(Cell::new(
+                    last.map_or("-".to_string(), |t| time_ago(t, now)),
+                ));
+                row.push(Cell::new(
+                    next.map_or("-".to_string(), |t| time_until(t, now)),
+                ));
+            }
+            if stats {
+                row.extend(top::stats_cells(usage.get(&service.id)));
+            }
+            if ports {
+                row.push(Cell::new(match listening.get(&service.id) {
+                    Some(ports) => ports
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    None => "-".to_string(),
+                }));
+            }
+            row.push(Cell::new(&service.command));
+            table.add_row(row);
         }
 
         println!("{}", table.build());
@@ -80,3 +161,46 @@ pub fn run(config: &ConfigManager, all: bool, ctx: &OutputContext) -> Result<Exi
 
     Ok(ExitCode::Success)
 }
+
+/// Ports the running services are listening on, for services listening on any
+pub fn listening_ports(services: &[Service]) -> HashMap<Uuid, Vec<ListeningPort>> {
+    let pids = top::running_pids(services);
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+    let (Some(procs), Some(sockets)) = (ProcessTable::read(), SocketTable::read()) else {
+        return HashMap::new();
+    };
+
+    pids.into_iter()
+        .map(|(id, pid)| (id, sockets.ports_of_tree(&procs, pid)))
+        .filter(|(_, ports)| !ports.is_empty())
+        .collect()
+}
+
+/// Estimate when an enabled scheduled service will next run
+fn next_run(
+    service: &Service,
+    last_started: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if !service.enabled {
+        return None;
+    }
+    let schedule: Schedule = service.schedule.as_ref()?.spec.parse().ok()?;
+    schedule.next_run(last_started, now)
+}
+
+fn time_ago(t: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    format!(
+        "{} ago",
+        format_duration((now - t).to_std().unwrap_or_default())
+    )
+}
+
+fn time_until(t: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    match (t - now).to_std() {
+        Ok(wait) if !wait.is_zero() => format!("in {}", format_duration(wait)),
+        _ => "due".to_string(),
+    }
+}
@@ -1,97 +1,648 @@
 //! Logs command implementation
+//!
+//! Shows the logs of one or more services. With several services, lines are
+//! interleaved in time order behind a `name |` prefix, foreman-style. Raw logs
+//! have no timestamps, so the history of each is shown in one block, placed
+//! at the time the log was last written. Logs are
+//! read backwards from their end where possible, and otherwise streamed
+//! through a [`LineSearch`], so only the lines that will be shown are held in
+//! memory.
+//!
+//! Following is done in-process: logs are re-read from the top when they are
+//! truncated or replaced, and a marker is shown when a followed service
+//! starts again or exits.
 
 use anyhow::Result;
-use lars_core::ConfigManager;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use lars_core::duration::parse_time;
+use lars_core::{
+    logs, rotation, ConfigManager, LarsError, LineSearch, LogFilter, LogFollower, LogFormat,
+    LogLine, LogQuery, LogStream, ReverseLines, Service, Shown, StatusTracker,
+};
+use owo_colors::OwoColorize;
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-#[cfg(not(unix))]
-use std::io::{Seek, SeekFrom};
-use std::process::Command;
+use std::path::PathBuf;
 
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
+/// How often followed logs are checked for new lines
+const FOLLOW_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// How often followed services are checked for exits
+const STATUS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Options for viewing service logs
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// Follow log output
+    #[arg(short, long)]
+    pub follow: bool,
+
+    /// Stop following once the services have exited
+    #[arg(long, requires = "follow")]
+    pub until_exit: bool,
+
+    /// Number of lines to show per service
+    #[arg(short = 'n', long, default_value = "50")]
+    pub lines: usize,
+
+    /// Show the last N bytes of each log instead of lines
+    #[arg(short = 'c', long, value_name = "N", conflicts_with_all = ["lines", "previous"])]
+    pub bytes: Option<u64>,
+
+    /// Show the log from the previous run instead
+    #[arg(short, long, conflicts_with = "follow")]
+    pub previous: bool,
+
+    /// Only lines logged since this time (a duration like 10m, or an RFC 3339 timestamp)
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+
+    /// Only lines logged before this time (a duration like 10m, or an RFC 3339 timestamp)
+    #[arg(long, value_name = "TIME")]
+    pub until: Option<String>,
+
+    /// Only lines written to stderr
+    #[arg(long, conflicts_with = "stdout")]
+    pub stderr: bool,
+
+    /// Only lines written to stdout
+    #[arg(long)]
+    pub stdout: bool,
+
+    /// Only lines matching this regular expression
+    #[arg(long, value_name = "REGEX")]
+    pub grep: Option<String>,
+
+    /// Lines of context to show around each match
+    #[arg(short = 'C', long, value_name = "N", default_value = "0")]
+    pub context: usize,
+
+    /// Only JSON lines whose field matches (FIELD=VALUE or FIELD!=VALUE; repeatable)
+    #[arg(long = "where", value_name = "CONDITION")]
+    pub conditions: Vec<String>,
+
+    /// Show only these fields of JSON lines (comma-separated)
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    pub fields: Vec<String>,
+}
+
+impl LogsArgs {
+    fn filter(&self) -> Result<LogFilter> {
+        let now = Utc::now();
+        let parse = |s: &String| parse_time(s, now).map_err(|e| anyhow::anyhow!(e));
+
+        Ok(LogFilter {
+            since: self.since.as_ref().map(parse).transpose()?,
+            until: self.until.as_ref().map(parse).transpose()?,
+            stream: if self.stderr {
+                Some(LogStream::Stderr)
+            } else if self.stdout {
+                Some(LogStream::Stdout)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn query(&self) -> Result<LogQuery> {
+        Ok(LogQuery::new(
+            self.grep.as_deref(),
+            &self.conditions,
+            &self.fields,
+            self.context,
+        )?)
+    }
+}
+
+/// A selected service's log
+struct Source {
+    name: String,
+    /// The service's current log
+    log_path: PathBuf,
+    /// The file shown: the current log, or the previous run's log with --previous
+    path: PathBuf,
+}
+
 pub fn run(
     config: &ConfigManager,
-    name: &str,
-    follow: bool,
-    lines: usize,
+    names: &[String],
+    tags: &[String],
+    all: bool,
+    args: LogsArgs,
     ctx: &OutputContext,
 ) -> Result<ExitCode> {
-    let service = config.get_service(name)?;
-    let log_path = config.log_path_for_service(&service.id);
+    let services = select_services(config, names, tags, all)?;
+
+    let filter = args.filter()?;
+    if !filter.is_empty() {
+        if let Some(raw) = services.iter().find(|s| s.log_format == LogFormat::Raw) {
+            return Err(anyhow::anyhow!(
+                "Filtering by time or stream needs timestamped logs; '{}' logs raw output (see --log-format json)",
+                raw.name
+            ));
+        }
+    }
+    let query = args.query()?;
+
+    let sources: Vec<Source> = services
+        .iter()
+        .map(|s| {
+            let log_path = config.log_path_for_service(&s.id);
+            let path = if args.previous {
+                rotation::previous_path(&log_path)
+            } else {
+                log_path.clone()
+            };
+            Source {
+                name: s.name.clone(),
+                log_path,
+                path,
+            }
+        })
+        .collect();
+
+    if !args.follow && sources.iter().all(|s| !s.path.exists()) {
+        let message = if args.previous {
+            "No previous log found for this service"
+        } else {
+            "No log file found for this service"
+        };
 
-    if !log_path.exists() {
         if ctx.json {
             ctx.json(&serde_json::json!({
                 "error": "no_logs",
-                "message": "No log file found for this service"
+                "message": message
             }))?;
         } else {
-            ctx.warn("No log file found for this service");
-            ctx.info(&format!("Expected at: {}", log_path.display()));
+            ctx.warn(message);
+            if sources.len() == 1 && !args.previous {
+                ctx.info(&format!("Expected at: {}", sources[0].path.display()));
+            }
         }
         return Ok(ExitCode::Success);
     }
 
-    if follow {
-        #[cfg(unix)]
-        {
-            use std::os::unix::process::CommandExt;
-            let log_path_str = log_path.to_string_lossy();
-            let mut cmd = Command::new("tail");
-            cmd.args(["-f", "-n", &lines.to_string(), &log_path_str]);
+    // Following starts before the history is read, so no line falls between
+    // the two
+    let follower = args
+        .follow
+        .then(|| LogFollower::new(sources.iter().map(|s| s.log_path.clone()).collect()));
+
+    let mut searches: Vec<LineSearch> = sources.iter().map(|_| LineSearch::new(&query)).collect();
+    let mut lines = Vec::new();
+    for (idx, source) in sources.iter().enumerate() {
+        lines.extend(history(
+            source,
+            idx,
+            &args,
+            &filter,
+            &query,
+            &mut searches[idx],
+        )?);
+    }
+    // Raw history has no timestamps, so each raw log is kept together and
+    // placed at the time it was last written
+    let written: Vec<DateTime<Utc>> = sources
+        .iter()
+        .map(|s| {
+            std::fs::metadata(&s.path)
+                .and_then(|m| m.modified())
+                .map_or(DateTime::<Utc>::MIN_UTC, DateTime::from)
+        })
+        .collect();
+    logs::interleave(&mut lines, DateTime::<Utc>::MIN_UTC, |s| {
+        s.line.ts.or(Some(written[s.line.source]))
+    });
+
+    let printer = Printer::new(&sources, &query, ctx);
 
-            if !ctx.quiet && !ctx.json {
-                ctx.info(&format!("Following logs for '{}' (Ctrl+C to stop)...", name));
+    let Some(follower) = follower else {
+        if ctx.json {
+            printer.print_json(&lines)?;
+        } else {
+            for line in &lines {
+                printer.print(line);
             }
+        }
+        return Ok(ExitCode::Success);
+    };
+
+    for line in &lines {
+        printer.print_event(line)?;
+    }
+
+    if !ctx.quiet && !ctx.json {
+        let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+        ctx.info(&format!(
+            "Following logs for '{}' (Ctrl+C to stop)...",
+            names.join("', '")
+        ));
+    }
+
+    follow(
+        config,
+        &services,
+        follower,
+        &filter,
+        searches,
+        &printer,
+        args.until_exit,
+    )
+}
 
-            let err = cmd.exec();
-            return Err(anyhow::anyhow!("Failed to exec tail: {}", err));
+/// Print new lines as they are logged, along with restarts and exits
+fn follow(
+    config: &ConfigManager,
+    services: &[Service],
+    mut follower: LogFollower,
+    filter: &LogFilter,
+    mut searches: Vec<LineSearch>,
+    printer: &Printer,
+    until_exit: bool,
+) -> Result<ExitCode> {
+    let manager = runners::manager(config.clone());
+    let mut tracker = StatusTracker::new();
+    let mut running = vec![false; services.len()];
+    // The first poll reports every service, as it is now
+    for change in tracker.poll(&manager, services) {
+        if let Some(idx) = services.iter().position(|s| s.name == change.name) {
+            running[idx] = change.status.is_running();
         }
+    }
+    let mut launches = launch_times(config, services);
+    let mut failed = false;
+    let mut last_check = std::time::Instant::now();
 
-        #[cfg(not(unix))]
-        {
-            let mut file = File::open(&log_path)?;
-            file.seek(SeekFrom::End(0))?;
-            let mut reader = BufReader::new(file);
+    loop {
+        // Starts are recorded as services start, so one is checked right
+        // away and its marker shown ahead of the new run's output
+        let now_launched = launch_times(config, services);
+        let mut exits = Vec::new();
+        if now_launched != launches || last_check.elapsed() >= STATUS_INTERVAL {
+            launches = now_launched;
+            last_check = std::time::Instant::now();
+            for change in tracker.poll(&manager, services) {
+                let Some(idx) = services.iter().position(|s| s.name == change.name) else {
+                    continue;
+                };
+                if let Some(started_at) = change.started_at {
+                    printer.print_status(idx, &StatusEvent::Started(started_at))?;
+                }
+
+                // Exits are shown after reading, so a service's last lines
+                // come before it is reported as exited
+                let was_running = running[idx] || change.started_at.is_some();
+                running[idx] = change.status.is_running();
+                if was_running && !running[idx] {
+                    exits.push((idx, StatusEvent::Exited(change.exit_code)));
+                }
+            }
+        }
 
-            if !ctx.quiet && !ctx.json {
-                ctx.info(&format!("Following logs for '{}' (Ctrl+C to stop)...", name));
+        let mut new_lines = Vec::new();
+        for line in follower.poll()? {
+            if passes(&line, filter) {
+                let source = line.source;
+                new_lines.extend(searches[source].push(line));
             }
+        }
+        logs::interleave(&mut new_lines, Utc::now(), |s| s.line.ts);
+
+        for line in &new_lines {
+            printer.print_event(line)?;
+        }
+        for (idx, event) in &exits {
+            failed |= matches!(event, StatusEvent::Exited(Some(code)) if *code != 0);
+            printer.print_status(*idx, event)?;
+        }
+
+        if until_exit && running.iter().all(|r| !r) {
+            return Ok(if failed {
+                ExitCode::GeneralError
+            } else {
+                ExitCode::Success
+            });
+        }
+
+        std::thread::sleep(FOLLOW_POLL_INTERVAL);
+    }
+}
+
+/// A change in a followed service's status
+enum StatusEvent {
+    /// The service was started (again) at this time
+    Started(DateTime<Utc>),
+    /// The service exited with this code, or was stopped
+    Exited(Option<i32>),
+}
+
+/// When each service was last started
+fn launch_times(config: &ConfigManager, services: &[Service]) -> Vec<Option<DateTime<Utc>>> {
+    let state = config.load_state();
+    services
+        .iter()
+        .map(|s| state.service(&s.id).and_then(|st| st.last_started_at))
+        .collect()
+}
+
+/// Resolve service names, tags and `--all` into a list of services
+fn select_services(
+    config: &ConfigManager,
+    names: &[String],
+    tags: &[String],
+    all: bool,
+) -> Result<Vec<Service>> {
+    let app_config = config.load()?;
+    let mut selected: Vec<Service> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for name in names {
+        let service = app_config
+            .find_service_by_name(name)
+            .ok_or_else(|| LarsError::ServiceNotFound(name.clone()))?;
+        if seen.insert(service.id) {
+            selected.push(service.clone());
+        }
+    }
 
-            loop {
-                let mut line = String::new();
-                let bytes = reader.read_line(&mut line)?;
-                if bytes > 0 {
-                    print!("{}", line);
-                } else {
-                    std::thread::sleep(std::time::Duration::from_millis(100));
+    let tagged = tags
+        .iter()
+        .flat_map(|tag| app_config.services_with_tag(tag));
+    let everything = app_config.services.iter().filter(|_| all);
+    for service in tagged.chain(everything) {
+        if seen.insert(service.id) {
+            selected.push(service.clone());
+        }
+    }
+
+    if selected.is_empty() {
+        if all {
+            return Err(anyhow::anyhow!("No services configured"));
+        }
+        return Err(anyhow::anyhow!(
+            "No services are tagged {}",
+            tags.join(", ")
+        ));
+    }
+
+    Ok(selected)
+}
+
+/// Read the end of a log, holding only the lines that will be shown
+fn history(
+    source: &Source,
+    idx: usize,
+    args: &LogsArgs,
+    filter: &LogFilter,
+    query: &LogQuery,
+    search: &mut LineSearch,
+) -> Result<Vec<Shown>> {
+    if let Some(bytes) = args.bytes {
+        let text = logs::tail_bytes(&source.path, bytes)?;
+        let lines = text.lines().map(str::to_string);
+        return Ok(keep_last(lines, idx, usize::MAX, filter, search));
+    }
+
+    // Archives are compressed, and context needs the lines around each
+    // match, so both are streamed from the start
+    if args.previous {
+        let Some(reader) = rotation::open_previous(&source.log_path)? else {
+            return Ok(Vec::new());
+        };
+        let lines = lossy_lines(reader);
+        return Ok(keep_last(lines, idx, args.lines, filter, search));
+    }
+    if query.context > 0 {
+        let Ok(file) = File::open(&source.path) else {
+            return Ok(Vec::new());
+        };
+        let lines = lossy_lines(BufReader::new(file));
+        return Ok(keep_last(lines, idx, args.lines, filter, search));
+    }
+
+    let Some(reverse) = ReverseLines::open(&source.path)? else {
+        return Ok(Vec::new());
+    };
+    let mut shown = Vec::new();
+    for raw in reverse {
+        if shown.len() == args.lines {
+            break;
+        }
+        let line = LogLine::parse(idx, &raw?);
+        if passes(&line, filter) && query.matches(&line) {
+            shown.push(Shown { line, gap: false });
+        }
+    }
+    shown.reverse();
+
+    Ok(shown)
+}
+
+/// Stream lines through the filter and search, keeping the last `count`
+/// lines to show
+fn keep_last(
+    lines: impl Iterator<Item = String>,
+    idx: usize,
+    count: usize,
+    filter: &LogFilter,
+    search: &mut LineSearch,
+) -> Vec<Shown> {
+    let mut shown = VecDeque::new();
+
+    for raw in lines {
+        let line = LogLine::parse(idx, &raw);
+        if !passes(&line, filter) {
+            continue;
+        }
+        for line in search.push(line) {
+            shown.push_back(line);
+            if shown.len() > count {
+                shown.pop_front();
+            }
+        }
+    }
+
+    // The first line kept may follow lines dropped from the tail
+    if let Some(first) = shown.front_mut() {
+        first.gap = false;
+    }
+
+    shown.into()
+}
+
+/// Check a line against the time and stream filter
+fn passes(line: &LogLine, filter: &LogFilter) -> bool {
+    line.record().is_none_or(|r| filter.matches(&r))
+}
+
+/// Read lines until the end of a log, replacing invalid UTF-8 with `U+FFFD`
+/// rather than stopping at it
+fn lossy_lines(mut reader: impl BufRead) -> impl Iterator<Item = String> {
+    std::iter::from_fn(move || {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                    if line.last() == Some(&b'\r') {
+                        line.pop();
+                    }
                 }
+                Some(String::from_utf8_lossy(&line).into_owned())
             }
         }
-    } else {
-        let file = File::open(&log_path)?;
-        let reader = BufReader::new(file);
-        let all_lines: Vec<String> = reader.lines().filter_map(|l| l.ok()).collect();
+    })
+}
+
+/// Renders lines for one or several services
+struct Printer<'a> {
+    sources: &'a [Source],
+    query: &'a LogQuery,
+    ctx: &'a OutputContext,
+    width: usize,
+}
 
-        let start = if all_lines.len() > lines {
-            all_lines.len() - lines
+impl<'a> Printer<'a> {
+    fn new(sources: &'a [Source], query: &'a LogQuery, ctx: &'a OutputContext) -> Self {
+        let width = sources.iter().map(|s| s.name.len()).max().unwrap_or(0);
+        Self {
+            sources,
+            query,
+            ctx,
+            width,
+        }
+    }
+
+    fn multi(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    fn print(&self, shown: &Shown) {
+        let line = &shown.line;
+        // grep-style separator between groups of context
+        if shown.gap && self.query.context > 0 && !self.multi() {
+            println!("--");
+        }
+
+        let text = match line.record() {
+            Some(mut record) => {
+                record.line = self.query.render(line);
+                self.ctx.log_record(&record)
+            }
+            None => self.query.render(line),
+        };
+
+        if self.multi() {
+            let name = &self.sources[line.source].name;
+            let prefix = self.ctx.service_prefix(name, line.source, self.width);
+            println!("{} {}", prefix, text);
+        } else {
+            println!("{}", text);
+        }
+    }
+
+    /// Print a line while following: as text, or as an NDJSON event
+    fn print_event(&self, shown: &Shown) -> Result<()> {
+        if self.ctx.json {
+            let mut value = self.json_line(&shown.line);
+            value["event"] = serde_json::json!("line");
+            self.ctx.json_line(&value)
         } else {
-            0
+            self.print(shown);
+            Ok(())
+        }
+    }
+
+    /// Print a marker for a service starting or exiting
+    fn print_status(&self, source: usize, event: &StatusEvent) -> Result<()> {
+        let name = &self.sources[source].name;
+
+        if self.ctx.json {
+            let value = match event {
+                StatusEvent::Started(time) => serde_json::json!({
+                    "event": "started",
+                    "service": name,
+                    "time": time.to_rfc3339(),
+                }),
+                StatusEvent::Exited(code) => serde_json::json!({
+                    "event": "exited",
+                    "service": name,
+                    "time": Utc::now().to_rfc3339(),
+                    "exit_code": code,
+                }),
+            };
+            return self.ctx.json_line(&value);
+        }
+
+        let marker = match event {
+            StatusEvent::Started(time) => format!(
+                "── started at {} ──",
+                time.with_timezone(&chrono::Local).format("%H:%M:%S")
+            ),
+            StatusEvent::Exited(Some(code)) => format!("── exited with status {} ──", code),
+            StatusEvent::Exited(None) => "── stopped ──".to_string(),
+        };
+        let marker = if self.ctx.no_color {
+            marker
+        } else {
+            marker.dimmed().to_string()
         };
 
-        if ctx.json {
-            ctx.json(&serde_json::json!({
-                "log_path": log_path.to_string_lossy(),
-                "lines": &all_lines[start..]
-            }))?;
+        if self.multi() {
+            let prefix = self.ctx.service_prefix(name, source, self.width);
+            println!("{} {}", prefix, marker);
         } else {
-            for line in &all_lines[start..] {
-                println!("{}", line);
-            }
+            println!("{}", marker);
         }
+        Ok(())
+    }
 
-        Ok(ExitCode::Success)
+    fn json_line(&self, line: &LogLine) -> serde_json::Value {
+        let mut value = serde_json::json!({
+            "service": self.sources[line.source].name,
+            "line": line.text,
+        });
+        if let Some(record) = line.record() {
+            value["ts"] = serde_json::json!(record.ts);
+            value["stream"] = serde_json::json!(record.stream);
+        }
+        if let Some(fields) = self.query.project(line) {
+            value["fields"] = serde_json::Value::Object(fields);
+        }
+        value
+    }
+
+    fn print_json(&self, shown: &[Shown]) -> Result<()> {
+        let lines: Vec<&LogLine> = shown.iter().map(|s| &s.line).collect();
+        if self.multi() {
+            let services: Vec<&str> = self.sources.iter().map(|s| s.name.as_str()).collect();
+            let lines: Vec<_> = lines.iter().map(|l| self.json_line(l)).collect();
+            return self.ctx.json(&serde_json::json!({
+                "services": services,
+                "lines": lines,
+            }));
+        }
+
+        let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        let records: Vec<_> = lines.iter().filter_map(|l| l.record()).collect();
+        let mut value = serde_json::json!({
+            "log_path": self.sources[0].path.to_string_lossy(),
+            "lines": text,
+        });
+        if !records.is_empty() {
+            value["records"] = serde_json::json!(records);
+        }
+        if self.query.projects() {
+            let fields: Vec<_> = lines.iter().map(|l| self.query.project(l)).collect();
+            value["fields"] = serde_json::json!(fields);
+        }
+        self.ctx.json(&value)
     }
 }
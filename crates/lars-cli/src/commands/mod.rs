@@ -4,17 +4,26 @@ pub mod add;
 pub mod attach;
 pub mod completions;
 pub mod config_cmd;
+pub mod daemon;
 pub mod doctor;
 pub mod enable;
+pub mod events;
 pub mod export;
 pub mod import;
 pub mod inspect;
 pub mod list;
 pub mod logs;
+pub mod reload;
 pub mod remove;
 pub mod rename;
 pub mod restart;
+pub mod restart_all;
 pub mod start;
 pub mod start_all;
+pub mod status;
 pub mod stop;
 pub mod stop_all;
+pub mod top;
+pub mod tui;
+pub mod ui;
+pub mod watch;
@@ -0,0 +1,175 @@
+//! Reload command implementation
+//!
+//! Restarts only the running services whose command, working directory or
+//! environment changed since they were launched.
+
+use anyhow::Result;
+use lars_core::batch::BatchEvent;
+use lars_core::{ConfigManager, Service};
+use serde::Serialize;
+
+use crate::commands::start_all::with_warnings;
+use crate::output::{OutputContext, ProgressBoard, ProgressStatus};
+use crate::runners;
+use crate::ExitCode;
+
+#[derive(Serialize)]
+struct ReloadInfo {
+    name: String,
+    status: &'static str,
+    changed: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub fn run(
+    config: &ConfigManager,
+    jobs: usize,
+    dry_run: bool,
+    ctx: &OutputContext,
+) -> Result<ExitCode> {
+    let app_config = config.load()?;
+    let manager = runners::manager(config.clone());
+    let state = config.load_state();
+
+    let mut infos = Vec::new();
+    let mut to_restart: Vec<(usize, Service)> = Vec::new();
+
+    for service in app_config.services {
+        let running = manager.is_running(&service).unwrap_or(false);
+
+        if !running {
+            continue;
+        }
+
+        let launch = state.service(&service.id).and_then(|s| s.launch.as_ref());
+        // Launches are recorded with ports interpolated, so compare with the
+        // environment the service would be started with now
+        let (status, changed, error) = match launch.map(|r| (r, config.resolved_env(&service))) {
+            None => ("untracked", Vec::new(), None),
+            Some((_, Err(e))) => ("failed", Vec::new(), Some(e.to_string())),
+            Some((record, Ok(env))) => {
+                let mut current = service.clone();
+                current.env = env;
+                let changed = record.fingerprint.changed_fields(&current);
+                if changed.is_empty() {
+                    ("unchanged", changed, None)
+                } else if dry_run {
+                    ("would_restart", changed, None)
+                } else {
+                    to_restart.push((infos.len(), service.clone()));
+                    ("restarting", changed, None)
+                }
+            }
+        };
+
+        infos.push(ReloadInfo {
+            name: service.name,
+            status,
+            changed,
+            error,
+        });
+    }
+
+    if !ctx.json {
+        for info in &infos {
+            match info.status {
+                "untracked" => ctx.warn(&format!(
+                    "'{}' was not started by this version of lars; restart it once to track changes",
+                    info.name
+                )),
+                "would_restart" => ctx.info(&format!(
+                    "Would restart '{}' (changed: {})",
+                    info.name,
+                    info.changed.join(", ")
+                )),
+                "failed" => ctx.error(&format!(
+                    "Cannot tell whether '{}' changed: {}",
+                    info.name,
+                    info.error.as_deref().unwrap_or_default()
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    let names = to_restart.iter().map(|(_, s)| s.name.clone()).collect();
+    let mut board = ProgressBoard::new(ctx, names);
+    let services: Vec<_> = to_restart.iter().map(|(_, s)| s.clone()).collect();
+
+    let report = manager.restart_all(&services, jobs, |event| match event {
+        BatchEvent::Started(idx) => board.running(idx, "restarting"),
+        BatchEvent::Finished(idx, result) => {
+            let info = &infos[to_restart[idx].0];
+            match result {
+                Ok(None) => board.finish(
+                    idx,
+                    ProgressStatus::Skipped,
+                    &format!("'{}' stopped before it was restarted", info.name),
+                ),
+                Ok(Some(outcome)) => board.finish(
+                    idx,
+                    ProgressStatus::Success,
+                    &with_warnings(
+                        format!(
+                            "Restarted '{}' (changed: {})",
+                            info.name,
+                            info.changed.join(", ")
+                        ),
+                        outcome.warnings(),
+                    ),
+                ),
+                Err(e) => board.finish(
+                    idx,
+                    ProgressStatus::Failed,
+                    &format!("Failed to restart '{}': {}", info.name, e),
+                ),
+            }
+        }
+    });
+
+    for ((info_idx, _), result) in to_restart.iter().zip(report.results) {
+        let info = &mut infos[*info_idx];
+        match result {
+            Ok(Some(_)) => info.status = "restarted",
+            Ok(None) => info.status = "not_running",
+            Err(e) => {
+                info.status = "failed";
+                info.error = Some(e.to_string());
+            }
+        }
+    }
+
+    let count = |status: &str| infos.iter().filter(|i| i.status == status).count();
+    let restarted = count("restarted");
+    let unchanged = count("unchanged");
+    let failed = count("failed");
+
+    if ctx.json {
+        ctx.json(&serde_json::json!({
+            "dry_run": dry_run,
+            "services": infos,
+            "restarted": restarted,
+            "unchanged": unchanged,
+            "failed": failed
+        }))?;
+    } else if dry_run {
+        ctx.info(&format!(
+            "Summary: {} would restart, {} unchanged, {} failed",
+            count("would_restart"),
+            unchanged,
+            failed
+        ));
+    } else {
+        ctx.info(&format!(
+            "Summary: {} restarted, {} unchanged, {} failed",
+            restarted, unchanged, failed
+        ));
+    }
+
+    if failed > 0 {
+        Ok(ExitCode::StartFailed)
+    } else {
+        Ok(ExitCode::Success)
+    }
+}
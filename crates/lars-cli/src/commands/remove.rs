@@ -1,9 +1,10 @@
 //! Remove command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager};
+use lars_core::{ConfigManager, StopOutcome};
 
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
 pub fn run(
@@ -12,20 +13,17 @@ pub fn run(
     _force: bool,
     ctx: &OutputContext,
 ) -> Result<ExitCode> {
-    // Get the service first to check if it exists and get its runner type
     let service = config.get_service(name)?;
 
-    // Stop if running
-    if let Ok(runner) = create_runner(service.runner_type) {
-        if runner.is_running(&service)? {
-            ctx.info(&format!("Stopping service '{}'...", name));
-            runner.stop(&service)?;
+    // Stops the service first if it is running
+    let outcome = runners::manager(config.clone()).remove(&service)?;
+    if let StopOutcome::Stopped { warnings } = outcome {
+        ctx.info(&format!("Stopped service '{}'", name));
+        for warning in &warnings {
+            ctx.warn(warning);
         }
     }
 
-    // Remove from config
-    config.remove_service(name)?;
-
     if ctx.json {
         ctx.json(&serde_json::json!({
             "status": "removed",
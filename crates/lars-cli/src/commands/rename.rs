@@ -16,7 +16,7 @@ pub fn run(
     validate_service_name(new_name)?;
 
     // Get the existing service
-    let mut service = config.get_service(name)?;
+    let service = config.get_service(name)?;
 
     // Check if new name already exists
     if config.get_service(new_name).is_ok() {
@@ -27,11 +27,7 @@ pub fn run(
     }
 
     let old_name = service.name.clone();
-    service.name = new_name.to_string();
-
-    // Remove old and add with new name
-    config.remove_service(&old_name)?;
-    config.add_service(service)?;
+    config.update_service(&old_name, |service| service.name = new_name.to_string())?;
 
     if ctx.json {
         ctx.json(&serde_json::json!({
@@ -0,0 +1,83 @@
+//! Restart-all command implementation
+
+use anyhow::Result;
+use lars_core::batch::BatchEvent;
+use lars_core::ConfigManager;
+
+use crate::commands::start_all::with_warnings;
+use crate::output::{OutputContext, ProgressBoard, ProgressStatus};
+use crate::runners;
+use crate::ExitCode;
+
+pub fn run(config: &ConfigManager, jobs: usize, ctx: &OutputContext) -> Result<ExitCode> {
+    let enabled_services: Vec<_> = config
+        .list_services()?
+        .into_iter()
+        // Scheduled services are left to the daemon
+        .filter(|s| s.enabled && s.schedule.is_none())
+        .collect();
+
+    if enabled_services.is_empty() {
+        if ctx.json {
+            ctx.json(&serde_json::json!({
+                "restarted": 0,
+                "skipped": 0,
+                "failed": 0
+            }))?;
+        } else {
+            ctx.info("No enabled services to restart");
+        }
+        return Ok(ExitCode::Success);
+    }
+
+    let names = enabled_services.iter().map(|s| s.name.clone()).collect();
+    let mut board = ProgressBoard::new(ctx, names);
+
+    let manager = runners::manager(config.clone());
+    let report = manager.restart_all(&enabled_services, jobs, |event| match event {
+        BatchEvent::Started(idx) => board.running(idx, "restarting"),
+        BatchEvent::Finished(idx, result) => {
+            let name = &enabled_services[idx].name;
+            match result {
+                Ok(None) => board.finish(
+                    idx,
+                    ProgressStatus::Skipped,
+                    &format!("'{}' is not running", name),
+                ),
+                Ok(Some(outcome)) => board.finish(
+                    idx,
+                    ProgressStatus::Success,
+                    &with_warnings(format!("Restarted '{}'", name), outcome.warnings()),
+                ),
+                Err(e) => board.finish(
+                    idx,
+                    ProgressStatus::Failed,
+                    &format!("Failed to restart '{}': {}", name, e),
+                ),
+            }
+        }
+    });
+
+    let skipped = report.count(Option::is_none);
+    let failed = report.failed();
+    let restarted = report.results.len() - skipped - failed;
+
+    if ctx.json {
+        ctx.json(&serde_json::json!({
+            "restarted": restarted,
+            "skipped": skipped,
+            "failed": failed
+        }))?;
+    } else {
+        ctx.info(&format!(
+            "Summary: {} restarted, {} skipped, {} failed",
+            restarted, skipped, failed
+        ));
+    }
+
+    if failed > 0 {
+        Ok(ExitCode::StartFailed)
+    } else {
+        Ok(ExitCode::Success)
+    }
+}
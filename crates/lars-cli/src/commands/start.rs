@@ -1,9 +1,11 @@
 //! Start command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager, Runner};
+use lars_core::{ConfigManager, Runner, ServiceKind, StartOutcome};
 
+use crate::commands::attach;
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
 pub fn run(
@@ -13,9 +15,23 @@ pub fn run(
     ctx: &OutputContext,
 ) -> Result<ExitCode> {
     let service = config.get_service(name)?;
-    let runner = create_runner(service.runner_type)?;
+    // Waited for below, so that the wait can be announced
+    let manager = runners::manager(config.clone()).wait_for_tasks(false);
+    let runner = manager.runner(&service)?;
+
+    // Known before starting, so a task is still run to completion
+    let attach = if attach && !runner.capabilities().attach {
+        ctx.warn(&format!(
+            "Runner '{}' does not support attach; not attaching",
+            service.runner_type
+        ));
+        false
+    } else {
+        attach
+    };
 
-    if runner.is_running(&service)? {
+    let outcome = manager.start(&service)?;
+    if outcome == StartOutcome::AlreadyRunning {
         if ctx.json {
             ctx.json(&serde_json::json!({
                 "status": "already_running",
@@ -34,11 +50,37 @@ pub fn run(
 
     let log_path = config.log_path_for_service(&service.id);
 
-    if let Some(parent) = log_path.parent() {
-        std::fs::create_dir_all(parent)?;
+    for warning in outcome.warnings() {
+        ctx.warn(warning);
     }
 
-    runner.start(&service, &log_path)?;
+    // Tasks run to completion unless the caller wants to watch them
+    if service.kind == ServiceKind::Task && !attach {
+        if !ctx.json {
+            ctx.info(&format!("Running task '{}'...", name));
+        }
+
+        let exit_code = manager.wait_for_task(&service)?;
+
+        if ctx.json {
+            ctx.json(&serde_json::json!({
+                "status": if exit_code == 0 { "completed" } else { "failed" },
+                "name": name,
+                "exit_code": exit_code,
+                "log_path": log_path.to_string_lossy()
+            }))?;
+        } else if exit_code == 0 {
+            ctx.success(&format!("Task '{}' completed", name));
+        } else {
+            ctx.error(&format!("Task '{}' exited with status {}", name, exit_code));
+        }
+
+        return Ok(if exit_code == 0 {
+            ExitCode::Success
+        } else {
+            ExitCode::StartFailed
+        });
+    }
 
     if ctx.json {
         ctx.json(&serde_json::json!({
@@ -67,27 +109,7 @@ fn do_attach(
             ctx.info("Attaching to session...");
         }
 
-        #[cfg(unix)]
-        {
-            use std::os::unix::process::CommandExt;
-            let mut command = std::process::Command::new(&cmd[0]);
-            command.args(&cmd[1..]);
-            let err = command.exec();
-            return Err(anyhow::anyhow!("Failed to exec: {}", err));
-        }
-
-        #[cfg(not(unix))]
-        {
-            let status = std::process::Command::new(&cmd[0])
-                .args(&cmd[1..])
-                .status()?;
-
-            if status.success() {
-                return Ok(ExitCode::Success);
-            } else {
-                return Err(anyhow::anyhow!("Attach command failed"));
-            }
-        }
+        attach::exec(&cmd)
     } else {
         ctx.warn("Runner does not support attach");
         Ok(ExitCode::Success)
@@ -1,14 +1,20 @@
 //! Start-all command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager};
+use lars_core::batch::BatchEvent;
+use lars_core::{ConfigManager, StartOutcome};
 
-use crate::output::OutputContext;
+use crate::output::{OutputContext, ProgressBoard, ProgressStatus};
+use crate::runners;
 use crate::ExitCode;
 
-pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
+pub fn run(config: &ConfigManager, jobs: usize, ctx: &OutputContext) -> Result<ExitCode> {
     let services = config.list_services()?;
-    let enabled_services: Vec<_> = services.into_iter().filter(|s| s.enabled).collect();
+    // Scheduled services are left to the daemon
+    let enabled_services: Vec<_> = services
+        .into_iter()
+        .filter(|s| s.enabled && s.schedule.is_none())
+        .collect();
 
     if enabled_services.is_empty() {
         if ctx.json {
@@ -23,65 +29,38 @@ pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
         return Ok(ExitCode::Success);
     }
 
-    let mut started = 0;
-    let mut skipped = 0;
-    let mut failed = 0;
+    let names = enabled_services.iter().map(|s| s.name.clone()).collect();
+    let mut board = ProgressBoard::new(ctx, names);
 
-    for service in &enabled_services {
-        let runner = match create_runner(service.runner_type) {
-            Ok(r) => r,
-            Err(e) => {
-                if !ctx.json {
-                    ctx.error(&format!("Failed to create runner for '{}': {}", service.name, e));
-                }
-                failed += 1;
-                continue;
-            }
-        };
-
-        // Skip if already running
-        match runner.is_running(service) {
-            Ok(true) => {
-                if !ctx.json {
-                    ctx.info(&format!("Service '{}' is already running", service.name));
-                }
-                skipped += 1;
-                continue;
-            }
-            Ok(false) => {}
-            Err(e) => {
-                if !ctx.json {
-                    ctx.error(&format!("Failed to check status of '{}': {}", service.name, e));
-                }
-                failed += 1;
-                continue;
+    // Tasks count as started once they have completed successfully
+    let manager = runners::manager(config.clone());
+    let report = manager.start_all(&enabled_services, jobs, |event| match event {
+        BatchEvent::Started(idx) => board.running(idx, "starting"),
+        BatchEvent::Finished(idx, result) => {
+            let name = &enabled_services[idx].name;
+            match result {
+                Ok(StartOutcome::AlreadyRunning) => board.finish(
+                    idx,
+                    ProgressStatus::Skipped,
+                    &format!("Service '{}' is already running", name),
+                ),
+                Ok(outcome) => board.finish(
+                    idx,
+                    ProgressStatus::Success,
+                    &with_warnings(format!("Started '{}'", name), outcome.warnings()),
+                ),
+                Err(e) => board.finish(
+                    idx,
+                    ProgressStatus::Failed,
+                    &format!("Failed to start '{}': {}", name, e),
+                ),
             }
         }
+    });
 
-        // Get log path
-        let log_path = config.log_path_for_service(&service.id);
-
-        // Ensure log directory exists
-        if let Some(parent) = log_path.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
-
-        // Start the service
-        match runner.start(service, &log_path) {
-            Ok(_) => {
-                if !ctx.json {
-                    ctx.success(&format!("Started '{}'", service.name));
-                }
-                started += 1;
-            }
-            Err(e) => {
-                if !ctx.json {
-                    ctx.error(&format!("Failed to start '{}': {}", service.name, e));
-                }
-                failed += 1;
-            }
-        }
-    }
+    let skipped = report.count(|o| *o == StartOutcome::AlreadyRunning);
+    let failed = report.failed();
+    let started = report.results.len() - skipped - failed;
 
     if ctx.json {
         ctx.json(&serde_json::json!({
@@ -102,3 +81,12 @@ pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
         Ok(ExitCode::Success)
     }
 }
+
+/// Append hook warnings to a progress message
+pub(crate) fn with_warnings(msg: String, warnings: &[String]) -> String {
+    if warnings.is_empty() {
+        msg
+    } else {
+        format!("{} ({})", msg, warnings.join("; "))
+    }
+}
@@ -0,0 +1,160 @@
+//! Status command implementation
+//!
+//! Shows whether services are running, unhealthy, exited or stopped. With
+//! `--watch` it keeps polling and prints a line for every change, as NDJSON
+//! in JSON mode, so scripts can wait for a stack to come up or notice a
+//! crash without polling `lars list` themselves.
+
+use anyhow::Result;
+use comfy_table::{Cell, Color};
+use lars_core::{
+    ConfigManager, HealthStatus, LarsError, Service, ServiceStatus, StatusChange, StatusReport,
+    StatusTracker,
+};
+use owo_colors::OwoColorize;
+use std::time::Duration;
+
+use crate::output::{OutputContext, TableBuilder};
+use crate::runners;
+use crate::ExitCode;
+
+pub fn run(
+    config: &ConfigManager,
+    names: &[String],
+    all: bool,
+    watch: bool,
+    interval: Duration,
+    ctx: &OutputContext,
+) -> Result<ExitCode> {
+    let mut services = select_services(config, names, all)?;
+    let manager = runners::manager(config.clone());
+
+    if watch {
+        let mut tracker = StatusTracker::new();
+        loop {
+            for change in tracker.poll(&manager, &services) {
+                print_change(&change, ctx)?;
+            }
+            std::thread::sleep(interval);
+            // Pick up added, renamed and disabled services
+            if let Ok(selected) = select_services(config, names, all) {
+                services = selected;
+            }
+        }
+    }
+
+    let state = config.load_state();
+    let reports: Vec<StatusReport> = services
+        .iter()
+        .map(|service| StatusReport::check(&manager, service, &state))
+        .collect();
+
+    if ctx.json {
+        ctx.json(&reports)?;
+        return Ok(ExitCode::Success);
+    }
+
+    if reports.is_empty() {
+        ctx.info("No services configured");
+        return Ok(ExitCode::Success);
+    }
+
+    let mut table = TableBuilder::new(vec!["Name", "Status", "Details"]);
+    for report in &reports {
+        table.add_row(vec![
+            Cell::new(&report.name),
+            status_cell(report.status, ctx),
+            Cell::new(details(report)),
+        ]);
+    }
+    println!("{}", table.build());
+
+    Ok(ExitCode::Success)
+}
+
+/// Named services, or all enabled services (every service with `all`)
+fn select_services(config: &ConfigManager, names: &[String], all: bool) -> Result<Vec<Service>> {
+    let app_config = config.load()?;
+    if names.is_empty() {
+        return Ok(app_config
+            .services
+            .into_iter()
+            .filter(|s| all || s.enabled)
+            .collect());
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            app_config
+                .find_service_by_name(name)
+                .cloned()
+                .ok_or_else(|| LarsError::ServiceNotFound(name.clone()).into())
+        })
+        .collect()
+}
+
+fn details(report: &StatusReport) -> String {
+    if let Some(code) = report.exit_code {
+        return format!("status {}", code);
+    }
+    match &report.health {
+        Some(health) if health.status == HealthStatus::Unhealthy => {
+            health.error.clone().unwrap_or_default()
+        }
+        Some(health) => health
+            .latency_ms
+            .map(|ms| format!("healthy ({:.1} ms)", ms))
+            .unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+fn status_cell(status: ServiceStatus, ctx: &OutputContext) -> Cell {
+    let cell = Cell::new(status.to_string());
+    if ctx.no_color {
+        return cell;
+    }
+    match status {
+        ServiceStatus::Running => cell.fg(Color::Green),
+        ServiceStatus::Unhealthy | ServiceStatus::Exited => cell.fg(Color::Red),
+        ServiceStatus::Stopped => cell,
+    }
+}
+
+/// Print a change as an NDJSON line or as `HH:MM:SS name: old → new`
+fn print_change(change: &StatusChange, ctx: &OutputContext) -> Result<()> {
+    if ctx.json {
+        return ctx.json_line(change);
+    }
+
+    let status = match (ctx.no_color, change.status) {
+        (true, status) => status.to_string(),
+        (false, status @ ServiceStatus::Running) => status.green().to_string(),
+        (false, status @ (ServiceStatus::Unhealthy | ServiceStatus::Exited)) => {
+            status.red().to_string()
+        }
+        (false, status) => status.to_string(),
+    };
+    let transition = match change.previous {
+        Some(previous) => format!("{} → {}", previous, status),
+        None => status,
+    };
+    let detail = match (change.exit_code, &change.error) {
+        (Some(code), _) => format!(" (status {})", code),
+        (None, Some(error)) => format!(" ({})", error),
+        (None, None) if change.previous == Some(change.status) && change.started_at.is_some() => {
+            " (restarted)".to_string()
+        }
+        (None, None) => String::new(),
+    };
+
+    println!(
+        "{} {}: {}{}",
+        change.time.with_timezone(&chrono::Local).format("%H:%M:%S"),
+        change.name,
+        transition,
+        detail
+    );
+    Ok(())
+}
@@ -1,17 +1,17 @@
 //! Stop command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager};
+use lars_core::{ConfigManager, StopOutcome};
 
 use crate::output::OutputContext;
+use crate::runners;
 use crate::ExitCode;
 
 pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<ExitCode> {
     let service = config.get_service(name)?;
-    let runner = create_runner(service.runner_type)?;
+    let outcome = runners::manager(config.clone()).stop(&service)?;
 
-    // Check if running
-    if !runner.is_running(&service)? {
+    if outcome == StopOutcome::NotRunning {
         if ctx.json {
             ctx.json(&serde_json::json!({
                 "status": "not_running",
@@ -23,8 +23,9 @@ pub fn run(config: &ConfigManager, name: &str, ctx: &OutputContext) -> Result<Ex
         return Ok(ExitCode::Success);
     }
 
-    // Stop the service
-    runner.stop(&service)?;
+    for warning in outcome.warnings() {
+        ctx.warn(warning);
+    }
 
     if ctx.json {
         ctx.json(&serde_json::json!({
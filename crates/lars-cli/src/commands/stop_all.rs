@@ -1,12 +1,15 @@
 //! Stop-all command implementation
 
 use anyhow::Result;
-use lars_core::{create_runner, ConfigManager};
+use lars_core::batch::BatchEvent;
+use lars_core::{ConfigManager, StopOutcome};
 
-use crate::output::OutputContext;
+use crate::commands::start_all::with_warnings;
+use crate::output::{OutputContext, ProgressBoard, ProgressStatus};
+use crate::runners;
 use crate::ExitCode;
 
-pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
+pub fn run(config: &ConfigManager, jobs: usize, ctx: &OutputContext) -> Result<ExitCode> {
     let services = config.list_services()?;
 
     if services.is_empty() {
@@ -22,48 +25,33 @@ pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
         return Ok(ExitCode::Success);
     }
 
-    let mut stopped = 0;
-    let mut skipped = 0;
-    let mut failed = 0;
+    let names = services.iter().map(|s| s.name.clone()).collect();
+    let mut board = ProgressBoard::new(ctx, names);
 
-    for service in &services {
-        let runner = match create_runner(service.runner_type) {
-            Ok(r) => r,
-            Err(_) => {
-                skipped += 1;
-                continue;
-            }
-        };
+    let manager = runners::manager(config.clone());
+    let report = manager.stop_all(&services, jobs, |event| match event {
+        BatchEvent::Started(idx) => board.running(idx, "stopping"),
+        BatchEvent::Finished(idx, result) => match result {
+            Ok(StopOutcome::NotRunning) => board.finish(idx, ProgressStatus::Skipped, ""),
+            Ok(outcome) => board.finish(
+                idx,
+                ProgressStatus::Success,
+                &with_warnings(
+                    format!("Stopped '{}'", services[idx].name),
+                    outcome.warnings(),
+                ),
+            ),
+            Err(e) => board.finish(
+                idx,
+                ProgressStatus::Failed,
+                &format!("Failed to stop '{}': {}", services[idx].name, e),
+            ),
+        },
+    });
 
-        // Skip if not running
-        match runner.is_running(service) {
-            Ok(false) => {
-                skipped += 1;
-                continue;
-            }
-            Ok(true) => {}
-            Err(_) => {
-                skipped += 1;
-                continue;
-            }
-        }
-
-        // Stop the service
-        match runner.stop(service) {
-            Ok(_) => {
-                if !ctx.json {
-                    ctx.success(&format!("Stopped '{}'", service.name));
-                }
-                stopped += 1;
-            }
-            Err(e) => {
-                if !ctx.json {
-                    ctx.error(&format!("Failed to stop '{}': {}", service.name, e));
-                }
-                failed += 1;
-            }
-        }
-    }
+    let skipped = report.count(|o| *o == StopOutcome::NotRunning);
+    let failed = report.failed();
+    let stopped = report.results.len() - skipped - failed;
 
     if ctx.json {
         ctx.json(&serde_json::json!({
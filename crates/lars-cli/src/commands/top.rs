@@ -0,0 +1,184 @@
+//! Top command implementation
+//!
+//! Shows the resource usage of running services, refreshed periodically.
+//! The sampling helpers are shared with `list --stats`.
+
+use anyhow::Result;
+use chrono::Utc;
+use comfy_table::Cell;
+use lars_core::duration::format_duration;
+use lars_core::stats::format_bytes;
+use lars_core::{ConfigManager, ProcessStats, ProcessTable, Service};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::output::{OutputContext, TableBuilder};
+use crate::runners;
+use crate::ExitCode;
+
+/// How long CPU usage is measured over when there is no earlier snapshot
+pub const WARMUP: Duration = Duration::from_millis(500);
+
+/// Column headers matching [`stats_cells`]
+pub const STATS_HEADERS: [&str; 5] = ["CPU", "Memory", "FDs", "Threads", "Uptime"];
+
+#[derive(Serialize)]
+struct TopEntry<'a> {
+    name: &'a str,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<&'a ProcessStats>,
+}
+
+/// Root PIDs of the services that are running
+pub fn running_pids(services: &[Service]) -> HashMap<Uuid, u32> {
+    services
+        .iter()
+        .filter_map(|s| {
+            let runner = runners::create_runner(s.runner_type).ok()?;
+            if !runner.capabilities().pid {
+                return None;
+            }
+            let pid = runner.get_pid(s).ok()??;
+            Some((s.id, pid))
+        })
+        .collect()
+}
+
+/// Usage of each running service, measuring CPU since `previous`
+pub fn service_stats(
+    pids: &HashMap<Uuid, u32>,
+    table: Option<&ProcessTable>,
+    previous: Option<&ProcessTable>,
+) -> HashMap<Uuid, ProcessStats> {
+    let Some(table) = table else {
+        return HashMap::new();
+    };
+
+    pids.iter()
+        .filter_map(|(id, pid)| Some((*id, table.stats(*pid, previous)?)))
+        .collect()
+}
+
+/// Usage of each running service, measured over [`WARMUP`]
+pub fn sample(services: &[Service]) -> HashMap<Uuid, ProcessStats> {
+    let pids = running_pids(services);
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+
+    let before = ProcessTable::read();
+    std::thread::sleep(WARMUP);
+    service_stats(&pids, ProcessTable::read().as_ref(), before.as_ref())
+}
+
+/// Table cells for [`STATS_HEADERS`], dashes for services that are not running
+pub fn stats_cells(stats: Option<&ProcessStats>) -> Vec<Cell> {
+    let Some(stats) = stats else {
+        return STATS_HEADERS.iter().map(|_| Cell::new("-")).collect();
+    };
+
+    vec![
+        Cell::new(
+            stats
+                .cpu_percent
+                .map_or("-".to_string(), |cpu| format!("{:.1}%", cpu)),
+        ),
+        Cell::new(format_bytes(stats.rss_bytes)),
+        Cell::new(stats.open_fds),
+        Cell::new(stats.threads),
+        Cell::new(format_duration(Duration::from_secs(stats.uptime_secs))),
+    ]
+}
+
+pub fn run(
+    config: &ConfigManager,
+    all: bool,
+    interval: Duration,
+    iterations: Option<u64>,
+    ctx: &OutputContext,
+) -> Result<ExitCode> {
+    if ProcessTable::read().is_none() {
+        return Err(anyhow::anyhow!(
+            "Resource usage is not available on this platform"
+        ));
+    }
+
+    let clear = !ctx.json && std::io::stdout().is_terminal();
+    let mut previous = ProcessTable::read();
+    let mut wait = WARMUP;
+    let mut shown = 0;
+
+    loop {
+        std::thread::sleep(wait);
+        wait = interval;
+
+        // Reloaded every time, so added and removed services show up
+        let services: Vec<Service> = config
+            .list_services()?
+            .into_iter()
+            .filter(|s| all || s.enabled)
+            .collect();
+        let pids = running_pids(&services);
+        let table = ProcessTable::read();
+        let stats = service_stats(&pids, table.as_ref(), previous.as_ref());
+        previous = table;
+
+        // Busiest first, stopped services last
+        let mut rows: Vec<(&Service, Option<&ProcessStats>)> =
+            services.iter().map(|s| (s, stats.get(&s.id))).collect();
+        rows.sort_by(|a, b| {
+            let cpu = |s: Option<&ProcessStats>| s.map(|s| s.cpu_percent.unwrap_or(0.0));
+            cpu(b.1)
+                .partial_cmp(&cpu(a.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        if ctx.json {
+            let entries: Vec<TopEntry> = rows
+                .iter()
+                .map(|(service, stats)| TopEntry {
+                    name: &service.name,
+                    status: if stats.is_some() {
+                        "running"
+                    } else {
+                        "stopped"
+                    },
+                    stats: *stats,
+                })
+                .collect();
+            ctx.json_line(&serde_json::json!({
+                "time": Utc::now().to_rfc3339(),
+                "services": entries,
+            }))?;
+        } else {
+            let mut headers = vec!["Name", "Status", "PID"];
+            headers.extend(STATS_HEADERS);
+            let mut output = TableBuilder::new(headers);
+            for (service, stats) in &rows {
+                let mut row = vec![
+                    Cell::new(&service.name),
+                    ctx.status_cell(stats.is_some()),
+                    Cell::new(stats.map_or("-".to_string(), |s| s.pid.to_string())),
+                ];
+                row.extend(stats_cells(*stats));
+                output.add_row(row);
+            }
+
+            let mut stdout = std::io::stdout().lock();
+            if clear {
+                write!(stdout, "\x1b[2J\x1b[H")?;
+            }
+            writeln!(stdout, "{}", output.build())?;
+            stdout.flush()?;
+        }
+
+        shown += 1;
+        if iterations.is_some_and(|n| shown >= n) {
+            return Ok(ExitCode::Success);
+        }
+    }
+}
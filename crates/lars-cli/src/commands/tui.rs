@@ -0,0 +1,638 @@
+//! Tui command implementation
+//!
+//! A full-screen dashboard: the services with their status and resource
+//! usage, the log of the selected service, and keys to control it. Services
+//! are queried and controlled through the `Runner` trait and the shared
+//! lifecycle sequences, so it behaves like the equivalent commands with
+//! every runner.
+//!
+//! Status and usage are polled on a background thread, which sends a
+//! snapshot of every service to the render thread; the filter is applied to
+//! the latest snapshot, so typing never waits on tmux or `/proc`.
+
+use anyhow::Result;
+use lars_core::duration::format_duration;
+use lars_core::logs::tail_lines;
+use lars_core::stats::format_bytes;
+use lars_core::{
+    ConfigManager, LogFollower, LogLine, ProcessStats, ProcessTable, Service, ServiceManager,
+    ServiceStatus, StatusReport,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::commands::top;
+use crate::control;
+use crate::output::OutputContext;
+use crate::runners;
+use crate::ExitCode;
+
+/// How long to wait for a key press before redrawing
+const TICK: Duration = Duration::from_millis(250);
+
+/// How often service status and usage are refreshed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Log lines kept for the log pane
+const LOG_LINES: usize = 500;
+
+const HELP: &str =
+    "↑/↓ select  s start  x stop  r restart  e enable/disable  a attach  / filter  q quit";
+
+struct Entry {
+    service: Service,
+    report: StatusReport,
+    stats: Option<ProcessStats>,
+}
+
+/// Sent to the render thread from the background
+enum Update {
+    /// A fresh snapshot of every service
+    Entries(Vec<Entry>),
+    /// The outcome of an action, or a polling error
+    Message(String),
+}
+
+/// Log of the selected service
+#[derive(Default)]
+struct LogPane {
+    service_id: Option<Uuid>,
+    follower: Option<LogFollower>,
+    lines: VecDeque<String>,
+}
+
+impl LogPane {
+    /// Show the log of `service`, starting from its tail
+    fn show(&mut self, config: &ConfigManager, service: Option<&Service>) {
+        if self.service_id == service.map(|s| s.id) {
+            return;
+        }
+        *self = Self::default();
+        let Some(service) = service else {
+            return;
+        };
+
+        let path = config.log_path_for_service(&service.id);
+        self.service_id = Some(service.id);
+        self.follower = Some(LogFollower::new(vec![path.clone()]));
+        for raw in tail_lines(&path, LOG_LINES).unwrap_or_default() {
+            self.push(LogLine::parse(0, &raw).text);
+        }
+    }
+
+    fn poll(&mut self) {
+        let lines = match self.follower.as_mut() {
+            Some(follower) => follower.poll().unwrap_or_default(),
+            None => return,
+        };
+        for line in lines {
+            self.push(line.text);
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() == LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+}
+
+struct App<'a> {
+    config: &'a ConfigManager,
+    color: bool,
+    /// The latest snapshot of every service
+    entries: Vec<Entry>,
+    /// Indexes into `entries` of the services matching the filter
+    visible: Vec<usize>,
+    table: TableState,
+    filter: String,
+    editing_filter: bool,
+    log: LogPane,
+    message: Option<String>,
+    /// Snapshots and outcomes of actions running in the background
+    updates: (Sender<Update>, Receiver<Update>),
+    /// Asks the poller for a snapshot right away
+    wake: Sender<()>,
+}
+
+pub fn run(config: &ConfigManager, ctx: &OutputContext) -> Result<ExitCode> {
+    if ctx.json || !std::io::stdout().is_terminal() {
+        return Err(anyhow::anyhow!("lars tui needs an interactive terminal"));
+    }
+
+    let (wake, woken) = mpsc::channel();
+    let mut app = App::new(config, !ctx.no_color, wake);
+    spawn_poller(config.clone(), app.updates.0.clone(), woken);
+
+    let mut terminal = ratatui::try_init()?;
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+
+    result.map(|_| ExitCode::Success)
+}
+
+/// Poll services, their status and usage in the background.
+///
+/// A snapshot is sent every [`REFRESH_INTERVAL`] and whenever `wake`
+/// receives a request; the thread ends with the app.
+fn spawn_poller(config: ConfigManager, updates: Sender<Update>, wake: Receiver<()>) {
+    std::thread::spawn(move || {
+        let manager = runners::manager(config);
+        let mut previous = ProcessTable::read();
+        loop {
+            let update = match manager.config().list_services() {
+                Ok(services) => Update::Entries(poll(&manager, services, &mut previous)),
+                Err(e) => Update::Message(format!("Failed to load configuration: {}", e)),
+            };
+            if updates.send(update).is_err() {
+                return;
+            }
+
+            match wake.recv_timeout(REFRESH_INTERVAL) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            // Requests made meanwhile are answered by the same snapshot
+            while wake.try_recv().is_ok() {}
+        }
+    });
+}
+
+/// Query the status and usage of `services`
+fn poll(
+    manager: &ServiceManager,
+    services: Vec<Service>,
+    previous: &mut Option<ProcessTable>,
+) -> Vec<Entry> {
+    let state = manager.config().load_state();
+    let pids = top::running_pids(&services);
+    let table = ProcessTable::read();
+    let mut stats = top::service_stats(&pids, table.as_ref(), previous.as_ref());
+    *previous = table;
+
+    services
+        .into_iter()
+        .map(|service| Entry {
+            report: StatusReport::check(manager, &service, &state),
+            stats: stats.remove(&service.id),
+            service,
+        })
+        .collect()
+}
+
+/// Whether a service's name or one of its tags contains `filter`, which
+/// must be lowercase
+fn matches(service: &Service, filter: &str) -> bool {
+    filter.is_empty()
+        || service.name.to_lowercase().contains(filter)
+        || service
+            .tags
+            .iter()
+            .any(|t| t.to_lowercase().contains(filter))
+}
+
+impl<'a> App<'a> {
+    fn new(config: &'a ConfigManager, color: bool, wake: Sender<()>) -> Self {
+        Self {
+            config,
+            color,
+            entries: Vec::new(),
+            visible: Vec::new(),
+            table: TableState::default().with_selected(0),
+            filter: String::new(),
+            editing_filter: false,
+            log: LogPane::default(),
+            message: None,
+            updates: mpsc::channel(),
+            wake,
+        }
+    }
+}
+
+impl App<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            while let Ok(update) = self.updates.1.try_recv() {
+                match update {
+                    Update::Entries(entries) => self.set_entries(entries),
+                    Update::Message(message) => self.message = Some(message),
+                }
+            }
+            self.log.poll();
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(TICK)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.on_key(key, terminal)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Replace the snapshot, keeping the selection
+    fn set_entries(&mut self, entries: Vec<Entry>) {
+        let selected = self.selected().map(|e| e.service.id);
+        self.entries = entries;
+        self.apply_filter(selected);
+    }
+
+    /// Show the entries matching the filter, keeping `selected` selected if
+    /// it is still shown
+    fn apply_filter(&mut self, selected: Option<Uuid>) {
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.entries.len())
+            .filter(|&i| matches(&self.entries[i].service, &filter))
+            .collect();
+
+        let index = selected
+            .and_then(|id| {
+                self.visible
+                    .iter()
+                    .position(|&i| self.entries[i].service.id == id)
+            })
+            .unwrap_or(0);
+        self.table
+            .select((!self.visible.is_empty()).then_some(index));
+        self.show_selected_log();
+    }
+
+    fn refilter(&mut self) {
+        let selected = self.selected().map(|e| e.service.id);
+        self.apply_filter(selected);
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        let index = *self.visible.get(self.table.selected()?)?;
+        self.entries.get(index)
+    }
+
+    fn show_selected_log(&mut self) {
+        let service = self.selected().map(|e| e.service.clone());
+        self.log.show(self.config, service.as_ref());
+    }
+
+    /// Handle a key press, returning false to quit
+    fn on_key(&mut self, key: KeyEvent, terminal: &mut DefaultTerminal) -> Result<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(false);
+        }
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => return Ok(true),
+            }
+            self.refilter();
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return Ok(false),
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.refilter();
+            }
+            KeyCode::Esc => return Ok(false),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table.select_next();
+                if self.table.selected() >= Some(self.visible.len()) {
+                    self.table.select_last();
+                }
+                self.show_selected_log();
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.table.select_previous();
+                self.show_selected_log();
+            }
+            KeyCode::Char('/') => {
+                self.editing_filter = true;
+                self.message = None;
+            }
+            KeyCode::Char('s') => self.act("start"),
+            KeyCode::Char('x') => self.act("stop"),
+            KeyCode::Char('r') => self.act("restart"),
+            KeyCode::Char('e') => self.toggle_enabled(),
+            KeyCode::Char('a') => self.attach(terminal)?,
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    /// Start, stop or restart the selected service in the background
+    fn act(&mut self, action: &'static str) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        let name = entry.service.name.clone();
+        let config = self.config.clone();
+        let updates = self.updates.0.clone();
+        let wake = self.wake.clone();
+
+        self.message = Some(format!("Running {} on '{}'...", action, name));
+        std::thread::spawn(move || {
+            let message =
+                match control::handle(&config, action, serde_json::json!({ "name": name })) {
+                    Ok(outcome) => {
+                        let status = outcome["status"].as_str().unwrap_or(action);
+                        let mut message = format!("'{}' {}", name, status.replace('_', " "));
+                        for warning in outcome["warnings"].as_array().into_iter().flatten() {
+                            message
+                                .push_str(&format!("; {}", warning.as_str().unwrap_or_default()));
+                        }
+                        message
+                    }
+                    Err(e) => format!("Failed to {} '{}': {}", action, name, e.message),
+                };
+            let _ = updates.send(Update::Message(message));
+            let _ = wake.send(());
+        });
+    }
+
+    fn toggle_enabled(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        let name = entry.service.name.clone();
+        let enable = !entry.service.enabled;
+
+        self.message = Some(
+            match self
+                .config
+                .update_service(&name, |service| service.enabled = enable)
+            {
+                Ok(()) if enable => format!("'{}' enabled", name),
+                Ok(()) => format!("'{}' disabled", name),
+                Err(e) => format!("Failed to update '{}': {}", name, e),
+            },
+        );
+        let _ = self.wake.send(());
+    }
+
+    /// Hand the terminal to the runner's attach command until it exits
+    fn attach(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(entry) = self.selected() else {
+            return Ok(());
+        };
+        let service = entry.service.clone();
+        if !entry.report.status.is_running() {
+            self.message = Some(format!("'{}' is not running", service.name));
+            return Ok(());
+        }
+
+        let runner = runners::create_runner(service.runner_type)?;
+        let command = if runner.capabilities().attach {
+            runner.attach_command(&service)?
+        } else {
+            None
+        };
+        let Some(command) = command else {
+            self.message = Some(format!(
+                "Runner '{}' does not support attach",
+                service.runner_type
+            ));
+            return Ok(());
+        };
+
+        ratatui::restore();
+        let status = std::process::Command::new(&command[0])
+            .args(&command[1..])
+            .status();
+        *terminal = ratatui::try_init()?;
+        terminal.clear()?;
+
+        if let Err(e) = status {
+            self.message = Some(format!("Failed to attach: {}", e));
+        }
+        Ok(())
+    }
+
+    fn style(&self, color: Color) -> Style {
+        if self.color {
+            Style::new().fg(color)
+        } else {
+            Style::new()
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [services_area, log_area, status_area] = Layout::vertical([
+            Constraint::Percentage(40),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let rows: Vec<Row> = self
+            .visible
+            .iter()
+            .map(|&i| &self.entries[i])
+            .map(|entry| {
+                let (status, color) = match (entry.report.status, entry.report.exit_code) {
+                    (ServiceStatus::Running, _) => ("running".to_string(), Color::Green),
+                    (ServiceStatus::Unhealthy, _) => ("unhealthy".to_string(), Color::Red),
+                    (ServiceStatus::Exited, Some(code)) => {
+                        (format!("exited ({})", code), Color::Red)
+                    }
+                    (ServiceStatus::Exited | ServiceStatus::Stopped, _) => {
+                        ("stopped".to_string(), Color::DarkGray)
+                    }
+                };
+                let stats = entry.stats.as_ref();
+                let name = if entry.service.enabled {
+                    Cell::new(entry.service.name.as_str())
+                } else {
+                    Cell::new(format!("{} (disabled)", entry.service.name))
+                        .style(Style::new().add_modifier(Modifier::DIM))
+                };
+
+                Row::new(vec![
+                    name,
+                    Cell::new(status).style(self.style(color)),
+                    Cell::new(stats.map_or("-".to_string(), |s| s.pid.to_string())),
+                    Cell::new(stats.map_or("-".to_string(), |s| {
+                        format_duration(Duration::from_secs(s.uptime_secs))
+                    })),
+                    Cell::new(
+                        stats
+                            .and_then(|s| s.cpu_percent)
+                            .map_or("-".to_string(), |cpu| format!("{:.1}%", cpu)),
+                    ),
+                    Cell::new(stats.map_or("-".to_string(), |s| format_bytes(s.rss_bytes))),
+                    Cell::new(entry.service.tags.join(", ")),
+                ])
+            })
+            .collect();
+
+        let mut title = " Services ".to_string();
+        if !self.filter.is_empty() {
+            title.push_str(&format!("(filter: {}) ", self.filter));
+        }
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(7),
+                Constraint::Length(8),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(
+            Row::new(["Name", "Status", "PID", "Uptime", "CPU", "Memory", "Tags"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, services_area, &mut self.table);
+
+        let log_title = match self.selected() {
+            Some(entry) => format!(" Logs: {} ", entry.service.name),
+            None => " Logs ".to_string(),
+        };
+        let visible = log_area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self
+            .log
+            .lines
+            .iter()
+            .skip(self.log.lines.len().saturating_sub(visible))
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(log_title)),
+            log_area,
+        );
+
+        let status = if self.editing_filter {
+            Line::from(vec![
+                Span::raw("/"),
+                Span::raw(self.filter.as_str()),
+                Span::raw("▏"),
+            ])
+        } else {
+            match &self.message {
+                Some(message) => Line::raw(message.as_str()),
+                None => Line::styled(HELP, self.style(Color::DarkGray)),
+            }
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(name: &str, tags: &[&str]) -> Entry {
+        let mut service = Service::new(name.to_string(), "true".to_string());
+        service.tags = tags.iter().map(|t| t.to_string()).collect();
+        Entry {
+            report: StatusReport {
+                name: service.name.clone(),
+                status: ServiceStatus::Stopped,
+                exit_code: None,
+                health: None,
+                started_at: None,
+            },
+            service,
+            stats: None,
+        }
+    }
+
+    fn shown(app: &App) -> Vec<String> {
+        app.visible
+            .iter()
+            .map(|&i| app.entries[i].service.name.clone())
+            .collect()
+    }
+
+    fn selected(app: &App) -> Option<String> {
+        app.selected().map(|e| e.service.name.clone())
+    }
+
+    fn config(temp: &TempDir) -> ConfigManager {
+        ConfigManager::new(temp.path().to_path_buf(), temp.path().join("logs"))
+    }
+
+    #[test]
+    fn test_filter_matches_names_and_tags() {
+        let temp = TempDir::new().unwrap();
+        let config = config(&temp);
+        let mut app = App::new(&config, false, mpsc::channel().0);
+        app.set_entries(vec![
+            entry("api", &["Web"]),
+            entry("worker", &["jobs"]),
+            entry("db", &[]),
+        ]);
+        assert_eq!(shown(&app), ["api", "worker", "db"]);
+
+        app.filter = "W".to_string();
+        app.refilter();
+        assert_eq!(shown(&app), ["api", "worker"]);
+
+        app.filter = "nothing".to_string();
+        app.refilter();
+        assert!(shown(&app).is_empty());
+        assert_eq!(app.table.selected(), None);
+        assert_eq!(selected(&app), None);
+    }
+
+    #[test]
+    fn test_selection_is_kept() {
+        let temp = TempDir::new().unwrap();
+        let config = config(&temp);
+        let mut app = App::new(&config, false, mpsc::channel().0);
+        let entries = vec![entry("api", &[]), entry("worker", &[]), entry("db", &[])];
+        let ids: Vec<Uuid> = entries.iter().map(|e| e.service.id).collect();
+        app.set_entries(entries);
+        app.table.select(Some(1));
+        assert_eq!(selected(&app).as_deref(), Some("worker"));
+
+        // Filtering moves the row but keeps the service selected
+        app.filter = "o".to_string();
+        app.refilter();
+        assert_eq!(shown(&app), ["worker"]);
+        assert_eq!(selected(&app).as_deref(), Some("worker"));
+
+        // So does a new snapshot in a different order
+        app.filter.clear();
+        app.refilter();
+        let mut entries = vec![entry("db", &[]), entry("worker", &[]), entry("api", &[])];
+        for (entry, id) in entries.iter_mut().zip([ids[2], ids[1], ids[0]]) {
+            entry.service.id = id;
+        }
+        app.set_entries(entries);
+        assert_eq!(selected(&app).as_deref(), Some("worker"));
+        assert_eq!(app.table.selected(), Some(1));
+
+        // A selection that is filtered out falls back to the first row
+        app.filter = "a".to_string();
+        app.refilter();
+        assert_eq!(selected(&app).as_deref(), Some("api"));
+    }
+}
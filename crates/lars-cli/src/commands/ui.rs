@@ -0,0 +1,186 @@
+//! Ui command implementation
+//!
+//! Serves a small dashboard on 127.0.0.1 for bringing the stack up without
+//! touching tmux. The page polls `/api/services`, runs actions through the
+//! same methods as the control API and follows logs with server-sent events.
+//!
+//! Requests must name the dashboard's own address in their `Host` header,
+//! and actions must come from the dashboard's own origin, so other web pages
+//! open in the browser cannot drive it.
+
+use anyhow::Result;
+use lars_core::control::RpcError;
+use lars_core::logs::tail_lines;
+use lars_core::{ConfigManager, LogFollower, LogLine};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::control;
+use crate::output::OutputContext;
+use crate::ExitCode;
+
+/// The dashboard page
+const PAGE: &str = include_str!("ui.html");
+
+/// Log lines sent when a log stream is opened
+const LOG_BACKLOG: usize = 200;
+
+/// How often streamed logs are checked for new lines
+const LOG_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often an idle log stream is written to, to notice closed pages
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+pub fn run(config: &ConfigManager, port: u16, ctx: &OutputContext) -> Result<ExitCode> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow::anyhow!("Cannot listen on 127.0.0.1:{}: {}", port, e))?;
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map_or(port, |addr| addr.port());
+    let url = format!("http://127.0.0.1:{}", port);
+
+    if ctx.json {
+        ctx.json_line(&json!({ "event": "listening", "url": url }))?;
+    } else {
+        ctx.success(&format!("Dashboard running at {}", url));
+        ctx.info("Press Ctrl+C to stop");
+    }
+
+    for request in server.incoming_requests() {
+        let config = config.clone();
+        std::thread::spawn(move || {
+            let _ = handle(&config, request, port);
+        });
+    }
+
+    Ok(ExitCode::Success)
+}
+
+fn handle(config: &ConfigManager, request: Request, port: u16) -> std::io::Result<()> {
+    let header = |name: &str| {
+        request
+            .headers()
+            .iter()
+            .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+            .map(|h| h.value.to_string())
+    };
+
+    let allowed = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    let host = header("Host").unwrap_or_default();
+    if !allowed.contains(&host) {
+        return request.respond(error(403, "Unexpected Host header"));
+    }
+
+    let path: Vec<String> = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+    match (request.method(), path.as_slice()) {
+        (Method::Get, []) => {
+            let content_type = Header::from_bytes("Content-Type", "text/html; charset=utf-8")
+                .expect("valid header");
+            request.respond(Response::from_string(PAGE).with_header(content_type))
+        }
+        (Method::Get, ["api", "services"]) => {
+            let result = control::handle(config, "list", json!({ "all": true }));
+            request.respond(api_response(result))
+        }
+        (Method::Get, ["api", "services", name, "logs"]) => {
+            let name = name.to_string();
+            stream_logs(config, request, &name)
+        }
+        (Method::Post, ["api", "services", name, action @ ("start" | "stop" | "restart")]) => {
+            if header("Origin").is_some_and(|origin| origin != format!("http://{}", host)) {
+                return request.respond(error(403, "Cross-origin requests are not allowed"));
+            }
+            let result = control::handle(config, action, json!({ "name": name }));
+            request.respond(api_response(result))
+        }
+        _ => request.respond(error(404, "Not found")),
+    }
+}
+
+/// Send the tail of a service's log, then new lines as they are written
+fn stream_logs(config: &ConfigManager, request: Request, name: &str) -> std::io::Result<()> {
+    let service = match config.get_service(name) {
+        Ok(service) => service,
+        Err(e) => return request.respond(error(404, &e.to_string())),
+    };
+    let log_path = config.log_path_for_service(&service.id);
+
+    // Following starts first, so no line falls between the tail and the
+    // first poll
+    let mut follower = LogFollower::new(vec![log_path.clone()]);
+    let backlog = tail_lines(&log_path, LOG_BACKLOG).unwrap_or_default();
+
+    let mut writer = request.into_writer();
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/event-stream\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n"
+    )?;
+    for raw in &backlog {
+        send_line(&mut writer, &LogLine::parse(0, raw))?;
+    }
+    writer.flush()?;
+
+    let mut last_write = Instant::now();
+    loop {
+        std::thread::sleep(LOG_POLL_INTERVAL);
+
+        let lines = follower.poll().unwrap_or_default();
+        for line in &lines {
+            send_line(&mut writer, line)?;
+        }
+        if !lines.is_empty() || last_write.elapsed() >= KEEPALIVE_INTERVAL {
+            if lines.is_empty() {
+                write!(writer, ": keepalive\n\n")?;
+            }
+            writer.flush()?;
+            last_write = Instant::now();
+        }
+    }
+}
+
+fn send_line(writer: &mut impl Write, line: &LogLine) -> std::io::Result<()> {
+    let data = json!({ "text": line.text, "stream": line.stream, "ts": line.ts });
+    write!(writer, "data: {}\n\n", data)
+}
+
+fn api_response(result: Result<Value, RpcError>) -> Response<std::io::Cursor<Vec<u8>>> {
+    match result {
+        Ok(value) => json_response(200, &value),
+        Err(e) => {
+            let status = match e.code {
+                RpcError::METHOD_NOT_FOUND => 404,
+                RpcError::INVALID_PARAMS => 400,
+                _ => 500,
+            };
+            error(status, &e.message)
+        }
+    }
+}
+
+fn error(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn json_response(status: u16, value: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(content_type)
+}
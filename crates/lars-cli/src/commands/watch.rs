@@ -0,0 +1,135 @@
+//! Watch command implementation
+//!
+//! Runs in the foreground and restarts services when files under their
+//! working directory change.
+
+use anyhow::Result;
+use lars_core::{ConfigManager, Debouncer, LarsError, ServiceWatcher, WatchConfig};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::output::OutputContext;
+use crate::runners;
+use crate::ExitCode;
+
+/// How often pending changes are checked against their debounce delay
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn run(config: &ConfigManager, names: &[String], ctx: &OutputContext) -> Result<ExitCode> {
+    let app_config = config.load()?;
+    let manager = runners::manager(config.clone());
+
+    // Explicitly named services are watched even without a watch
+    // configuration, using the defaults.
+    let services: Vec<_> = if names.is_empty() {
+        app_config
+            .services
+            .into_iter()
+            .filter(|s| s.enabled && s.watch.is_some())
+            .collect()
+    } else {
+        names
+            .iter()
+            .map(|name| {
+                let mut service = app_config
+                    .find_service_by_name(name)
+                    .cloned()
+                    .ok_or_else(|| LarsError::ServiceNotFound(name.clone()))?;
+                service.watch.get_or_insert_with(WatchConfig::default);
+                Ok(service)
+            })
+            .collect::<Result<_>>()?
+    };
+
+    if services.is_empty() {
+        if ctx.json {
+            ctx.json(&serde_json::json!({
+                "error": "nothing_to_watch",
+                "message": "No enabled services have a watch configuration"
+            }))?;
+        } else {
+            ctx.info("No enabled services have a watch configuration");
+            ctx.info("Add one with `lars add --watch <glob>` or name services explicitly");
+        }
+        return Ok(ExitCode::Success);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watchers = Vec::new();
+    let mut debouncers = Vec::new();
+
+    for (idx, service) in services.iter().enumerate() {
+        watchers.push(ServiceWatcher::new(service, idx, tx.clone())?);
+
+        let debounce_ms = service.watch.as_ref().map_or(0, |w| w.debounce_ms);
+        debouncers.push(Debouncer::new(Duration::from_millis(debounce_ms)));
+
+        if !ctx.json {
+            let cwd = service.cwd.as_deref().unwrap_or(std::path::Path::new("."));
+            ctx.info(&format!("Watching '{}' in {}", service.name, cwd.display()));
+        }
+    }
+    drop(tx);
+
+    if !ctx.json {
+        ctx.info("Press Ctrl+C to stop");
+    }
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok((idx, path)) => debouncers[idx].record(path, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        for (idx, debouncer) in debouncers.iter_mut().enumerate() {
+            let Some(path) = debouncer.poll(now) else {
+                continue;
+            };
+
+            let name = &services[idx].name;
+
+            // Reload the definition so edits made while watching take effect.
+            // Stopped services stay stopped.
+            let result = config
+                .get_service(name)
+                .and_then(|service| manager.restart_running(&service));
+
+            if ctx.json {
+                let event = match &result {
+                    Ok(Some(_)) => "restarted",
+                    Ok(None) => "not_running",
+                    Err(_) => "restart_failed",
+                };
+                ctx.json_line(&serde_json::json!({
+                    "event": event,
+                    "name": name,
+                    "path": path.to_string_lossy(),
+                    "error": result.as_ref().err().map(|e| e.to_string()),
+                }))?;
+            } else {
+                match result {
+                    Ok(Some(outcome)) => {
+                        for warning in outcome.warnings() {
+                            ctx.warn(warning);
+                        }
+                        ctx.success(&format!(
+                            "Restarted '{}' ({} changed)",
+                            name,
+                            path.display()
+                        ))
+                    }
+                    Ok(None) => ctx.info(&format!(
+                        "'{}' is not running, not restarting ({} changed)",
+                        name,
+                        path.display()
+                    )),
+                    Err(e) => ctx.error(&format!("Failed to restart '{}': {}", name, e)),
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::Success)
+}
@@ -0,0 +1,186 @@
+//! Methods of the control API served by the daemon
+//!
+//! Each connection to the control socket is answered on its own thread. The
+//! methods run the same lifecycle sequences as the corresponding commands,
+//! so hooks, launch records and events behave identically. `lars ui` calls
+//! them directly for its HTTP API.
+
+use anyhow::Result;
+use chrono::Utc;
+use lars_core::control::{self, RpcError};
+use lars_core::duration::parse_time;
+use lars_core::logs::tail_lines;
+use lars_core::{ConfigManager, EventFilter, Service, StatusReport};
+use serde::Deserialize;
+use serde_json::{json, Value};
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use crate::commands::list;
+use crate::runners;
+
+/// Recorded as the actor of changes made through the control API
+#[cfg(unix)]
+const ACTOR: &str = "control socket";
+
+#[derive(Deserialize)]
+struct ListParams {
+    #[serde(default)]
+    all: bool,
+}
+
+#[derive(Deserialize)]
+struct NameParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct LogsParams {
+    name: String,
+    #[serde(default = "default_log_lines")]
+    lines: usize,
+}
+
+#[derive(Deserialize)]
+struct EventsParams {
+    name: Option<String>,
+    since: Option<String>,
+    #[serde(default = "default_event_limit")]
+    limit: usize,
+}
+
+fn default_log_lines() -> usize {
+    100
+}
+
+fn default_event_limit() -> usize {
+    50
+}
+
+/// Listen on the control socket and answer connections in the background.
+///
+/// Returns the socket path.
+#[cfg(unix)]
+pub fn spawn(config: &ConfigManager) -> Result<PathBuf> {
+    let path = config.control_socket_path();
+    let listener = control::bind(&path)?;
+    let config = config.clone().with_actor(ACTOR);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let config = config.clone();
+            std::thread::spawn(move || {
+                let _ = control::serve(stream, |method, params| handle(&config, method, params));
+            });
+        }
+    });
+
+    Ok(path)
+}
+
+/// Run one method call
+pub fn handle(config: &ConfigManager, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "list" => list(config, control::params(params)?),
+        "start" => start(config, control::params(params)?),
+        "stop" => stop(config, control::params(params)?),
+        "restart" => restart(config, control::params(params)?),
+        "logs" => logs(config, control::params(params)?),
+        "events" => events(config, control::params(params)?),
+        _ => return Err(RpcError::method_not_found(method)),
+    }
+    .map_err(|e| RpcError::failed(format!("{:#}", e)))
+}
+
+fn list(config: &ConfigManager, params: ListParams) -> Result<Value> {
+    let services: Vec<Service> = config
+        .list_services()?
+        .into_iter()
+        .filter(|s| params.all || s.enabled)
+        .collect();
+    let manager = runners::manager(config.clone());
+    let state = config.load_state();
+    let mut listening = list::listening_ports(&services);
+
+    let services: Vec<Value> = services
+        .iter()
+        .map(|s| {
+            let report = StatusReport::check(&manager, s, &state);
+            let pid = manager
+                .runner(s)
+                .ok()
+                .and_then(|r| r.get_pid(s).ok().flatten());
+
+            json!({
+                "id": s.id,
+                "name": s.name,
+                "status": report.status,
+                "enabled": s.enabled,
+                "runner": s.runner_type.to_string(),
+                "kind": s.kind.to_string(),
+                "tags": s.tags,
+                "pid": pid,
+                "exit_code": report.exit_code,
+                "ports": s.ports,
+                "listening": listening.remove(&s.id).unwrap_or_default(),
+                "health": report.health,
+                "command": s.command,
+            })
+        })
+        .collect();
+
+    Ok(Value::Array(services))
+}
+
+fn start(config: &ConfigManager, params: NameParams) -> Result<Value> {
+    let service = config.get_service(&params.name)?;
+
+    // Tasks are not waited for; callers follow them with `list` or `events`
+    let manager = runners::manager(config.clone()).wait_for_tasks(false);
+    let started = manager.start(&service)?;
+    Ok(outcome(&started.to_string(), &service, started.warnings()))
+}
+
+fn stop(config: &ConfigManager, params: NameParams) -> Result<Value> {
+    let service = config.get_service(&params.name)?;
+    let stopped = runners::manager(config.clone()).stop(&service)?;
+    Ok(outcome(&stopped.to_string(), &service, stopped.warnings()))
+}
+
+fn restart(config: &ConfigManager, params: NameParams) -> Result<Value> {
+    let service = config.get_service(&params.name)?;
+    let restarted = runners::manager(config.clone()).restart(&service)?;
+    Ok(outcome("restarted", &service, restarted.warnings()))
+}
+
+fn outcome(status: &str, service: &Service, warnings: &[String]) -> Value {
+    json!({
+        "status": status,
+        "name": service.name,
+        "warnings": warnings,
+    })
+}
+
+fn logs(config: &ConfigManager, params: LogsParams) -> Result<Value> {
+    let service = config.get_service(&params.name)?;
+    let lines = tail_lines(&config.log_path_for_service(&service.id), params.lines)?;
+    Ok(json!({ "name": service.name, "lines": lines }))
+}
+
+fn events(config: &ConfigManager, params: EventsParams) -> Result<Value> {
+    let mut filter = EventFilter {
+        since: params
+            .since
+            .map(|s| parse_time(&s, Utc::now()).map_err(|e| anyhow::anyhow!(e)))
+            .transpose()?,
+        ..Default::default()
+    };
+    if let Some(name) = params.name {
+        match config.get_service(&name) {
+            Ok(service) => filter.service_id = Some(service.id),
+            Err(_) => filter.service = Some(name),
+        }
+    }
+
+    Ok(serde_json::to_value(config.events(&filter, params.limit)?)?)
+}
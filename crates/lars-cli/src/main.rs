@@ -1,11 +1,14 @@
 //! LARS CLI - Local App Runner Service Command Line Interface
 
 use anyhow::Result;
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
 use lars_core::ConfigManager;
 
+mod batch;
 mod commands;
+mod control;
 mod output;
+mod runners;
 
 use commands::*;
 
@@ -52,34 +55,20 @@ struct Cli {
     /// Disable colored output
     #[arg(long, global = true, env = "NO_COLOR")]
     no_color: bool,
+
+    /// Run services with a fake runner keeping them in this file (tests only)
+    #[cfg(debug_assertions)]
+    #[arg(long, global = true, hide = true)]
+    fake_runner: Option<std::path::PathBuf>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Add a new service
     Add {
-        /// The command to run
-        command: String,
-
-        /// Service name (auto-generated if not provided)
-        #[arg(short, long)]
-        name: Option<String>,
-
-        /// Working directory
-        #[arg(short = 'd', long)]
-        workdir: Option<String>,
-
-        /// Environment variables (KEY=VALUE)
-        #[arg(short, long = "env", value_name = "KEY=VALUE")]
-        env: Vec<String>,
-
-        /// Add in disabled state
-        #[arg(long)]
-        disabled: bool,
-
-        /// Runner type
-        #[arg(short, long, default_value = "tmux")]
-        runner: String,
+        #[command(flatten)]
+        args: add::AddArgs,
     },
 
     /// Remove a service
@@ -109,6 +98,53 @@ enum Commands {
         /// Include disabled services
         #[arg(short, long)]
         all: bool,
+
+        /// Show CPU, memory, open files, threads and uptime of running services
+        #[arg(short, long)]
+        stats: bool,
+    },
+
+    /// Show resource usage of running services, refreshed periodically
+    Top {
+        /// Include disabled services
+        #[arg(short, long)]
+        all: bool,
+
+        /// Time between refreshes (e.g. 2s, 500ms)
+        #[arg(
+            short = 'd',
+            long,
+            default_value = "2s",
+            value_parser = lars_core::duration::parse_duration
+        )]
+        interval: std::time::Duration,
+
+        /// Exit after this many refreshes
+        #[arg(short = 'n', long)]
+        iterations: Option<u64>,
+    },
+
+    /// Show whether services are running, unhealthy, exited or stopped
+    Status {
+        /// Services to show (defaults to enabled services)
+        names: Vec<String>,
+
+        /// Include disabled services
+        #[arg(short, long)]
+        all: bool,
+
+        /// Keep running and print a line whenever a status changes
+        #[arg(short, long)]
+        watch: bool,
+
+        /// Time between checks when watching (e.g. 2s, 500ms)
+        #[arg(
+            short = 'd',
+            long,
+            default_value = "1s",
+            value_parser = lars_core::duration::parse_duration
+        )]
+        interval: std::time::Duration,
     },
 
     /// Start a service
@@ -134,10 +170,32 @@ enum Commands {
     },
 
     /// Start all enabled services
-    StartAll,
+    StartAll {
+        #[command(flatten)]
+        jobs: batch::JobsArgs,
+    },
 
     /// Stop all running services
-    StopAll,
+    StopAll {
+        #[command(flatten)]
+        jobs: batch::JobsArgs,
+    },
+
+    /// Restart all running enabled services
+    RestartAll {
+        #[command(flatten)]
+        jobs: batch::JobsArgs,
+    },
+
+    /// Restart running services whose definition changed since they were started
+    Reload {
+        #[command(flatten)]
+        jobs: batch::JobsArgs,
+
+        /// Only report which services would be restarted
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Rename a service
     Rename {
@@ -160,18 +218,67 @@ enum Commands {
         name: String,
     },
 
-    /// View service logs
+    /// View service logs, interleaving several services
+    ///
+    /// Timestamped lines are merged by time. Raw logs have no timestamps, so
+    /// the history of each raw log is shown in one block, placed at the time
+    /// the log was last written.
     Logs {
-        /// Service name
-        name: String,
+        /// Service names
+        #[arg(required_unless_present_any = ["tags", "all"])]
+        names: Vec<String>,
+
+        /// Include services with this tag
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
 
-        /// Follow log output
+        /// Include all services
         #[arg(short, long)]
-        follow: bool,
+        all: bool,
 
-        /// Number of lines to show
+        #[command(flatten)]
+        args: logs::LogsArgs,
+    },
+
+    /// Show the history of service events
+    Events {
+        /// Only events of this service
+        name: Option<String>,
+
+        /// Only events since this time (a duration like 2h, or an RFC 3339 timestamp)
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Number of most recent events to show
         #[arg(short = 'n', long, default_value = "50")]
         lines: usize,
+
+        /// Keep printing new events as they are recorded
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Restart services when files in their working directory change
+    Watch {
+        /// Services to watch (defaults to enabled services with a watch configuration)
+        names: Vec<String>,
+    },
+
+    /// Run scheduled services, rotate logs and serve the control socket in the foreground
+    Daemon {
+        /// Also serve Prometheus metrics at http://ADDR/metrics (a bare port listens on 127.0.0.1)
+        #[arg(long, value_name = "ADDR")]
+        metrics: Option<String>,
+    },
+
+    /// Full-screen terminal dashboard for following and controlling services
+    Tui,
+
+    /// Serve a web dashboard for starting, stopping and following services
+    Ui {
+        /// Port to listen on at 127.0.0.1 (0 picks a free port)
+        #[arg(short, long, default_value_t = 7070)]
+        port: u16,
     },
 
     /// Show or modify configuration
@@ -205,6 +312,17 @@ enum Commands {
         /// Shell to generate completions for
         shell: clap_complete::Shell,
     },
+
+    /// Run a command, capturing its output as structured log records
+    #[command(hide = true)]
+    LogRelay {
+        /// Log file to append to
+        log: std::path::PathBuf,
+
+        /// Command and arguments
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -238,21 +356,45 @@ fn setup_logging(verbose: u8, quiet: bool) {
 
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
 
+    // Keep stdout for command output, which may be JSON
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .init();
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // The relay runs inside a service's session and exits with its status
+    if let Commands::LogRelay { log, command } = &cli.command {
+        match lars_core::relay::run_relay(log, command) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("lars log-relay: {}", e);
+                std::process::exit(127);
+            }
+        }
+    }
 
     setup_logging(cli.verbose, cli.quiet);
 
+    #[cfg(debug_assertions)]
+    if let Some(store) = cli.fake_runner.clone() {
+        runners::use_fake_runner(store);
+    }
+
     // Set up output formatting
     let ctx = output::OutputContext::new(cli.json, cli.no_color, cli.quiet);
 
-    let result = run_command(cli.command, &ctx);
+    // Recorded in the event history as what made each change
+    let actor = matches
+        .subcommand_name()
+        .map(|name| format!("lars {}", name));
+
+    let result = run_command(cli.command, actor, &ctx);
 
     match result {
         Ok(code) => std::process::exit(code.into()),
@@ -263,18 +405,18 @@ fn main() {
     }
 }
 
-fn run_command(command: Commands, ctx: &output::OutputContext) -> Result<ExitCode> {
-    let config = ConfigManager::with_defaults()?;
+fn run_command(
+    command: Commands,
+    actor: Option<String>,
+    ctx: &output::OutputContext,
+) -> Result<ExitCode> {
+    let mut config = ConfigManager::with_defaults()?;
+    if let Some(actor) = actor {
+        config = config.with_actor(actor);
+    }
 
     match command {
-        Commands::Add {
-            command,
-            name,
-            workdir,
-            env,
-            disabled,
-            runner,
-        } => add::run(&config, command, name, workdir, env, disabled, runner, ctx),
+        Commands::Add { args } => add::run(&config, args, ctx),
 
         Commands::Remove { name, force } => remove::run(&config, &name, force, ctx),
 
@@ -282,7 +424,20 @@ fn run_command(command: Commands, ctx: &output::OutputContext) -> Result<ExitCod
 
         Commands::Disable { name } => enable::run(&config, &name, false, ctx),
 
-        Commands::List { all } => list::run(&config, all, ctx),
+        Commands::List { all, stats } => list::run(&config, all, stats, ctx),
+
+        Commands::Top {
+            all,
+            interval,
+            iterations,
+        } => top::run(&config, all, interval, iterations, ctx),
+
+        Commands::Status {
+            names,
+            all,
+            watch,
+            interval,
+        } => status::run(&config, &names, all, watch, interval, ctx),
 
         Commands::Start { name, attach } => start::run(&config, &name, attach, ctx),
 
@@ -290,9 +445,13 @@ fn run_command(command: Commands, ctx: &output::OutputContext) -> Result<ExitCod
 
         Commands::Restart { name } => restart::run(&config, &name, ctx),
 
-        Commands::StartAll => start_all::run(&config, ctx),
+        Commands::StartAll { jobs } => start_all::run(&config, jobs.jobs, ctx),
+
+        Commands::StopAll { jobs } => stop_all::run(&config, jobs.jobs, ctx),
 
-        Commands::StopAll => stop_all::run(&config, ctx),
+        Commands::RestartAll { jobs } => restart_all::run(&config, jobs.jobs, ctx),
+
+        Commands::Reload { jobs, dry_run } => reload::run(&config, jobs.jobs, dry_run, ctx),
 
         Commands::Rename { name, new_name } => rename::run(&config, &name, &new_name, ctx),
 
@@ -301,10 +460,33 @@ fn run_command(command: Commands, ctx: &output::OutputContext) -> Result<ExitCod
         Commands::Attach { name } => attach::run(&config, &name, ctx),
 
         Commands::Logs {
+            names,
+            tags,
+            all,
+            args,
+        } => logs::run(&config, &names, &tags, all, args, ctx),
+
+        Commands::Events {
             name,
+            since,
+            lines,
             follow,
+        } => events::run(
+            &config,
+            name.as_deref(),
+            since.as_deref(),
             lines,
-        } => logs::run(&config, &name, follow, lines, ctx),
+            follow,
+            ctx,
+        ),
+
+        Commands::Watch { names } => watch::run(&config, &names, ctx),
+
+        Commands::Daemon { metrics } => daemon::run(&config, metrics.as_deref(), ctx),
+
+        Commands::Tui => tui::run(&config, ctx),
+
+        Commands::Ui { port } => ui::run(&config, port, ctx),
 
         Commands::Config { action } => match action {
             ConfigAction::Show => config_cmd::show(&config, ctx),
@@ -321,5 +503,7 @@ fn run_command(command: Commands, ctx: &output::OutputContext) -> Result<ExitCod
             completions::run(shell);
             Ok(ExitCode::Success)
         }
+
+        Commands::LogRelay { .. } => unreachable!("the log relay is handled in main"),
     }
 }
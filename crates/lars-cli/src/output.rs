@@ -1,8 +1,10 @@
 //! Output formatting utilities for the CLI
 
 use comfy_table::{Cell, Color};
-use owo_colors::OwoColorize;
+use lars_core::{Event, EventKind, LogRecord, LogStream, ServiceStatus, StatusReport};
+use owo_colors::{AnsiColors, OwoColorize};
 use serde::Serialize;
+use std::io::{IsTerminal, Write};
 
 /// Context for output formatting
 #[derive(Debug, Clone)]
@@ -65,11 +67,7 @@ impl OutputContext {
             return;
         }
 
-        if self.no_color {
-            println!("  {}", msg);
-        } else {
-            println!("  {}", msg);
-        }
+        println!("  {}", msg);
     }
 
     /// Print JSON output
@@ -79,6 +77,56 @@ impl OutputContext {
         Ok(())
     }
 
+    /// Print a single compact JSON line (for NDJSON event streams)
+    pub fn json_line<T: Serialize>(&self, value: &T) -> anyhow::Result<()> {
+        let output = serde_json::to_string(value)?;
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", output)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Format a structured log record as a display line
+    pub fn log_record(&self, record: &LogRecord) -> String {
+        let ts = record
+            .ts
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string();
+        let tag = match record.stream {
+            LogStream::Stdout => "out",
+            LogStream::Stderr => "err",
+        };
+
+        if self.no_color {
+            format!("{} {} {}", ts, tag, record.line)
+        } else if record.stream == LogStream::Stderr {
+            format!("{} {} {}", ts.dimmed(), tag.red(), record.line)
+        } else {
+            format!("{} {} {}", ts.dimmed(), tag.dimmed(), record.line)
+        }
+    }
+
+    /// Format a foreman-style `name |` prefix, padded to `width` and colored
+    /// per service
+    pub fn service_prefix(&self, name: &str, idx: usize, width: usize) -> String {
+        const COLORS: [AnsiColors; 6] = [
+            AnsiColors::Cyan,
+            AnsiColors::Magenta,
+            AnsiColors::Green,
+            AnsiColors::Yellow,
+            AnsiColors::Blue,
+            AnsiColors::BrightRed,
+        ];
+
+        let prefix = format!("{:<width$} |", name, width = width);
+        if self.no_color {
+            prefix
+        } else {
+            prefix.color(COLORS[idx % COLORS.len()]).to_string()
+        }
+    }
+
     /// Create a status cell for tables (running/stopped)
     pub fn status_cell(&self, running: bool) -> Cell {
         if running {
@@ -98,6 +146,50 @@ impl OutputContext {
         }
     }
 
+    /// Create a status cell for a service from its status report
+    pub fn report_cell(&self, report: &StatusReport) -> Cell {
+        match (report.status, report.exit_code) {
+            (ServiceStatus::Exited, Some(code)) => self.exited_cell(code),
+            (ServiceStatus::Unhealthy, _) => {
+                let cell = Cell::new("unhealthy");
+                if self.no_color {
+                    cell
+                } else {
+                    cell.fg(Color::Red)
+                }
+            }
+            (status, _) => self.status_cell(status.is_running()),
+        }
+    }
+
+    /// Create a status cell for a service that exited on its own
+    pub fn exited_cell(&self, code: i32) -> Cell {
+        let cell = Cell::new(format!("exited ({})", code));
+        if self.no_color {
+            cell
+        } else if code == 0 {
+            cell.fg(Color::Green)
+        } else {
+            cell.fg(Color::Red)
+        }
+    }
+
+    /// Create a cell for the kind of a recorded event
+    pub fn event_cell(&self, event: &Event) -> Cell {
+        let cell = Cell::new(event.kind.to_string());
+        if self.no_color {
+            return cell;
+        }
+
+        match event.kind {
+            EventKind::Started | EventKind::Healthy => cell.fg(Color::Green),
+            EventKind::Failed | EventKind::Unhealthy => cell.fg(Color::Red),
+            EventKind::Exited if event.exit_code != Some(0) => cell.fg(Color::Red),
+            EventKind::Stopped | EventKind::Exited => cell.fg(Color::Yellow),
+            _ => cell,
+        }
+    }
+
     /// Create an enabled cell for tables
     pub fn enabled_cell(&self, enabled: bool) -> Cell {
         if enabled {
@@ -178,3 +270,143 @@ impl TableBuilder {
         self.table
     }
 }
+
+/// Outcome of one entry on a [`ProgressBoard`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStatus {
+    Success,
+    Skipped,
+    Failed,
+}
+
+/// Per-service progress display for batch commands.
+///
+/// On an interactive terminal every service gets its own line which is redrawn
+/// in place as work starts and finishes. Otherwise only completion messages are
+/// printed, one per line, in completion order. Nothing is printed in JSON mode.
+pub struct ProgressBoard<'a> {
+    ctx: &'a OutputContext,
+    live: bool,
+    width: usize,
+    names: Vec<String>,
+    lines: Vec<String>,
+    drawn: bool,
+}
+
+impl<'a> ProgressBoard<'a> {
+    pub fn new(ctx: &'a OutputContext, names: Vec<String>) -> Self {
+        let live = !ctx.json && !ctx.quiet && std::io::stdout().is_terminal();
+        let width = match ratatui::crossterm::terminal::size() {
+            Ok((w, _)) if w > 0 => w as usize,
+            _ => 80,
+        };
+        let lines = names.iter().map(|n| format!("  {} (pending)", n)).collect();
+
+        Self {
+            ctx,
+            live,
+            width,
+            names,
+            lines,
+            drawn: false,
+        }
+    }
+
+    /// Mark an entry as in progress, e.g. `board.running(0, "starting")`
+    pub fn running(&mut self, idx: usize, label: &str) {
+        if !self.live {
+            return;
+        }
+
+        let marker = if self.ctx.no_color {
+            "~".to_string()
+        } else {
+            "~".cyan().to_string()
+        };
+        self.lines[idx] = format!("{} {} ({}...)", marker, self.names[idx], label);
+        self.draw();
+    }
+
+    /// Mark an entry as finished with the given status and message.
+    ///
+    /// An empty message on a skipped entry is not printed outside live mode.
+    pub fn finish(&mut self, idx: usize, status: ProgressStatus, msg: &str) {
+        if self.ctx.json {
+            return;
+        }
+
+        if !self.live {
+            match status {
+                ProgressStatus::Success => self.ctx.success(msg),
+                ProgressStatus::Skipped if msg.is_empty() => {}
+                ProgressStatus::Skipped => self.ctx.info(msg),
+                ProgressStatus::Failed => self.ctx.error(msg),
+            }
+            return;
+        }
+
+        let msg = if msg.is_empty() {
+            format!("{} (skipped)", self.names[idx])
+        } else {
+            msg.to_string()
+        };
+        let marker = match (status, self.ctx.no_color) {
+            (ProgressStatus::Success, true) => "✓".to_string(),
+            (ProgressStatus::Success, false) => "✓".green().to_string(),
+            (ProgressStatus::Skipped, true) => "-".to_string(),
+            (ProgressStatus::Skipped, false) => "-".dimmed().to_string(),
+            (ProgressStatus::Failed, true) => "✗".to_string(),
+            (ProgressStatus::Failed, false) => "✗".red().to_string(),
+        };
+        self.lines[idx] = format!("{} {}", marker, msg);
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        let mut stdout = std::io::stdout().lock();
+
+        // Move back to the first line of the board and rewrite it
+        if self.drawn && !self.lines.is_empty() {
+            let _ = write!(stdout, "\x1b[{}A", self.lines.len());
+        }
+
+        for line in &self.lines {
+            let _ = writeln!(stdout, "\x1b[2K{}", truncate_visible(line, self.width));
+        }
+
+        let _ = stdout.flush();
+        self.drawn = true;
+    }
+}
+
+/// Truncate a line to `width` visible characters, ignoring ANSI escape codes,
+/// so that redrawn lines never wrap and throw off cursor movement.
+fn truncate_visible(line: &str, width: usize) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut visible = 0;
+    let mut in_escape = false;
+    let mut truncated = false;
+
+    for c in line.chars() {
+        if in_escape {
+            out.push(c);
+            if c.is_ascii_alphabetic() {
+                in_escape = false;
+            }
+        } else if c == '\x1b' {
+            out.push(c);
+            in_escape = true;
+        } else if visible + 1 < width {
+            out.push(c);
+            visible += 1;
+        } else {
+            truncated = true;
+        }
+    }
+
+    if truncated {
+        out.push_str("\x1b[0m");
+    }
+
+    out
+}
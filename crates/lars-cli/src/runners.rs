@@ -0,0 +1,46 @@
+//! Runners used by the commands
+//!
+//! Debug builds accept a hidden `--fake-runner <FILE>` option that replaces
+//! every runner with a [`lars_core::testing::FakeRunner`] keeping its
+//! services in that file, so the CLI tests can run whole commands without
+//! tmux.
+
+#[cfg(debug_assertions)]
+use lars_core::testing::FakeRunner;
+use lars_core::{ConfigManager, Result, Runner, RunnerType, ServiceManager};
+#[cfg(debug_assertions)]
+use std::path::PathBuf;
+#[cfg(debug_assertions)]
+use std::sync::{Arc, OnceLock};
+
+/// File the fake runner keeps its services in, when one replaces the runners
+#[cfg(debug_assertions)]
+static FAKE_RUNNER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Run every service with a fake runner keeping its services in `store`
+#[cfg(debug_assertions)]
+pub fn use_fake_runner(store: PathBuf) {
+    let _ = FAKE_RUNNER.set(store);
+}
+
+/// Create a runner for the specified type
+pub fn create_runner(runner_type: RunnerType) -> Result<Box<dyn Runner>> {
+    #[cfg(debug_assertions)]
+    if let Some(store) = FAKE_RUNNER.get() {
+        return Ok(Box::new(FakeRunner::persistent(store)));
+    }
+
+    lars_core::create_runner(runner_type)
+}
+
+/// Create a service manager running services with the commands' runners
+pub fn manager(config: ConfigManager) -> ServiceManager {
+    let manager = ServiceManager::new(config);
+
+    #[cfg(debug_assertions)]
+    if let Some(store) = FAKE_RUNNER.get() {
+        return manager.with_runner(Arc::new(FakeRunner::persistent(store)));
+    }
+
+    manager
+}
@@ -1,18 +1,44 @@
 //! Integration tests for the LARS CLI
 
+use assert_cmd::cargo::cargo_bin_cmd;
 use assert_cmd::Command;
 use predicates::prelude::*;
+use std::path::PathBuf;
 use tempfile::TempDir;
 
 fn lars_cmd(temp: &TempDir) -> Command {
-    let mut cmd = Command::cargo_bin("lars").unwrap();
+    let mut cmd = cargo_bin_cmd!("lars");
     cmd.env("LARS_CONFIG_HOME", temp.path());
     cmd
 }
 
+/// `lars` running services with an in-memory runner kept in `temp` instead
+/// of tmux
+fn fake_lars_cmd(temp: &TempDir) -> Command {
+    let mut cmd = lars_cmd(temp);
+    cmd.arg("--fake-runner").arg(temp.path().join("runner.json"));
+    cmd
+}
+
+/// Path of a service's log, creating the log directory so it can be written
+fn service_log_path(temp: &TempDir, name: &str) -> PathBuf {
+    let config = std::fs::read_to_string(temp.path().join("config.json")).unwrap();
+    let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+    let service = config["services"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["name"] == name)
+        .unwrap();
+
+    let log_dir = temp.path().join("logs");
+    std::fs::create_dir_all(&log_dir).unwrap();
+    log_dir.join(format!("{}.log", service["id"].as_str().unwrap()))
+}
+
 #[test]
 fn test_help() {
-    let mut cmd = Command::cargo_bin("lars").unwrap();
+    let mut cmd = cargo_bin_cmd!("lars");
     cmd.arg("--help").assert().success().stdout(
         predicate::str::contains("Local App Runner")
             .and(predicate::str::contains("add"))
@@ -23,7 +49,7 @@ fn test_help() {
 
 #[test]
 fn test_version() {
-    let mut cmd = Command::cargo_bin("lars").unwrap();
+    let mut cmd = cargo_bin_cmd!("lars");
     cmd.arg("--version")
         .assert()
         .success()
@@ -93,6 +119,30 @@ fn test_add_duplicate_fails() {
         .stderr(predicate::str::contains("already exists"));
 }
 
+#[test]
+fn test_add_unavailable_runner_warns() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test", "--runner", "screen"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added service 'test'"))
+        .stderr(predicate::str::contains(
+            "won't start until the runner is available",
+        ))
+        .stderr(predicate::str::contains(
+            "Runner 'screen' does not support: attach, pid",
+        ));
+
+    // Doctor reports the runner and the services using it
+    lars_cmd(&temp)
+        .args(["doctor", "--json"])
+        .assert()
+        .stdout(predicate::str::contains("screen runner"))
+        .stdout(predicate::str::contains("(used by test)"));
+}
+
 #[test]
 fn test_add_invalid_name_fails() {
     let temp = TempDir::new().unwrap();
@@ -305,7 +355,7 @@ fn test_export_import() {
 
 #[test]
 fn test_completions() {
-    let mut cmd = Command::cargo_bin("lars").unwrap();
+    let mut cmd = cargo_bin_cmd!("lars");
     cmd.args(["completions", "bash"])
         .assert()
         .success()
@@ -374,3 +424,1418 @@ fn test_add_with_workdir() {
         .success()
         .stdout(predicate::str::contains("\"/tmp\""));
 }
+
+#[test]
+fn test_start_all_no_enabled_services() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test", "--disabled"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["start-all", "--jobs", "2", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"started\": 0")
+                .and(predicate::str::contains("\"skipped\": 0"))
+                .and(predicate::str::contains("\"failed\": 0")),
+        );
+}
+
+#[test]
+fn test_stop_all_parallel_skips_stopped_services() {
+    let temp = TempDir::new().unwrap();
+
+    for name in ["one", "two", "three"] {
+        lars_cmd(&temp)
+            .args(["add", "echo hello", "--name", name])
+            .assert()
+            .success();
+    }
+
+    lars_cmd(&temp)
+        .args(["stop-all", "-j", "3", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"stopped\": 0")
+                .and(predicate::str::contains("\"skipped\": 3"))
+                .and(predicate::str::contains("\"failed\": 0")),
+        );
+}
+
+#[test]
+fn test_start_all_rejects_zero_jobs() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["start-all", "--jobs", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--jobs"));
+}
+
+#[test]
+fn test_restart_all_no_enabled_services() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["restart-all", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"restarted\": 0")
+                .and(predicate::str::contains("\"failed\": 0")),
+        );
+}
+
+#[test]
+fn test_restart_all_skips_stopped_services() {
+    let temp = TempDir::new().unwrap();
+
+    for name in ["one", "two"] {
+        lars_cmd(&temp)
+            .args(["add", "echo hello", "--name", name])
+            .assert()
+            .success();
+    }
+
+    lars_cmd(&temp)
+        .args(["restart-all", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"restarted\": 0")
+                .and(predicate::str::contains("\"skipped\": 2"))
+                .and(predicate::str::contains("\"failed\": 0")),
+        );
+}
+
+#[test]
+fn test_reload_skips_stopped_services() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["reload", "--dry-run", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"dry_run\": true")
+                .and(predicate::str::contains("\"services\": []")),
+        );
+}
+
+#[test]
+fn test_reload_reports_unresolvable_env_per_service() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "serve", "--name", "web", "-p", "http"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "serve", "--name", "api"])
+        .args(["-e", "WEB=localhost:${web.ports.http}"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "serve", "--name", "db", "-p", "sql"])
+        .assert()
+        .success();
+
+    fake_lars_cmd(&temp)
+        .args(["start-all", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"started\": 3"));
+    fake_lars_cmd(&temp)
+        .args(["remove", "web"])
+        .assert()
+        .success();
+
+    // api can no longer be resolved, but db is still compared, and nothing
+    // is written during a dry run
+    let state_path = temp.path().join("state.json");
+    let state = std::fs::read_to_string(&state_path).unwrap();
+    fake_lars_cmd(&temp)
+        .args(["reload", "--dry-run", "--json"])
+        .assert()
+        .code(21)
+        .stdout(
+            predicate::str::contains("\"status\": \"failed\"")
+                .and(predicate::str::contains("Service not found: web"))
+                .and(predicate::str::contains("\"unchanged\": 1"))
+                .and(predicate::str::contains("\"failed\": 1")),
+        );
+    assert_eq!(std::fs::read_to_string(&state_path).unwrap(), state);
+}
+
+#[test]
+fn test_start_stop_restart() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "serve", "--name", "api"])
+        .assert()
+        .success();
+
+    fake_lars_cmd(&temp)
+        .args(["start", "api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Started service 'api'"));
+
+    fake_lars_cmd(&temp)
+        .args(["status", "api", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"status\": \"running\""));
+
+    fake_lars_cmd(&temp)
+        .args(["restart", "api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restarted service 'api'"));
+
+    fake_lars_cmd(&temp)
+        .args(["stop", "api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Stopped service 'api'"));
+
+    fake_lars_cmd(&temp)
+        .args(["status", "api", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"status\": \"stopped\""));
+}
+
+#[test]
+fn test_start_all_and_stop_all() {
+    let temp = TempDir::new().unwrap();
+
+    for name in ["one", "two", "three"] {
+        lars_cmd(&temp)
+            .args(["add", "serve", "--name", name])
+            .assert()
+            .success();
+    }
+
+    fake_lars_cmd(&temp)
+        .args(["start", "one"])
+        .assert()
+        .success();
+
+    fake_lars_cmd(&temp)
+        .args(["start-all", "--jobs", "2", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"started\": 2")
+                .and(predicate::str::contains("\"skipped\": 1"))
+                .and(predicate::str::contains("\"failed\": 0")),
+        );
+
+    fake_lars_cmd(&temp)
+        .args(["stop-all", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"stopped\": 3")
+                .and(predicate::str::contains("\"failed\": 0")),
+        );
+}
+
+#[test]
+fn test_add_with_watch() {
+    let temp = TempDir::new().unwrap();
+    let workdir = temp.path().to_str().unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "go run .",
+            "--name",
+            "api",
+            "-d",
+            workdir,
+            "--watch",
+            "**/*.go",
+            "--watch-ignore",
+            "**/*_test.go",
+        ])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["inspect", "api", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("**/*.go")
+                .and(predicate::str::contains("**/*_test.go"))
+                .and(predicate::str::contains("debounce_ms")),
+        );
+}
+
+#[test]
+fn test_add_watch_requires_workdir() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "go run .", "--name", "api", "--watch", "**/*.go"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("working directory"));
+}
+
+#[test]
+fn test_watch_nothing_to_watch() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["watch"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No enabled services have a watch configuration",
+        ));
+}
+
+#[test]
+fn test_watch_leaves_stopped_service_stopped() {
+    use std::io::{BufRead, BufReader};
+
+    let temp = TempDir::new().unwrap();
+    let workdir = temp.path().join("src");
+    std::fs::create_dir(&workdir).unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "sleep 60",
+            "--name",
+            "api",
+            "-d",
+            workdir.to_str().unwrap(),
+            "--watch",
+            "*.txt",
+        ])
+        .assert()
+        .success();
+
+    let mut watch = std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+        .env("LARS_CONFIG_HOME", temp.path())
+        .args(["--json", "watch"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Keep changing the file until the watcher has picked it up
+    let changed = workdir.join("changed.txt");
+    std::thread::spawn(move || {
+        for i in 0..50 {
+            let _ = std::fs::write(&changed, i.to_string());
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+    });
+
+    let mut line = String::new();
+    BufReader::new(watch.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+
+    let event: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(event["event"], "not_running");
+    assert_eq!(event["name"], "api");
+}
+
+#[test]
+fn test_add_task_with_hooks() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "npm run migrate",
+            "--name",
+            "migrate",
+            "--kind",
+            "task",
+            "--pre-start",
+            "npm ci",
+            "--post-stop",
+            "rm -rf tmp",
+        ])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["inspect", "migrate", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"kind\": \"task\"")
+                .and(predicate::str::contains("\"pre_start\""))
+                .and(predicate::str::contains("npm ci"))
+                .and(predicate::str::contains("\"post_stop\"")),
+        );
+
+    lars_cmd(&temp)
+        .args(["list", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"kind\": \"task\""));
+}
+
+#[test]
+fn test_add_invalid_kind_fails() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test", "--kind", "daemon"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid service kind"));
+}
+
+#[test]
+fn test_add_with_schedule() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "./sync.sh",
+            "--name",
+            "sync",
+            "--kind",
+            "task",
+            "--schedule",
+            "*/15 * * * *",
+            "--overlap",
+            "queue",
+        ])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["inspect", "sync", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("*/15 * * * *")
+                .and(predicate::str::contains("\"overlap\": \"queue\"")),
+        );
+
+    lars_cmd(&temp)
+        .args(["list", "--json"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\"schedule\": \"*/15 * * * *\"")
+                .and(predicate::str::contains("\"next_run\"")),
+        );
+
+    lars_cmd(&temp)
+        .args(["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Next run"));
+}
+
+#[test]
+fn test_add_invalid_schedule_fails() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "./sync.sh",
+            "--name",
+            "sync",
+            "--schedule",
+            "61 * * * *",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("out of range"));
+
+    lars_cmd(&temp)
+        .args(["add", "./sync.sh", "--name", "sync", "--overlap", "kill"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_start_all_skips_scheduled() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "./sync.sh",
+            "--name",
+            "sync",
+            "--schedule",
+            "@hourly",
+        ])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["start-all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No enabled services to start"));
+}
+
+#[test]
+fn test_add_with_log_mode() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "echo hello",
+            "--name",
+            "test",
+            "--log-mode",
+            "append",
+        ])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["inspect", "test", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"log_mode\": \"append\""));
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "echo hello",
+            "--name",
+            "other",
+            "--log-mode",
+            "rotate",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid log mode"));
+}
+
+#[test]
+fn test_logs_previous_without_archive() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["logs", "test", "--previous", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("no_logs"));
+
+    lars_cmd(&temp)
+        .args(["logs", "test", "--previous", "--follow"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_logs_previous_shows_last_run() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "test"])
+        .assert()
+        .success();
+    let log = service_log_path(&temp, "test");
+
+    std::fs::write(&log, "first run\n").unwrap();
+    fake_lars_cmd(&temp)
+        .args(["start", "test"])
+        .assert()
+        .success();
+    std::fs::write(&log, "second run\n").unwrap();
+    fake_lars_cmd(&temp)
+        .args(["restart", "test"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["logs", "test", "--previous"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("second run").and(predicate::str::contains("first").not()),
+        );
+}
+
+#[test]
+fn test_config_set_log_rotation() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["config", "set", "log_retain", "2"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["config", "show", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"log_retain\": 2"));
+
+    lars_cmd(&temp)
+        .args(["config", "set", "log_max_size_mb", "big"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_logs_filters_need_structured_logs() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "raw"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["logs", "raw", "--since", "10m"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("timestamped logs"));
+
+    lars_cmd(&temp)
+        .args([
+            "add",
+            "echo hello",
+            "--name",
+            "structured",
+            "--log-format",
+            "json",
+        ])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["logs", "structured", "--since", "yesterday"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid time"));
+}
+
+#[test]
+fn test_logs_places_raw_history_when_written() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo api", "--name", "api"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "echo web", "--name", "web", "--log-format", "json"])
+        .assert()
+        .success();
+
+    let now = chrono::Utc::now();
+    let record = |ago: i64, line: &str| {
+        let ts = now - chrono::Duration::minutes(ago);
+        format!(
+            "{{\"ts\":\"{}\",\"stream\":\"stdout\",\"line\":\"{}\"}}\n",
+            ts.to_rfc3339(),
+            line
+        )
+    };
+    let web = record(120, "web early") + &record(1, "web late");
+    std::fs::write(service_log_path(&temp, "web"), web).unwrap();
+
+    // The raw log was last written an hour ago
+    let api = service_log_path(&temp, "api");
+    std::fs::write(&api, "api first\napi second\n").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&api)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3600))
+        .unwrap();
+
+    let output = lars_cmd(&temp)
+        .args(["logs", "api", "web", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let logs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let lines: Vec<&str> = logs["lines"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| l["line"].as_str().unwrap())
+        .collect();
+    assert_eq!(lines, ["web early", "api first", "api second", "web late"]);
+}
+
+#[test]
+fn test_log_relay_records_streams() {
+    let temp = TempDir::new().unwrap();
+    let log = temp.path().join("relay.log");
+
+    lars_cmd(&temp)
+        .args(["log-relay", log.to_str().unwrap(), "--"])
+        .args(["sh", "-c", "echo out; echo err >&2; exit 4"])
+        .assert()
+        .code(4);
+
+    let records: Vec<serde_json::Value> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert!(records
+        .iter()
+        .any(|r| r["stream"] == "stdout" && r["line"] == "out"));
+    assert!(records
+        .iter()
+        .any(|r| r["stream"] == "stderr" && r["line"] == "err"));
+}
+
+#[test]
+fn test_logs_multiple_services_by_tag() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo api", "--name", "api", "--tag", "web"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "echo worker", "--name", "worker", "-t", "web"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "echo db", "--name", "db"])
+        .assert()
+        .success();
+
+    for name in ["api", "worker", "db"] {
+        let log = format!("hello from {}\n", name);
+        std::fs::write(service_log_path(&temp, name), log).unwrap();
+    }
+
+    let output = lars_cmd(&temp)
+        .args(["logs", "--tag", "web", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let logs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(logs["services"], serde_json::json!(["api", "worker"]));
+    assert_eq!(logs["lines"].as_array().unwrap().len(), 2);
+
+    lars_cmd(&temp)
+        .args(["--no-color", "logs", "api", "db"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("api | hello from api")
+                .and(predicate::str::contains("db  | hello from db")),
+        );
+
+    lars_cmd(&temp)
+        .args(["logs", "--tag", "missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No services are tagged missing"));
+
+    lars_cmd(&temp).args(["logs"]).assert().failure();
+}
+
+#[test]
+fn test_logs_search_and_fields() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    let log: String = (1..=10)
+        .map(|i| {
+            let level = if i == 5 { "error" } else { "info" };
+            format!("{{\"level\":\"{}\",\"msg\":\"request {}\"}}\n", level, i)
+        })
+        .collect();
+    std::fs::write(service_log_path(&temp, "api"), log).unwrap();
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "--grep", "request (1|9)\"", "-C", "1"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("request 2\"}\n--\n")
+                .and(predicate::str::contains("request 10")),
+        );
+
+    lars_cmd(&temp)
+        .args([
+            "logs",
+            "api",
+            "--where",
+            "level=error",
+            "--fields",
+            "msg,level",
+        ])
+        .assert()
+        .success()
+        .stdout("request 5 error\n");
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "--where", "level"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid log query: condition 'level' must be FIELD=VALUE",
+        ));
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "--grep", "("])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid log query: regex parse error",
+        ));
+}
+
+#[test]
+fn test_logs_context_reads_past_invalid_utf8() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    std::fs::write(
+        service_log_path(&temp, "api"),
+        b"first\nbinary \xff\xfe\nlast match\n",
+    )
+    .unwrap();
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "--grep", "match", "-C", "1"])
+        .assert()
+        .success()
+        .stdout("binary \u{FFFD}\u{FFFD}\nlast match\n");
+}
+
+#[test]
+fn test_logs_tail_lines_and_bytes() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    let log: String = (1..=1000).map(|i| format!("line {}\n", i)).collect();
+    std::fs::write(service_log_path(&temp, "api"), log).unwrap();
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "-n", "2"])
+        .assert()
+        .success()
+        .stdout("line 999\nline 1000\n");
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "-c", "14"])
+        .assert()
+        .success()
+        .stdout("999\nline 1000\n");
+
+    let output = lars_cmd(&temp)
+        .args(["logs", "api", "-n", "3", "--json"])
+        .output()
+        .unwrap();
+    let logs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        logs["lines"],
+        serde_json::json!(["line 998", "line 999", "line 1000"])
+    );
+}
+
+#[test]
+fn test_logs_follow_until_exit() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    lars_cmd(&temp)
+        .args(["logs", "api", "--until-exit"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--follow"));
+
+    std::fs::write(service_log_path(&temp, "api"), "first\nsecond\n").unwrap();
+
+    // The service is not running, so following ends after the history
+    let output = lars_cmd(&temp)
+        .args(["logs", "api", "-f", "--until-exit", "--json"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "line");
+    assert_eq!(events[0]["service"], "api");
+    assert_eq!(events[1]["line"], "second");
+}
+
+#[test]
+fn test_logs_follow_shows_restarts_and_stops() {
+    use std::io::{BufRead, BufReader};
+
+    let temp = TempDir::new().unwrap();
+    let store = temp.path().join("runner.json");
+
+    lars_cmd(&temp)
+        .args(["add", "serve", "--name", "api"])
+        .assert()
+        .success();
+    fake_lars_cmd(&temp)
+        .args(["start", "api"])
+        .assert()
+        .success();
+
+    let mut follow = std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+        .env("LARS_CONFIG_HOME", temp.path())
+        .arg("--fake-runner")
+        .arg(&store)
+        .args(["--json", "logs", "api", "-f", "--until-exit"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut reader = BufReader::new(follow.stdout.take().unwrap());
+    let mut next = || {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str::<serde_json::Value>(&line).unwrap()
+    };
+    // Give the follower time to note that the service is running
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+
+    fake_lars_cmd(&temp)
+        .args(["restart", "api"])
+        .assert()
+        .success();
+    let started = next();
+    assert_eq!(started["event"], "started");
+    assert_eq!(started["service"], "api");
+
+    fake_lars_cmd(&temp)
+        .args(["stop", "api"])
+        .assert()
+        .success();
+    let exited = next();
+    assert_eq!(exited["event"], "exited");
+    assert_eq!(exited["exit_code"], serde_json::Value::Null);
+
+    // Nothing is left running, so following ends
+    assert!(follow.wait().unwrap().success());
+}
+
+#[test]
+fn test_events_history() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["rename", "api", "web"])
+        .assert()
+        .success();
+    lars_cmd(&temp).args(["disable", "web"]).assert().success();
+    lars_cmd(&temp)
+        .args(["config", "set", "log_retain", "3"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["remove", "web", "-f"])
+        .assert()
+        .success();
+
+    let output = lars_cmd(&temp).args(["events", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let events: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let kinds: Vec<&str> = events.iter().map(|e| e["kind"].as_str().unwrap()).collect();
+    assert_eq!(
+        kinds,
+        ["added", "renamed", "disabled", "configured", "removed"]
+    );
+    assert_eq!(events[1]["detail"], "from 'api'");
+    assert_eq!(events[1]["actor"], "lars rename");
+    assert_eq!(events[3]["detail"], "log_retain = 3");
+
+    // Removed services are looked up by the name they had
+    let output = lars_cmd(&temp)
+        .args(["events", "web", "--json", "-n", "1"])
+        .output()
+        .unwrap();
+    let events: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["kind"], "removed");
+
+    lars_cmd(&temp)
+        .args(["--no-color", "events", "--since", "1h"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lars disable"));
+}
+
+#[test]
+fn test_status_watch_and_events_follow() {
+    use std::io::{BufRead, BufReader};
+
+    let temp = TempDir::new().unwrap();
+    lars_cmd(&temp)
+        .args(["add", "sleep 60", "--name", "api"])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "exit 3", "--name", "job"])
+        .assert()
+        .success();
+
+    let exit = service_log_path(&temp, "job").with_extension("exit");
+    std::fs::write(exit, "3\n").unwrap();
+
+    let output = lars_cmd(&temp).args(["--json", "status"]).output().unwrap();
+    assert!(output.status.success());
+    let reports: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(reports[0]["status"], "stopped");
+    assert_eq!(reports[1]["status"], "exited");
+    assert_eq!(reports[1]["exit_code"], 3);
+
+    // Checking the status only reads; the exit is left for the daemon
+    let output = lars_cmd(&temp).args(["events", "--json"]).output().unwrap();
+    let events: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let kinds: Vec<&str> = events.iter().map(|e| e["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, ["added", "added"]);
+
+    let spawn = |args: &[&str]| {
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+            .env("LARS_CONFIG_HOME", temp.path())
+            .args(args)
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap()
+    };
+    let next = |reader: &mut dyn BufRead| {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str::<serde_json::Value>(&line).unwrap()
+    };
+
+    // Watching starts with the current status of every service
+    let mut watch = spawn(&["--json", "status", "--watch", "-d", "100ms"]);
+    let mut reader = BufReader::new(watch.stdout.take().unwrap());
+    let first = next(&mut reader);
+    assert_eq!(first["name"], "api");
+    assert_eq!(first["status"], "stopped");
+    assert!(first["previous"].is_null());
+    assert_eq!(next(&mut reader)["status"], "exited");
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+
+    // Following prints the history, then events as they are recorded
+    let mut follow = spawn(&["--json", "events", "--follow", "-n", "1"]);
+    let mut reader = BufReader::new(follow.stdout.take().unwrap());
+    let added = next(&mut reader);
+    assert_eq!(added["kind"], "added");
+    assert_eq!(added["service"], "job");
+
+    lars_cmd(&temp).args(["disable", "api"]).assert().success();
+    let disabled = next(&mut reader);
+    assert_eq!(disabled["kind"], "disabled");
+    assert_eq!(disabled["service"], "api");
+    follow.kill().unwrap();
+    follow.wait().unwrap();
+}
+
+#[test]
+fn test_webhook_notification_on_crash() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let temp = TempDir::new().unwrap();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+    lars_cmd(&temp)
+        .args(["config", "set", "notify_webhook", "example.com/hook"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("http://"));
+    lars_cmd(&temp)
+        .args(["config", "set", "notify_webhook", &url])
+        .assert()
+        .success();
+    lars_cmd(&temp)
+        .args(["add", "sleep 60", "--name", "api"])
+        .assert()
+        .success();
+
+    // A stand-in webhook that answers one request and returns its body
+    let webhook = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_ascii_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    });
+
+    let exit = service_log_path(&temp, "api").with_extension("exit");
+    std::fs::write(exit, "1\n").unwrap();
+
+    // Recording the exit does not notify by itself
+    lars_cmd(&temp).args(["events"]).assert().success();
+    let exit = service_log_path(&temp, "api").with_extension("exit");
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    std::fs::write(exit, "2\n").unwrap();
+
+    // The daemon notifies about the exit it records
+    let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+        .env("LARS_CONFIG_HOME", temp.path())
+        .arg("daemon")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let body = webhook.join().unwrap();
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    assert_eq!(body["trigger"], "crashed");
+    assert_eq!(body["service"], "api");
+    assert_eq!(body["exit_code"], 2);
+    assert_eq!(body["text"], "api exited with status 2");
+}
+
+#[test]
+fn test_top_and_list_stats() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    // Stopped services have no usage
+    let output = lars_cmd(&temp)
+        .args(["list", "--stats", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let services: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(services[0].get("stats").is_none());
+
+    lars_cmd(&temp)
+        .args(["--no-color", "list", "--stats"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Threads"));
+
+    let output = lars_cmd(&temp)
+        .args(["top", "-n", "2", "-d", "10ms", "--json"])
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let snapshots: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0]["services"][0]["name"], "api");
+    assert_eq!(snapshots[0]["services"][0]["status"], "stopped");
+}
+
+#[test]
+fn test_declared_ports() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "bad", "--port", "99999"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid port: 99999"));
+
+    let listener = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+    let taken = listener.local_addr().unwrap().port().to_string();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api", "-p", &taken])
+        .args(["-p", "5353/udp"])
+        .assert()
+        .success();
+
+    let config = std::fs::read_to_string(temp.path().join("config.json")).unwrap();
+    let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+    assert_eq!(config["services"][0]["ports"][1]["protocol"], "udp");
+
+    lars_cmd(&temp)
+        .args(["start", "api"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "port {} of 'api' is already in use",
+            taken
+        )));
+}
+
+#[test]
+fn test_named_port_slots() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "bad", "--port", "my.port"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid port name: my.port"));
+
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api", "-p", "http"])
+        .args(["-p", "admin=9001"])
+        .assert()
+        .success();
+
+    let config = std::fs::read_to_string(temp.path().join("config.json")).unwrap();
+    let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+    assert_eq!(
+        config["services"][0]["ports"],
+        serde_json::json!([{"name": "http"}, {"name": "admin", "port": 9001}])
+    );
+
+    lars_cmd(&temp)
+        .args(["--no-color", "inspect", "api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Ports:   http, admin=9001"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_control_socket() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let temp = TempDir::new().unwrap();
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+        .env("LARS_CONFIG_HOME", temp.path())
+        .arg("daemon")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let socket = temp.path().join("run").join("lars.sock");
+    let stream = (0..50)
+        .find_map(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            UnixStream::connect(&socket).ok()
+        })
+        .expect("daemon did not open its control socket");
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut call = |request: serde_json::Value| {
+        writeln!(&stream, "{}", request).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str::<serde_json::Value>(&line).unwrap()
+    };
+
+    let response = call(serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "list"}));
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"][0]["name"], "api");
+    assert_eq!(response["result"][0]["status"], "stopped");
+
+    let response = call(serde_json::json!({
+        "jsonrpc": "2.0", "id": 2, "method": "stop", "params": {"name": "missing"}
+    }));
+    assert_eq!(response["error"]["message"], "Service not found: missing");
+
+    let response = call(serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "nope"}));
+    assert_eq!(response["error"]["code"], -32601);
+
+    let response = call(serde_json::json!({
+        "jsonrpc": "2.0", "id": 4, "method": "events", "params": {"name": "api"}
+    }));
+    assert_eq!(response["result"][0]["kind"], "added");
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+}
+
+#[test]
+fn test_daemon_metrics() {
+    use std::io::{Read, Write};
+
+    let temp = TempDir::new().unwrap();
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api"])
+        .assert()
+        .success();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+        .env("LARS_CONFIG_HOME", temp.path())
+        .args(["daemon", "--metrics", &port.to_string()])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut stream = (0..50)
+        .find_map(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            std::net::TcpStream::connect(("127.0.0.1", port)).ok()
+        })
+        .expect("daemon did not serve metrics");
+    write!(
+        stream,
+        "GET /metrics HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("# TYPE lars_service_up gauge"));
+    assert!(response.contains("lars_service_up{service=\"api\"} 0"));
+    assert!(response.contains("lars_service_restarts_total{service=\"api\"} 0"));
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+}
+
+#[test]
+fn test_ui_dashboard() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let temp = TempDir::new().unwrap();
+    lars_cmd(&temp)
+        .args(["add", "echo hello", "--name", "api", "-p", "http"])
+        .assert()
+        .success();
+
+    let mut ui = std::process::Command::new(assert_cmd::cargo::cargo_bin!("lars"))
+        .env("LARS_CONFIG_HOME", temp.path())
+        .args(["--json", "ui", "--port", "0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut line = String::new();
+    BufReader::new(ui.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let listening: serde_json::Value = serde_json::from_str(&line).unwrap();
+    let url = listening["url"].as_str().unwrap();
+    let addr = url.trim_start_matches("http://");
+
+    let request = |method: &str, path: &str, host: &str| {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            method, path, host
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let response = request("GET", "/", addr);
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("<title>lars</title>"));
+
+    let response = request("GET", "/api/services", addr);
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body[0]["name"], "api");
+    assert_eq!(body[0]["status"], "stopped");
+    assert_eq!(body[0]["ports"][0]["name"], "http");
+
+    let response = request("POST", "/api/services/missing/start", addr);
+    assert!(response.starts_with("HTTP/1.1 500"));
+    assert!(response.contains("Service not found: missing"));
+
+    // Pages served from other hosts cannot reach the API
+    let response = request("GET", "/api/services", "attacker.example");
+    assert!(response.starts_with("HTTP/1.1 403"));
+
+    ui.kill().unwrap();
+    ui.wait().unwrap();
+}
+
+#[test]
+fn test_tui_needs_terminal() {
+    let temp = TempDir::new().unwrap();
+
+    lars_cmd(&temp)
+        .arg("tui")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs an interactive terminal"));
+}
@@ -0,0 +1,306 @@
+//! Async process runners, for embedding lars-core in tokio applications
+//!
+//! [`AsyncRunner`] mirrors [`crate::Runner`] with methods that return
+//! futures. [`AsyncTmuxRunner`] runs the calls of a [`TmuxRunner`] on
+//! tokio's blocking thread pool, so both APIs go through the same commands
+//! and [`CommandExecutor`], and services started by one can be stopped by
+//! the other.
+//!
+//! No call blocks an executor worker thread, but each one holds a thread of
+//! the blocking pool while tmux runs, so many concurrent calls queue once
+//! that pool (`max_blocking_threads`) is used up. Waiting for a service to
+//! stop sleeps on the tokio timer between checks and holds no thread.
+//!
+//! Enabled by the `async` feature.
+//!
+//! ```no_run
+//! # async fn example() -> lars_core::Result<()> {
+//! use lars_core::{create_async_runner, ConfigManager};
+//!
+//! let config = ConfigManager::with_defaults()?;
+//! let service = config.get_service("api")?;
+//! let runner = create_async_runner(service.runner_type).await?;
+//!
+//! runner.start(&service, &config.log_path_for_service(&service.id)).await?;
+//! runner.stop(&service).await?;
+//! runner.wait_stopped(&service, 10).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{LarsError, Result};
+use crate::models::{RunnerType, Service};
+use crate::runner::{CommandExecutor, Runner, RunnerCapabilities, TmuxRunner};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How often [`AsyncRunner::wait_stopped`] checks the service
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Future returned by [`AsyncRunner`] methods
+pub type RunnerFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Async counterpart of [`crate::Runner`]
+pub trait AsyncRunner: Send + Sync {
+    /// Start a service
+    fn start<'a>(&'a self, service: &'a Service, log_path: &'a Path) -> RunnerFuture<'a, ()>;
+
+    /// Stop a service
+    fn stop<'a>(&'a self, service: &'a Service) -> RunnerFuture<'a, ()>;
+
+    /// Check if a service is running
+    fn is_running<'a>(&'a self, service: &'a Service) -> RunnerFuture<'a, bool>;
+
+    /// Get the PID of a running service (if available)
+    fn get_pid<'a>(&'a self, service: &'a Service) -> RunnerFuture<'a, Option<u32>>;
+
+    /// Get the runner type
+    fn runner_type(&self) -> RunnerType;
+
+    /// What the runner can do beyond starting and stopping services (see
+    /// [`crate::Runner::capabilities`])
+    fn capabilities(&self) -> RunnerCapabilities {
+        self.runner_type().capabilities()
+    }
+
+    /// Restart a service (stop then start), waiting up to `timeout_secs`
+    /// for it to stop
+    fn restart<'a>(
+        &'a self,
+        service: &'a Service,
+        log_path: &'a Path,
+        timeout_secs: u64,
+    ) -> RunnerFuture<'a, ()> {
+        Box::pin(async move {
+            if self.is_running(service).await? {
+                self.stop(service).await?;
+                self.wait_stopped(service, timeout_secs).await?;
+            }
+
+            self.start(service, log_path).await
+        })
+    }
+
+    /// Wait until a service is no longer running
+    ///
+    /// Returns [`LarsError::StopTimeout`] if it is still running after
+    /// `timeout_secs`.
+    fn wait_stopped<'a>(&'a self, service: &'a Service, timeout_secs: u64) -> RunnerFuture<'a, ()> {
+        Box::pin(async move {
+            let start = Instant::now();
+            while self.is_running(service).await? {
+                if start.elapsed() > Duration::from_secs(timeout_secs) {
+                    return Err(LarsError::StopTimeout(service.name.clone()));
+                }
+                tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Tmux-based async runner, managing the same sessions as [`TmuxRunner`]
+#[derive(Debug, Clone, Default)]
+pub struct AsyncTmuxRunner {
+    runner: TmuxRunner,
+}
+
+impl AsyncTmuxRunner {
+    /// Create a new AsyncTmuxRunner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an AsyncTmuxRunner that runs `tmux` through `executor`
+    pub fn with_executor(executor: Arc<dyn CommandExecutor>) -> Self {
+        Self {
+            runner: TmuxRunner::with_executor(executor),
+        }
+    }
+
+    /// Check if tmux is available
+    pub async fn is_available(&self) -> bool {
+        let runner = self.runner.clone();
+        tokio::task::spawn_blocking(move || runner.is_available())
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Run a call of the blocking runner on tokio's blocking thread pool
+    fn blocking<'a, T, F>(&self, service: &Service, call: F) -> RunnerFuture<'a, T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&TmuxRunner, &Service) -> Result<T> + Send + 'static,
+    {
+        let runner = self.runner.clone();
+        let service = service.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || call(&runner, &service))
+                .await
+                .map_err(|e| LarsError::Io(std::io::Error::other(e)))?
+        })
+    }
+}
+
+impl AsyncRunner for AsyncTmuxRunner {
+    fn start<'a>(&'a self, service: &'a Service, log_path: &'a Path) -> RunnerFuture<'a, ()> {
+        let log_path = log_path.to_path_buf();
+        self.blocking(service, move |runner, service| {
+            runner.start(service, &log_path)
+        })
+    }
+
+    fn stop<'a>(&'a self, service: &'a Service) -> RunnerFuture<'a, ()> {
+        self.blocking(service, |runner, service| runner.stop(service))
+    }
+
+    fn is_running<'a>(&'a self, service: &'a Service) -> RunnerFuture<'a, bool> {
+        self.blocking(service, |runner, service| runner.is_running(service))
+    }
+
+    fn get_pid<'a>(&'a self, service: &'a Service) -> RunnerFuture<'a, Option<u32>> {
+        self.blocking(service, |runner, service| runner.get_pid(service))
+    }
+
+    fn runner_type(&self) -> RunnerType {
+        RunnerType::Tmux
+    }
+}
+
+/// Create an async runner for the specified type
+pub async fn create_async_runner(runner_type: RunnerType) -> Result<Box<dyn AsyncRunner>> {
+    match runner_type {
+        RunnerType::Tmux => {
+            let runner = AsyncTmuxRunner::new();
+            if !runner.is_available().await {
+                return Err(LarsError::RunnerNotAvailable(
+                    "tmux is not installed or not in PATH".to_string(),
+                ));
+            }
+            Ok(Box::new(runner))
+        }
+        RunnerType::Screen => Err(LarsError::RunnerNotAvailable(
+            "screen runner is not yet implemented".to_string(),
+        )),
+        RunnerType::Direct => Err(LarsError::RunnerNotAvailable(
+            "direct runner is not yet implemented".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedExecutor;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Reports a service as running for a number of checks
+    struct StoppingRunner {
+        checks_left: AtomicU32,
+    }
+
+    impl AsyncRunner for StoppingRunner {
+        fn start<'a>(&'a self, _: &'a Service, _: &'a Path) -> RunnerFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn stop<'a>(&'a self, _: &'a Service) -> RunnerFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn is_running<'a>(&'a self, _: &'a Service) -> RunnerFuture<'a, bool> {
+            Box::pin(async {
+                let left = self.checks_left.load(Ordering::SeqCst);
+                if left > 0 {
+                    self.checks_left.store(left - 1, Ordering::SeqCst);
+                }
+                Ok(left > 0)
+            })
+        }
+
+        fn get_pid<'a>(&'a self, _: &'a Service) -> RunnerFuture<'a, Option<u32>> {
+            Box::pin(async { Ok(None) })
+        }
+
+        fn runner_type(&self) -> RunnerType {
+            RunnerType::Direct
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_stopped() {
+        let service = Service::new("api".to_string(), "serve".to_string());
+
+        let runner = StoppingRunner {
+            checks_left: AtomicU32::new(3),
+        };
+        runner.wait_stopped(&service, 10).await.unwrap();
+        assert_eq!(runner.checks_left.load(Ordering::SeqCst), 0);
+
+        let runner = StoppingRunner {
+            checks_left: AtomicU32::new(u32::MAX),
+        };
+        let err = runner.wait_stopped(&service, 1).await.unwrap_err();
+        assert!(matches!(err, LarsError::StopTimeout(name) if name == "api"));
+    }
+
+    #[test]
+    fn test_async_tmux_runner_type() {
+        assert_eq!(AsyncTmuxRunner::new().runner_type(), RunnerType::Tmux);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        assert_eq!(
+            AsyncTmuxRunner::new().capabilities(),
+            RunnerCapabilities::TMUX
+        );
+        let runner = StoppingRunner {
+            checks_left: AtomicU32::new(0),
+        };
+        assert_eq!(runner.capabilities(), RunnerCapabilities::default());
+    }
+
+    #[tokio::test]
+    async fn test_async_tmux_runs_through_executor() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let log_path = temp.path().join("api.log");
+        let tmux = Arc::new(ScriptedExecutor::new());
+        let runner = AsyncTmuxRunner::with_executor(tmux.clone());
+        let service = Service::new("api".to_string(), "serve".to_string());
+
+        assert!(runner.is_available().await);
+        runner.start(&service, &log_path).await.unwrap();
+        assert!(tmux.has_session(&TmuxRunner::session_name(&service)));
+        assert!(runner.is_running(&service).await.unwrap());
+        assert_eq!(runner.get_pid(&service).await.unwrap(), Some(4242));
+
+        runner.stop(&service).await.unwrap();
+        runner.wait_stopped(&service, 1).await.unwrap();
+        assert_eq!(runner.get_pid(&service).await.unwrap(), None);
+    }
+
+    // Integration tests that require tmux are marked with #[ignore]
+    // Run with: cargo test --features async -- --ignored
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_async_tmux_start_stop() {
+        let runner = AsyncTmuxRunner::new();
+        let service = Service::new("test-async".to_string(), "sleep 60".to_string());
+        let log_path = std::env::temp_dir().join(format!("lar_{}.log", service.id));
+
+        runner.start(&service, &log_path).await.unwrap();
+        assert!(runner.is_running(&service).await.unwrap());
+        assert!(runner.get_pid(&service).await.unwrap().is_some());
+
+        runner.stop(&service).await.unwrap();
+        runner.wait_stopped(&service, 5).await.unwrap();
+        assert!(!runner.is_running(&service).await.unwrap());
+    }
+}
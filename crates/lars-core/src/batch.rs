@@ -0,0 +1,112 @@
+//! Bounded-concurrency execution for batch operations (start-all, stop-all)
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+/// Default number of services handled concurrently by batch operations
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Progress notification emitted while a batch is running
+pub enum BatchEvent<'a, R> {
+    /// Work on the item at this index has begun
+    Started(usize),
+    /// Work on the item at this index has finished
+    Finished(usize, &'a R),
+}
+
+/// Run `work` over `items` using at most `jobs` worker threads.
+///
+/// Items are dispatched in input order, so `jobs = 1` behaves exactly like a
+/// sequential loop. `on_event` is invoked on the calling thread as items start
+/// and finish, which keeps all terminal output in one place. Results are
+/// returned in input order regardless of completion order.
+pub fn run_bounded<T, R, W, E>(items: &[T], jobs: usize, work: W, mut on_event: E) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    E: FnMut(BatchEvent<'_, R>),
+{
+    let workers = jobs.clamp(1, items.len().max(1));
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+
+    std::thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let next = &next;
+            let work = &work;
+
+            scope.spawn(move || loop {
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                let Some(item) = items.get(idx) else {
+                    break;
+                };
+
+                let _ = tx.send((idx, None));
+                let result = work(item);
+                let _ = tx.send((idx, Some(result)));
+            });
+        }
+
+        // Only the workers hold senders now, so the receive loop ends
+        // once every worker has run out of items.
+        drop(tx);
+
+        for (idx, result) in rx {
+            match result {
+                None => on_event(BatchEvent::Started(idx)),
+                Some(result) => {
+                    on_event(BatchEvent::Finished(idx, &result));
+                    results[idx] = Some(result);
+                }
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|r| r.expect("every batch item produces a result"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_bounded_keeps_input_order() {
+        let items: Vec<u64> = (0..10).collect();
+        let mut started = 0;
+        let mut finished = Vec::new();
+
+        let results = run_bounded(
+            &items,
+            3,
+            |n| {
+                std::thread::sleep(std::time::Duration::from_millis(10 - n));
+                n * 2
+            },
+            |event| match event {
+                BatchEvent::Started(_) => started += 1,
+                BatchEvent::Finished(idx, result) => finished.push((idx, *result)),
+            },
+        );
+
+        assert_eq!(results, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+        assert_eq!(started, items.len());
+        finished.sort();
+        assert_eq!(
+            finished,
+            (0..10).map(|n| (n, n as u64 * 2)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_run_bounded_empty() {
+        let results = run_bounded(&[] as &[u32], 4, |n| *n, |_| {});
+        assert!(results.is_empty());
+    }
+}
@@ -3,15 +3,27 @@
 //! Handles loading, saving, and managing the application configuration
 //! with support for atomic saves and platform-specific paths.
 
+use crate::control::{SOCKET_DIR_NAME, SOCKET_FILE_NAME};
 use crate::error::{ConfigError, LarsError, Result};
+use crate::events::{self, Event, EventFilter, EventKind, EVENTS_FILE_NAME};
+use crate::health::{Health, HealthStatus};
 use crate::models::{AppConfig, CURRENT_CONFIG_VERSION};
+use crate::ports;
+use crate::runner::exit_path_for_log;
+use crate::state::{ExitRecord, LaunchRecord, RuntimeState, STATE_FILE_NAME, STATE_LOCK_FILE_NAME};
 use directories::ProjectDirs;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// Configuration file name
 const CONFIG_FILE_NAME: &str = "config.json";
 
+/// Serializes read-modify-write cycles on the state file between threads;
+/// other processes are kept out by a lock on the state lock file.
+static STATE_LOCK: Mutex<()> = Mutex::new(());
+
 /// Manages the application configuration
 #[derive(Debug, Clone)]
 pub struct ConfigManager {
@@ -19,6 +31,8 @@ pub struct ConfigManager {
     config_dir: PathBuf,
     /// Directory where log files are stored
     log_dir: PathBuf,
+    /// What is making changes, recorded with events
+    actor: Option<String>,
 }
 
 impl ConfigManager {
@@ -30,6 +44,7 @@ impl ConfigManager {
         Self {
             config_dir,
             log_dir,
+            actor: None,
         }
     }
 
@@ -45,6 +60,7 @@ impl ConfigManager {
             return Ok(Self {
                 config_dir: base.clone(),
                 log_dir: base.join("logs"),
+                actor: None,
             });
         }
 
@@ -62,9 +78,16 @@ impl ConfigManager {
         Ok(Self {
             config_dir,
             log_dir,
+            actor: None,
         })
     }
 
+    /// Set what is making changes (e.g. `lars start`), recorded with events
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
     /// Get the path to the config file
     pub fn config_path(&self) -> PathBuf {
         self.config_dir.join(CONFIG_FILE_NAME)
@@ -80,11 +103,46 @@ impl ConfigManager {
         &self.log_dir
     }
 
+    /// Get the state directory (holds runtime state next to the log directory)
+    pub fn state_dir(&self) -> &Path {
+        self.log_dir.parent().unwrap_or(&self.config_dir)
+    }
+
+    /// Get the path to the runtime state file
+    pub fn state_path(&self) -> PathBuf {
+        self.state_dir().join(STATE_FILE_NAME)
+    }
+
+    /// Get the path to the event history
+    pub fn events_path(&self) -> PathBuf {
+        self.state_dir().join(EVENTS_FILE_NAME)
+    }
+
+    /// Get the path to the daemon's control socket
+    pub fn control_socket_path(&self) -> PathBuf {
+        self.config_dir.join(SOCKET_DIR_NAME).join(SOCKET_FILE_NAME)
+    }
+
     /// Get the log file path for a service
     pub fn log_path_for_service(&self, service_id: &uuid::Uuid) -> PathBuf {
         self.log_dir.join(format!("{}.log", service_id))
     }
 
+    /// Get the path of the file a service's exit status is written to
+    pub fn exit_path_for_service(&self, service_id: &uuid::Uuid) -> PathBuf {
+        exit_path_for_log(&self.log_path_for_service(service_id))
+    }
+
+    /// Get the log file path for a service's lifecycle hooks
+    pub fn hook_log_path_for_service(&self, service_id: &uuid::Uuid) -> PathBuf {
+        self.log_dir.join(format!("{}.hooks.log", service_id))
+    }
+
+    /// Get how a service last exited on its own, if it has since its last start
+    pub fn last_exit(&self, service_id: &uuid::Uuid) -> Option<ExitRecord> {
+        ExitRecord::read(&self.exit_path_for_service(service_id))
+    }
+
     /// Ensure all required directories exist
     pub fn ensure_directories(&self) -> Result<()> {
         fs::create_dir_all(&self.config_dir)?;
@@ -128,6 +186,81 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Load the runtime state from disk for display.
+    ///
+    /// A missing or unreadable state file yields empty state, since it only
+    /// holds information that is rebuilt as services are started. Updates go
+    /// through [`Self::update_state`], which refuses to replace a state file
+    /// it cannot parse.
+    pub fn load_state(&self) -> RuntimeState {
+        self.read_state().unwrap_or_default()
+    }
+
+    /// Read the runtime state, treating only a missing file as empty state
+    fn read_state(&self) -> Result<RuntimeState> {
+        let state_path = self.state_path();
+        let contents = match fs::read_to_string(&state_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(RuntimeState::default())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&contents)
+            .map_err(|e| ConfigError::StateParseError(state_path, e).into())
+    }
+
+    /// Apply a change to the runtime state and save it atomically.
+    ///
+    /// Fails without writing if the existing state file cannot be parsed,
+    /// rather than replacing the launch records and ports it holds.
+    pub fn update_state<F, T>(&self, update_fn: F) -> Result<T>
+    where
+        F: FnOnce(&mut RuntimeState) -> T,
+    {
+        let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        // The CLI, the daemon and watchers all update the state, so hold an
+        // advisory lock from load to save. It is released when dropped.
+        fs::create_dir_all(self.state_dir())?;
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.state_dir().join(STATE_LOCK_FILE_NAME))?;
+        lock_exclusive(&lock)?;
+
+        let mut state = self.read_state()?;
+        let result = update_fn(&mut state);
+
+        let state_path = self.state_path();
+        let temp_path = state_path.with_extension("json.tmp");
+
+        let contents = serde_json::to_string_pretty(&state).map_err(ConfigError::ParseError)?;
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, &state_path)?;
+
+        Ok(result)
+    }
+
+    /// Append an event to the event history, noting who made the change
+    pub fn record_event(&self, mut event: Event) -> Result<()> {
+        if event.actor.is_none() {
+            event.actor = self.actor.clone();
+        }
+        if event.user.is_none() {
+            event.user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok();
+        }
+        events::append_event(&self.events_path(), &event)
+    }
+
+    /// Read the last `limit` events passing `filter`, oldest first
+    pub fn events(&self, filter: &EventFilter, limit: usize) -> Result<Vec<Event>> {
+        events::read_events(&self.events_path(), filter, limit)
+    }
+
     fn migrate(&self, config: &mut AppConfig) -> Result<()> {
         if config.config_version < CURRENT_CONFIG_VERSION {
             config.config_version = CURRENT_CONFIG_VERSION;
@@ -176,8 +309,11 @@ impl ConfigManager {
             return Err(LarsError::ServiceAlreadyExists(service.name));
         }
 
+        let event = Event::for_service(EventKind::Added, &service);
         config.add_service(service);
         self.save(&config)?;
+        // The event history is best-effort; the change itself has been saved
+        let _ = self.record_event(event);
 
         Ok(())
     }
@@ -191,6 +327,7 @@ impl ConfigManager {
             .ok_or_else(|| LarsError::ServiceNotFound(name.to_string()))?;
 
         self.save(&config)?;
+        let _ = self.record_event(Event::for_service(EventKind::Removed, &service));
 
         Ok(service)
     }
@@ -216,10 +353,26 @@ impl ConfigManager {
             .find_service_by_name_mut(name)
             .ok_or_else(|| LarsError::ServiceNotFound(name.to_string()))?;
 
+        let before = service.clone();
         update_fn(service);
         service.touch();
 
+        let event = if before.name != service.name {
+            Event::for_service(EventKind::Renamed, service)
+                .with_detail(format!("from '{}'", before.name))
+        } else if before.enabled != service.enabled {
+            let kind = if service.enabled {
+                EventKind::Enabled
+            } else {
+                EventKind::Disabled
+            };
+            Event::for_service(kind, service)
+        } else {
+            Event::for_service(EventKind::Updated, service)
+        };
+
         self.save(&config)?;
+        let _ = self.record_event(event);
 
         Ok(())
     }
@@ -229,6 +382,314 @@ impl ConfigManager {
         let config = self.load()?;
         Ok(config.services)
     }
+
+    /// Record that a service was just started with `service` as its
+    /// definition, including the environment it was launched with
+    pub fn record_launch(&self, service: &crate::models::Service) -> Result<()> {
+        self.update_state(|state| {
+            let record = LaunchRecord::new(service);
+            let entry = state.service_mut(service.id);
+            entry.last_started_at = Some(record.started_at);
+            entry.launch = Some(record);
+        })
+    }
+
+    /// Clear the launch record of a service that has been stopped
+    pub fn clear_launch(&self, service_id: &uuid::Uuid) -> Result<()> {
+        self.update_state(|state| {
+            if let Some(entry) = state.services.get_mut(service_id) {
+                entry.launch = None;
+            }
+        })
+    }
+
+    /// Record an `exited` event if the service has exited on its own since
+    /// its exit was last recorded, returning the event.
+    ///
+    /// Exits are noticed after the fact, so the event carries the time the
+    /// process exited rather than the time it was recorded.
+    pub fn record_exit(&self, service: &crate::models::Service) -> Result<Option<Event>> {
+        let Some(exit) = self.last_exit(&service.id) else {
+            return Ok(None);
+        };
+        let recorded = |state: &RuntimeState| {
+            state.service(&service.id).and_then(|s| s.exit_recorded_at) == Some(exit.finished_at)
+        };
+        if recorded(&self.load_state()) {
+            return Ok(None);
+        }
+
+        // Checked again under the state lock, so that an exit noticed by
+        // several commands at once is recorded by only one of them
+        let first = self.update_state(|state| {
+            let first = !recorded(state);
+            state.service_mut(service.id).exit_recorded_at = Some(exit.finished_at);
+            first
+        })?;
+        if !first {
+            return Ok(None);
+        }
+        // Not attributed to whichever command happened to notice the exit
+        let event = Event::for_service(EventKind::Exited, service)
+            .at(exit.finished_at)
+            .with_exit_code(exit.code);
+        events::append_event(&self.events_path(), &event)?;
+        Ok(Some(event))
+    }
+
+    /// Record exits of all services (see [`ConfigManager::record_exit`])
+    pub fn record_exits(&self) -> Result<()> {
+        for service in self.list_services()? {
+            self.record_exit(&service)?;
+        }
+        Ok(())
+    }
+
+    /// Record an `unhealthy` event when a running service's health check
+    /// starts failing, and a `healthy` event once it passes again, returning
+    /// the event.
+    ///
+    /// `health` is `None` for services that are not running or have nothing
+    /// to check, which forgets the recorded health without an event.
+    pub fn record_health(
+        &self,
+        service: &crate::models::Service,
+        health: Option<&Health>,
+    ) -> Result<Option<Event>> {
+        let current = health.map(|h| h.status);
+        let recorded = self
+            .load_state()
+            .service(&service.id)
+            .and_then(|s| s.health);
+        if current == recorded {
+            return Ok(None);
+        }
+
+        // Like exits, the transition is taken under the state lock
+        let recorded = self.update_state(|state| {
+            std::mem::replace(&mut state.service_mut(service.id).health, current)
+        })?;
+        // Like exits, health changes are not attributed to an actor
+        let event = match (recorded, health) {
+            (Some(HealthStatus::Unhealthy), Some(health))
+                if health.status == HealthStatus::Unhealthy =>
+            {
+                return Ok(None)
+            }
+            (_, Some(health)) if health.status == HealthStatus::Unhealthy => {
+                let event = Event::for_service(EventKind::Unhealthy, service);
+                match &health.error {
+                    Some(error) => event.with_error(error.clone()),
+                    None => event,
+                }
+            }
+            (Some(HealthStatus::Unhealthy), Some(_)) => {
+                Event::for_service(EventKind::Healthy, service)
+            }
+            _ => return Ok(None),
+        };
+        events::append_event(&self.events_path(), &event)?;
+        Ok(Some(event))
+    }
+
+    /// Get the launch record of a service, if it was started by lars
+    pub fn launch_record(&self, service_id: &uuid::Uuid) -> Option<LaunchRecord> {
+        self.load_state()
+            .service(service_id)
+            .and_then(|s| s.launch.clone())
+    }
+
+    /// Ports of a service's named slots, allocating free ports to slots that
+    /// have none yet.
+    ///
+    /// Allocations are kept in the state, so a service gets the same ports
+    /// on every start for as long as they stay free.
+    pub fn assigned_ports(
+        &self,
+        service: &crate::models::Service,
+    ) -> Result<BTreeMap<String, u16>> {
+        self.assign_ports(service, false)
+    }
+
+    /// Environment to start a service with: its own `env` with port
+    /// references interpolated, plus `PORT_<NAME>` for each named port and
+    /// `PORT` for the first declared port. Variables set in `env` win.
+    ///
+    /// Ports allocated earlier are re-allocated if something else has taken
+    /// them since.
+    pub fn launch_env(&self, service: &crate::models::Service) -> Result<HashMap<String, String>> {
+        self.resolve_env(service, true)
+    }
+
+    /// Environment a service would be started with now, as [`Self::launch_env`]
+    /// but with the ports allocated earlier, even if they have been taken.
+    ///
+    /// Nothing is allocated and the state is not written, so slots that
+    /// have no port yet are left out.
+    pub fn resolved_env(
+        &self,
+        service: &crate::models::Service,
+    ) -> Result<HashMap<String, String>> {
+        let state = self.load_state();
+        self.interpolate_env(service, |other| Ok(allocated_ports(other, &state)))
+    }
+
+    fn resolve_env(
+        &self,
+        service: &crate::models::Service,
+        recheck: bool,
+    ) -> Result<HashMap<String, String>> {
+        let ports = self.assign_ports(service, recheck)?;
+        self.interpolate_env(service, |other| {
+            if other.id == service.id {
+                Ok(ports.clone())
+            } else {
+                self.assigned_ports(other)
+            }
+        })
+    }
+
+    /// Build a service's environment with the ports `ports_of` gives each
+    /// service
+    fn interpolate_env<F>(
+        &self,
+        service: &crate::models::Service,
+        ports_of: F,
+    ) -> Result<HashMap<String, String>>
+    where
+        F: Fn(&crate::models::Service) -> Result<BTreeMap<String, u16>>,
+    {
+        let ports = ports_of(service)?;
+        let mut others: HashMap<String, BTreeMap<String, u16>> = HashMap::new();
+
+        let mut env = HashMap::new();
+        for (key, value) in &service.env {
+            let value = ports::interpolate(value, |other, slot| match other {
+                None => Ok(ports.get(slot).copied()),
+                Some(name) => {
+                    if !others.contains_key(name) {
+                        let other = self.get_service(name)?;
+                        others.insert(name.to_string(), ports_of(&other)?);
+                    }
+                    Ok(others[name].get(slot).copied())
+                }
+            })?;
+            env.insert(key.clone(), value);
+        }
+
+        for (name, port) in &ports {
+            env.entry(ports::port_env_var(name))
+                .or_insert_with(|| port.to_string());
+        }
+        let first = service.ports.first().and_then(|spec| {
+            spec.port
+                .or_else(|| ports.get(spec.name.as_deref()?).copied())
+        });
+        if let Some(port) = first {
+            env.entry("PORT".to_string())
+                .or_insert_with(|| port.to_string());
+        }
+
+        Ok(env)
+    }
+
+    /// Resolve the named ports of a service, allocating and persisting ports
+    /// for slots without a fixed number. With `recheck`, allocated ports that
+    /// are no longer free are replaced.
+    fn assign_ports(
+        &self,
+        service: &crate::models::Service,
+        recheck: bool,
+    ) -> Result<BTreeMap<String, u16>> {
+        let named = service.ports.iter().filter(|spec| spec.name.is_some());
+        if named.clone().all(|spec| spec.port.is_some()) {
+            return Ok(named
+                .filter_map(|spec| Some((spec.name.clone()?, spec.port?)))
+                .collect());
+        }
+
+        // Fixed ports of every service are never handed out
+        let fixed: HashSet<u16> = self
+            .list_services()?
+            .iter()
+            .flat_map(|s| s.ports.iter().filter_map(|spec| spec.port))
+            .collect();
+
+        self.update_state(|state| {
+            let mut reserved = fixed;
+            for (id, other) in &state.services {
+                if *id != service.id {
+                    reserved.extend(other.ports.values());
+                }
+            }
+            let previous = state
+                .service(&service.id)
+                .map(|s| s.ports.clone())
+                .unwrap_or_default();
+
+            let mut assigned = BTreeMap::new();
+            for spec in named {
+                let Some(name) = spec.name.clone() else {
+                    continue;
+                };
+                let port = match (spec.port, previous.get(&name)) {
+                    (Some(port), _) => port,
+                    (None, Some(&port))
+                        if !recheck
+                            || (!reserved.contains(&port)
+                                && ports::is_free(port, spec.protocol)) =>
+                    {
+                        port
+                    }
+                    (None, _) => ports::allocate_port(spec.protocol, &reserved)?,
+                };
+                reserved.insert(port);
+                assigned.insert(name, port);
+            }
+
+            state.service_mut(service.id).ports = assigned.clone();
+            Ok(assigned)
+        })?
+    }
+}
+
+/// Ports of a service's named slots that are fixed or were allocated earlier
+fn allocated_ports(
+    service: &crate::models::Service,
+    state: &RuntimeState,
+) -> BTreeMap<String, u16> {
+    let previous = state.service(&service.id).map(|s| &s.ports);
+    service
+        .ports
+        .iter()
+        .filter_map(|spec| {
+            let name = spec.name.clone()?;
+            let port = spec.port.or_else(|| previous?.get(&name).copied())?;
+            Some((name, port))
+        })
+        .collect()
+}
+
+/// Wait for an exclusive advisory lock on a file, held until it is closed.
+///
+/// Without flock only the in-process lock serializes updates.
+fn lock_exclusive(file: &fs::File) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        // SAFETY: flock only locks the open file description
+        while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = file;
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -355,6 +816,69 @@ mod tests {
         assert!(!service.enabled);
     }
 
+    #[test]
+    fn test_launch_record_round_trip() {
+        let (manager, _temp) = test_config_manager();
+
+        let service = Service::new("test".to_string(), "echo hello".to_string());
+        assert!(manager.launch_record(&service.id).is_none());
+
+        manager.record_launch(&service).unwrap();
+        let record = manager.launch_record(&service.id).unwrap();
+        assert_eq!(record.fingerprint.command, "echo hello");
+        assert!(manager.state_path().starts_with(manager.state_dir()));
+
+        manager.clear_launch(&service.id).unwrap();
+        assert!(manager.launch_record(&service.id).is_none());
+
+        // The last start time outlives the launch record
+        let state = manager.load_state();
+        let entry = state.service(&service.id).unwrap();
+        assert_eq!(entry.last_started_at, Some(record.started_at));
+    }
+
+    #[test]
+    fn test_update_state_waits_for_state_lock() {
+        let (manager, _temp) = test_config_manager();
+        let service = Service::new("test".to_string(), "echo hello".to_string());
+
+        // Stands in for another process holding the lock
+        fs::create_dir_all(manager.state_dir()).unwrap();
+        let lock = fs::File::create(manager.state_dir().join(STATE_LOCK_FILE_NAME)).unwrap();
+        lock_exclusive(&lock).unwrap();
+
+        let writer = {
+            let manager = manager.clone();
+            let service = service.clone();
+            std::thread::spawn(move || manager.record_launch(&service).unwrap())
+        };
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(manager.launch_record(&service.id).is_none());
+
+        drop(lock);
+        writer.join().unwrap();
+        assert!(manager.launch_record(&service.id).is_some());
+    }
+
+    #[test]
+    fn test_update_state_keeps_unparseable_state() {
+        let (manager, _temp) = test_config_manager();
+        let service = Service::new("test".to_string(), "echo hello".to_string());
+
+        fs::create_dir_all(manager.state_dir()).unwrap();
+        fs::write(manager.state_path(), "{\"services\": {").unwrap();
+
+        assert!(manager.load_state().services.is_empty());
+        assert!(matches!(
+            manager.record_launch(&service),
+            Err(LarsError::Config(ConfigError::StateParseError(..)))
+        ));
+        assert_eq!(
+            fs::read_to_string(manager.state_path()).unwrap(),
+            "{\"services\": {"
+        );
+    }
+
     #[test]
     fn test_log_path_for_service() {
         let (manager, _temp) = test_config_manager();
@@ -365,4 +889,166 @@ mod tests {
         assert!(log_path.to_string_lossy().contains(&id.to_string()));
         assert!(log_path.to_string_lossy().ends_with(".log"));
     }
+
+    #[test]
+    fn test_service_changes_are_recorded_as_events() {
+        let (manager, _temp) = test_config_manager();
+        let manager = manager.with_actor("lars test");
+
+        let service = Service::new("api".to_string(), "echo hello".to_string());
+        let id = service.id;
+        manager.add_service(service).unwrap();
+        manager
+            .update_service("api", |s| s.name = "web".to_string())
+            .unwrap();
+        manager
+            .update_service("web", |s| s.enabled = false)
+            .unwrap();
+        manager
+            .update_service("web", |s| s.command = "echo hi".to_string())
+            .unwrap();
+        manager.remove_service("web").unwrap();
+
+        let events = manager.events(&EventFilter::default(), 10).unwrap();
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::Added,
+                EventKind::Renamed,
+                EventKind::Disabled,
+                EventKind::Updated,
+                EventKind::Removed
+            ]
+        );
+        assert!(events.iter().all(|e| e.service_id == Some(id)));
+        assert_eq!(events[1].detail.as_deref(), Some("from 'api'"));
+        assert_eq!(events[0].actor.as_deref(), Some("lars test"));
+    }
+
+    #[test]
+    fn test_record_exit_once() {
+        let (manager, _temp) = test_config_manager();
+
+        let service = Service::new("job".to_string(), "exit 3".to_string());
+        assert!(manager.record_exit(&service).unwrap().is_none());
+        assert!(manager
+            .events(&EventFilter::default(), 10)
+            .unwrap()
+            .is_empty());
+
+        fs::create_dir_all(manager.log_dir()).unwrap();
+        fs::write(manager.exit_path_for_service(&service.id), "3\n").unwrap();
+        let recorded = manager.record_exit(&service).unwrap().unwrap();
+        assert!(manager.record_exit(&service).unwrap().is_none());
+
+        let events = manager.events(&EventFilter::default(), 10).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Exited);
+        assert_eq!(events[0].exit_code, Some(3));
+        assert_eq!(events[0], recorded);
+    }
+
+    #[test]
+    fn test_record_health_transitions() {
+        let (manager, _temp) = test_config_manager();
+
+        let service = Service::new("api".to_string(), "serve".to_string());
+        let healthy = Health {
+            status: HealthStatus::Healthy,
+            latency_ms: Some(0.2),
+            error: None,
+        };
+        let unhealthy = Health {
+            status: HealthStatus::Unhealthy,
+            latency_ms: None,
+            error: Some("port 8080 is not accepting connections".to_string()),
+        };
+
+        let recorded: Vec<EventKind> = [
+            Some(&healthy),
+            Some(&healthy),
+            Some(&unhealthy),
+            Some(&unhealthy),
+            Some(&healthy),
+            None,
+        ]
+        .into_iter()
+        .filter_map(|health| manager.record_health(&service, health).unwrap())
+        .map(|event| event.kind)
+        .collect();
+        assert_eq!(recorded, vec![EventKind::Unhealthy, EventKind::Healthy]);
+
+        let events = manager.events(&EventFilter::default(), 10).unwrap();
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![EventKind::Unhealthy, EventKind::Healthy]);
+        assert_eq!(events[0].error, unhealthy.error);
+        assert!(events[0].actor.is_none());
+        assert!(manager
+            .load_state()
+            .service(&service.id)
+            .unwrap()
+            .health
+            .is_none());
+    }
+
+    #[test]
+    fn test_launch_env_allocates_and_interpolates_ports() {
+        use crate::models::PortSpec;
+
+        let (manager, _temp) = test_config_manager();
+
+        let mut api = Service::new("api".to_string(), "serve".to_string());
+        api.ports = vec![PortSpec::slot("http"), PortSpec::slot("debug")];
+        api.env.insert(
+            "URL".to_string(),
+            "http://localhost:${ports.http}".to_string(),
+        );
+        api.env.insert("PORT_DEBUG".to_string(), "9229".to_string());
+        let mut web = Service::new("web".to_string(), "serve".to_string());
+        web.ports = vec![PortSpec::tcp(4000)];
+        web.env
+            .insert("API".to_string(), "localhost:${api.ports.http}".to_string());
+        manager.add_service(api.clone()).unwrap();
+        manager.add_service(web.clone()).unwrap();
+
+        let env = manager.launch_env(&api).unwrap();
+        let http: u16 = env["PORT_HTTP"].parse().unwrap();
+        assert_eq!(env["PORT"], env["PORT_HTTP"]);
+        assert_eq!(env["URL"], format!("http://localhost:{}", http));
+        // Set explicitly, so not overridden
+        assert_eq!(env["PORT_DEBUG"], "9229");
+
+        // The assignment is kept for the service and visible to others
+        assert_eq!(manager.assigned_ports(&api).unwrap()["http"], http);
+        let env = manager.launch_env(&web).unwrap();
+        assert_eq!(env["API"], format!("localhost:{}", http));
+        assert_eq!(env["PORT"], "4000");
+
+        // Resolved with the ports assigned now, so a change is noticed
+        manager
+            .update_state(|state| {
+                state
+                    .service_mut(api.id)
+                    .ports
+                    .insert("http".to_string(), 1)
+            })
+            .unwrap();
+        assert_eq!(manager.resolved_env(&web).unwrap()["API"], "localhost:1");
+
+        // Resolving without launching allocates nothing
+        let mut worker = Service::new("worker".to_string(), "work".to_string());
+        worker.ports = vec![PortSpec::slot("metrics")];
+        manager.add_service(worker.clone()).unwrap();
+        let env = manager.resolved_env(&worker).unwrap();
+        assert!(!env.contains_key("PORT_METRICS"));
+        assert!(manager.load_state().service(&worker.id).is_none());
+
+        web.env
+            .insert("OTHER".to_string(), "${api.ports.grpc}".to_string());
+        assert!(matches!(
+            manager.launch_env(&web),
+            Err(LarsError::UnknownPort(_))
+        ));
+    }
 }
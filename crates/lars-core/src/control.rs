@@ -0,0 +1,360 @@
+//! Control API spoken over a Unix domain socket
+//!
+//! `lars daemon` listens on `run/lars.sock` in the config directory (see
+//! [`crate::ConfigManager::control_socket_path`]) so that editor plugins and
+//! scripts can drive lars without parsing CLI output. The protocol is
+//! JSON-RPC 2.0 with one JSON object per line in each direction:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"start","params":{"name":"api"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"status":"started","name":"api","warnings":[]}}
+//! ```
+//!
+//! This module holds the wire types and the connection loop; the methods
+//! themselves are implemented by the daemon. The CLI does not go through the
+//! socket itself: every command runs its lifecycle in-process, whether or
+//! not a daemon is up.
+
+use crate::error::{LarsError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+
+/// Directory holding the control socket, only accessible to the current user
+pub const SOCKET_DIR_NAME: &str = "run";
+
+/// Control socket file name
+pub const SOCKET_FILE_NAME: &str = "lars.sock";
+
+/// A method call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// Echoed in the response; absent for notifications, which get none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+impl Request {
+    /// Create a call expecting a response
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id.into()),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// The outcome of a method call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, outcome: std::result::Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Error returned by a method call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    /// The request was not valid JSON
+    pub const PARSE_ERROR: i64 = -32700;
+    /// The request was not a valid JSON-RPC request
+    pub const INVALID_REQUEST: i64 = -32600;
+    /// No such method
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// Missing or mistyped parameters
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The operation itself failed, e.g. a service could not be started
+    pub const FAILED: i64 = -32000;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// An operation that failed
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(Self::FAILED, message)
+    }
+
+    /// Parameters that could not be decoded
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(Self::INVALID_PARAMS, message)
+    }
+
+    /// A method that does not exist
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(
+            Self::METHOD_NOT_FOUND,
+            format!("Unknown method: {}", method),
+        )
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+/// Decode the parameters of a call into a method's parameter type.
+///
+/// A call without parameters decodes like an empty object, so methods whose
+/// parameters are all optional can be called without any.
+pub fn params<T: serde::de::DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    let params = if params.is_null() {
+        Value::Object(Default::default())
+    } else {
+        params
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(e.to_string()))
+}
+
+/// Answer requests on a connection until the peer closes it.
+///
+/// `handler` is called with the method name and parameters of each request.
+pub fn serve<S, F>(stream: S, mut handler: F) -> Result<()>
+where
+    S: std::io::Read + Write,
+    F: FnMut(&str, Value) -> std::result::Result<Value, RpcError>,
+{
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Err(e) => Response::new(
+                Value::Null,
+                Err(RpcError::new(RpcError::PARSE_ERROR, e.to_string())),
+            ),
+            Ok(value) => {
+                let id = value.get("id").cloned();
+                match serde_json::from_value::<Request>(value) {
+                    Ok(request) if request.jsonrpc == "2.0" => {
+                        let outcome = handler(&request.method, request.params);
+                        match request.id {
+                            Some(id) => Response::new(id, outcome),
+                            None => continue,
+                        }
+                    }
+                    Ok(_) => Response::new(
+                        id.unwrap_or_default(),
+                        Err(RpcError::new(
+                            RpcError::INVALID_REQUEST,
+                            "Only JSON-RPC 2.0 is supported",
+                        )),
+                    ),
+                    Err(e) => Response::new(
+                        id.unwrap_or_default(),
+                        Err(RpcError::new(RpcError::INVALID_REQUEST, e.to_string())),
+                    ),
+                }
+            }
+        };
+
+        let stream = reader.get_mut();
+        serde_json::to_writer(&mut *stream, &response)
+            .map_err(|e| LarsError::Control(e.to_string()))?;
+        stream.write_all(b"\n")?;
+        stream.flush()?;
+    }
+}
+
+/// Listen on the control socket.
+///
+/// A socket left behind by a daemon that is gone is replaced; fails if
+/// another daemon is still answering on it. The socket's directory is
+/// created (or restricted) to mode 0700 before binding, so the socket is
+/// never reachable by other users, whatever the umask.
+#[cfg(unix)]
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if let Some(parent) = path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)?;
+        std::fs::set_permissions(parent, std::fs::Permissions::from_mode(0o700))?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(LarsError::Control(format!(
+                "another daemon is already listening on {}",
+                path.display()
+            )));
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn echo(method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        match method {
+            "echo" => Ok(params),
+            "fail" => Err(RpcError::failed("it broke")),
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    #[test]
+    fn test_serve_over_socket() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(SOCKET_DIR_NAME).join(SOCKET_FILE_NAME);
+        let listener = bind(&path).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, echo).unwrap();
+        });
+
+        let stream = UnixStream::connect(&path).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut call = |id: u64, method: &str, params: Value| {
+            let request = Request::new(id, method, params);
+            writeln!(&stream, "{}", serde_json::to_string(&request).unwrap()).unwrap();
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            serde_json::from_str::<Response>(&line).unwrap()
+        };
+
+        let response = call(1, "echo", json!({"name": "api"}));
+        assert_eq!(response.id, json!(1));
+        assert_eq!(response.result, Some(json!({"name": "api"})));
+        let response = call(2, "fail", Value::Null);
+        assert_eq!(response.error.unwrap().message, "it broke");
+        let response = call(3, "nope", Value::Null);
+        assert_eq!(response.error.unwrap().code, RpcError::METHOD_NOT_FOUND);
+
+        // The server returns once both halves of the connection are closed
+        drop(reader);
+        drop(stream);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_malformed_requests_and_notifications() {
+        let input = concat!(
+            "not json\n",
+            "{\"jsonrpc\":\"2.0\",\"method\":\"echo\"}\n",
+            "{\"jsonrpc\":\"1.0\",\"id\":7,\"method\":\"echo\"}\n",
+        );
+        let mut stream = std::io::Cursor::new(input.as_bytes().to_vec());
+        serve(&mut stream, echo).unwrap();
+
+        let output = &stream.get_ref()[input.len()..];
+        let responses: Vec<Response> = output
+            .split(|b| *b == b'\n')
+            .filter(|l| !l.is_empty())
+            .map(|l| serde_json::from_slice(l).unwrap())
+            .collect();
+
+        // The notification gets no response
+        assert_eq!(responses.len(), 2);
+        assert_eq!(
+            responses[0].error.as_ref().unwrap().code,
+            RpcError::PARSE_ERROR
+        );
+        assert_eq!(responses[1].id, json!(7));
+        assert_eq!(
+            responses[1].error.as_ref().unwrap().code,
+            RpcError::INVALID_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_bind_replaces_stale_socket() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(SOCKET_FILE_NAME);
+
+        drop(bind(&path).unwrap());
+        assert!(path.exists());
+        let listener = bind(&path).unwrap();
+
+        let err = bind(&path).unwrap_err();
+        assert!(err.to_string().contains("already listening"));
+        drop(listener);
+    }
+
+    #[test]
+    fn test_bind_restricts_socket_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join(SOCKET_DIR_NAME);
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = dir.join(SOCKET_FILE_NAME);
+        let _listener = bind(&path).unwrap();
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(mode(&path), 0o600);
+    }
+
+    #[test]
+    fn test_params_default_to_empty_object() {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(default)]
+            all: bool,
+        }
+
+        let decoded: Params = params(Value::Null).unwrap();
+        assert!(!decoded.all);
+        let err = params::<Params>(json!({"all": "yes"})).err().unwrap();
+        assert_eq!(err.code, RpcError::INVALID_PARAMS);
+    }
+}
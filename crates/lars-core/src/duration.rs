@@ -0,0 +1,175 @@
+//! Human-friendly durations such as `90s`, `10m` or `1h30m`
+
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Parse a duration made of `<number><unit>` parts.
+///
+/// Units are `ms`, `s`, `m`, `h` and `d`; parts can be combined
+/// (`1h30m`). A bare number is taken as seconds.
+///
+/// # Examples
+///
+/// ```
+/// use lars_core::duration::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+/// assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+/// assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let invalid = || format!("Invalid duration: {}", input);
+
+    if input.is_empty() {
+        return Err(invalid());
+    }
+
+    if let Ok(secs) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(invalid());
+        }
+        let value: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let part = match &rest[..unit_len] {
+            "ms" => Some(Duration::from_millis(value)),
+            "s" => Some(Duration::from_secs(value)),
+            "m" => value.checked_mul(60).map(Duration::from_secs),
+            "h" => value.checked_mul(3600).map(Duration::from_secs),
+            "d" => value.checked_mul(86400).map(Duration::from_secs),
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit_len..];
+        total = part
+            .and_then(|part| total.checked_add(part))
+            .ok_or_else(invalid)?;
+    }
+
+    Ok(total)
+}
+
+/// Parse a point in time: an RFC 3339 timestamp, or a duration meaning that
+/// long before `now` (`10m` is ten minutes ago).
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use lars_core::duration::parse_time;
+///
+/// let now = Utc.with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap();
+/// assert_eq!(
+///     parse_time("10m", now),
+///     Ok(Utc.with_ymd_and_hms(2026, 3, 10, 8, 50, 0).unwrap())
+/// );
+/// assert_eq!(
+///     parse_time("2026-03-10T08:00:00+01:00", now),
+///     Ok(Utc.with_ymd_and_hms(2026, 3, 10, 7, 0, 0).unwrap())
+/// );
+/// ```
+pub fn parse_time(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(input.trim()) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let ago = parse_duration(input).map_err(|_| {
+        format!(
+            "Invalid time: {} (expected a duration or RFC 3339 timestamp)",
+            input
+        )
+    })?;
+    chrono::Duration::from_std(ago)
+        .ok()
+        .and_then(|ago| now.checked_sub_signed(ago))
+        .ok_or_else(|| format!("Invalid time: {}", input))
+}
+
+/// Format a duration compactly using its two largest units, e.g. `2h5m`
+///
+/// # Examples
+///
+/// ```
+/// use lars_core::duration::format_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+/// assert_eq!(format_duration(Duration::from_secs(7500)), "2h5m");
+/// assert_eq!(format_duration(Duration::from_secs(90000)), "1d1h");
+/// ```
+pub fn format_duration(duration: Duration) -> String {
+    const UNITS: [(u64, &str); 4] = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+
+    let secs = duration.as_secs();
+    let Some(idx) = UNITS.iter().position(|(size, _)| secs >= *size) else {
+        return "0s".to_string();
+    };
+
+    let (size, unit) = UNITS[idx];
+    let mut out = format!("{}{}", secs / size, unit);
+
+    if let Some((next_size, next_unit)) = UNITS.get(idx + 1) {
+        let next = (secs % size) / next_size;
+        if next > 0 {
+            out.push_str(&format!("{}{}", next, next_unit));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(172800)));
+        assert_eq!(parse_duration(" 1h30m "), Ok(Duration::from_secs(5400)));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_overflow() {
+        assert!(parse_duration("999999999999999999d").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_invalid() {
+        assert!(parse_time("yesterday", Utc::now()).is_err());
+        assert!(parse_time("2026-03-10", Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_secs(60)), "1m");
+        assert_eq!(format_duration(Duration::from_secs(3605)), "1h");
+        assert_eq!(format_duration(Duration::from_secs(3660)), "1h1m");
+    }
+}
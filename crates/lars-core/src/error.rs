@@ -44,6 +44,42 @@ pub enum LarsError {
     /// Process execution failed
     #[error("Process execution failed: {0}")]
     ProcessFailed(String),
+
+    /// A required lifecycle hook failed
+    #[error("Hook failed: {0}")]
+    HookFailed(String),
+
+    /// File watching could not be set up
+    #[error("Watch error: {0}")]
+    Watch(String),
+
+    /// A port the service declares is already taken
+    #[error("Port conflict: {0}")]
+    PortConflict(String),
+
+    /// An env value refers to a port that is not declared
+    #[error("Unknown port reference: {0}")]
+    UnknownPort(String),
+
+    /// A task that was waited for exited with a non-zero status
+    #[error("Task '{name}' exited with status {code}")]
+    TaskFailed { name: String, code: i32 },
+
+    /// A task that was waited for ran past the task timeout and was stopped
+    #[error("Task '{name}' did not finish within {secs}s and was stopped")]
+    TaskTimeout { name: String, secs: u64 },
+
+    /// A task that was waited for was stopped before it finished
+    #[error("Task '{0}' was stopped before it finished")]
+    TaskStopped(String),
+
+    /// A log search pattern or condition could not be parsed
+    #[error("Invalid log query: {0}")]
+    InvalidQuery(String),
+
+    /// A control API request could not be made or was refused
+    #[error("Control API error: {0}")]
+    Control(String),
 }
 
 /// Validation errors for input sanitization
@@ -77,6 +113,10 @@ pub enum ConfigError {
     #[error("Failed to parse config: {0}")]
     ParseError(#[from] serde_json::Error),
 
+    /// Runtime state file exists but cannot be parsed
+    #[error("Failed to parse runtime state {}: {1}", .0.display())]
+    StateParseError(std::path::PathBuf, #[source] serde_json::Error),
+
     /// Config directory is not writable
     #[error("Config directory is not writable: {0}")]
     NotWritable(String),
@@ -0,0 +1,294 @@
+//! Service event history
+//!
+//! Every change to a service — being added, edited, renamed, started,
+//! stopped, exiting on its own or failing to start — is appended as one JSON
+//! line to `events.jsonl` in the state directory (see
+//! [`crate::ConfigManager::events_path`]). The file is only ever appended to,
+//! so it answers questions like "when did the worker die last night?" long
+//! after the fact.
+//!
+//! ```text
+//! {"time":"2026-03-10T02:14:09Z","kind":"exited","service":"worker","exit_code":137}
+//! ```
+
+use crate::error::Result;
+use crate::logs::ReverseLines;
+use crate::models::Service;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Event history file name
+pub const EVENTS_FILE_NAME: &str = "events.jsonl";
+
+/// What happened
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// The service was added to the configuration
+    Added,
+    /// The service was removed from the configuration
+    Removed,
+    /// The service definition was changed
+    Updated,
+    /// The service was renamed
+    Renamed,
+    /// The service was enabled
+    Enabled,
+    /// The service was disabled
+    Disabled,
+    /// The service was started
+    Started,
+    /// The service was stopped by lars
+    Stopped,
+    /// The service's process exited on its own
+    Exited,
+    /// Starting the service failed
+    Failed,
+    /// The running service stopped accepting connections on its ports
+    Unhealthy,
+    /// The service accepts connections again after being unhealthy
+    Healthy,
+    /// Services were imported from a file
+    Imported,
+    /// An application setting was changed
+    Configured,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EventKind::Added => "added",
+            EventKind::Removed => "removed",
+            EventKind::Updated => "updated",
+            EventKind::Renamed => "renamed",
+            EventKind::Enabled => "enabled",
+            EventKind::Disabled => "disabled",
+            EventKind::Started => "started",
+            EventKind::Stopped => "stopped",
+            EventKind::Exited => "exited",
+            EventKind::Failed => "failed",
+            EventKind::Unhealthy => "unhealthy",
+            EventKind::Healthy => "healthy",
+            EventKind::Imported => "imported",
+            EventKind::Configured => "configured",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A recorded event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Event {
+    /// When it happened
+    pub time: DateTime<Utc>,
+    /// What happened
+    pub kind: EventKind,
+    /// Name of the service at the time, for service events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// ID of the service, for service events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<Uuid>,
+    /// Exit code, for services that exited on their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Why an operation failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Further details, such as a service's previous name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// What made the change (e.g. `lars start`, `lars daemon`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// The user lars was running as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl Event {
+    /// Create an event that is not about a particular service
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            time: Utc::now(),
+            kind,
+            service: None,
+            service_id: None,
+            exit_code: None,
+            error: None,
+            detail: None,
+            actor: None,
+            user: None,
+        }
+    }
+
+    /// Create an event about a service
+    pub fn for_service(kind: EventKind, service: &Service) -> Self {
+        Self {
+            service: Some(service.name.clone()),
+            service_id: Some(service.id),
+            ..Self::new(kind)
+        }
+    }
+
+    /// Set when the event happened
+    pub fn at(mut self, time: DateTime<Utc>) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Set the exit code
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    /// Set the error
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    /// Set the details
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Which events to read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    /// Only events recorded under this service name
+    pub service: Option<String>,
+    /// Only events about the service with this ID
+    pub service_id: Option<Uuid>,
+    /// Only events at or after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl EventFilter {
+    /// Check whether an event passes the filter
+    pub fn matches(&self, event: &Event) -> bool {
+        let service = match (&self.service_id, &self.service) {
+            (Some(id), _) => event.service_id == Some(*id),
+            (None, Some(name)) => event.service.as_ref() == Some(name),
+            (None, None) => true,
+        };
+
+        service && self.since.is_none_or(|since| event.time >= since)
+    }
+}
+
+/// Append an event to an event history file
+pub fn append_event(path: &Path, event: &Event) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let line = serde_json::to_string(event).map_err(crate::error::ConfigError::ParseError)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // One write per line, so concurrent writers do not interleave
+    file.write_all(format!("{}\n", line).as_bytes())?;
+    Ok(())
+}
+
+/// Read the last `limit` events passing `filter`, oldest first.
+///
+/// The file is read backwards, stopping once `limit` events are found.
+/// Lines that are not events are skipped.
+pub fn read_events(path: &Path, filter: &EventFilter, limit: usize) -> Result<Vec<Event>> {
+    let Some(lines) = ReverseLines::open(path)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut events = Vec::new();
+    for line in lines {
+        if events.len() == limit {
+            break;
+        }
+        let Ok(event) = serde_json::from_str::<Event>(&line?) else {
+            continue;
+        };
+        if filter.matches(&event) {
+            events.push(event);
+        }
+    }
+    events.reverse();
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_append_and_read_events() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state").join(EVENTS_FILE_NAME);
+        let api = Service::new("api".to_string(), "npm start".to_string());
+        let worker = Service::new("worker".to_string(), "npm run worker".to_string());
+
+        assert!(read_events(&path, &EventFilter::default(), 10)
+            .unwrap()
+            .is_empty());
+
+        for (secs, event) in [
+            (100, Event::for_service(EventKind::Added, &api)),
+            (200, Event::for_service(EventKind::Started, &worker)),
+            (
+                300,
+                Event::for_service(EventKind::Exited, &worker).with_exit_code(137),
+            ),
+            (
+                400,
+                Event::new(EventKind::Configured).with_detail("log_retain = 3"),
+            ),
+        ] {
+            let event = event.at(Utc.timestamp_opt(secs, 0).unwrap());
+            append_event(&path, &event).unwrap();
+        }
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not an event\n")
+            .unwrap();
+
+        let all = read_events(&path, &EventFilter::default(), 10).unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].kind, EventKind::Added);
+
+        let last = read_events(&path, &EventFilter::default(), 1).unwrap();
+        assert_eq!(last[0].kind, EventKind::Configured);
+
+        let filter = EventFilter {
+            service: Some("worker".to_string()),
+            ..Default::default()
+        };
+        let worker_events = read_events(&path, &filter, 10).unwrap();
+        assert_eq!(worker_events.len(), 2);
+        assert_eq!(worker_events[1].exit_code, Some(137));
+
+        let filter = EventFilter {
+            since: Some(Utc.timestamp_opt(250, 0).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(read_events(&path, &filter, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_event_serialization_skips_empty_fields() {
+        let event = Event::new(EventKind::Imported).at(Utc.timestamp_opt(0, 0).unwrap());
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert_eq!(json, r#"{"time":"1970-01-01T00:00:00Z","kind":"imported"}"#);
+    }
+}
@@ -0,0 +1,187 @@
+//! Health of running services
+//!
+//! A service that declares TCP ports (see [`crate::PortSpec`]) is healthy
+//! while every one of them accepts connections on localhost. Services
+//! without TCP ports have no health beyond running or not.
+
+use crate::models::{Protocol, Service};
+use crate::state::RuntimeState;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// How long a port may take to accept a connection
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long after a start a service may take to open its ports before it
+/// counts as unhealthy
+pub const STARTUP_GRACE: Duration = Duration::from_secs(30);
+
+/// Whether a running service answers on its ports
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Unhealthy,
+}
+
+impl std::fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthStatus::Healthy => write!(f, "healthy"),
+            HealthStatus::Unhealthy => write!(f, "unhealthy"),
+        }
+    }
+}
+
+/// Result of checking a service's ports
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Health {
+    pub status: HealthStatus,
+    /// Time taken by the slowest port to accept a connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    /// Why the service is unhealthy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// TCP ports of a service: its fixed ports and the ports assigned to its
+/// named slots (see [`crate::ConfigManager::assigned_ports`]). Slots without
+/// an assignment are left out.
+pub fn tcp_ports(service: &Service, assigned: &BTreeMap<String, u16>) -> Vec<u16> {
+    service
+        .ports
+        .iter()
+        .filter(|spec| spec.protocol == Protocol::Tcp)
+        .filter_map(|spec| {
+            spec.port
+                .or_else(|| assigned.get(spec.name.as_deref()?).copied())
+        })
+        .collect()
+}
+
+/// Check that every port accepts a connection on localhost.
+///
+/// Returns `None` when there are no ports to check.
+pub fn probe(ports: &[u16], timeout: Duration) -> Option<Health> {
+    if ports.is_empty() {
+        return None;
+    }
+
+    let mut slowest = Duration::ZERO;
+    for &port in ports {
+        match connect(port, timeout) {
+            Some(latency) => slowest = slowest.max(latency),
+            None => {
+                return Some(Health {
+                    status: HealthStatus::Unhealthy,
+                    latency_ms: None,
+                    error: Some(format!("port {} is not accepting connections", port)),
+                })
+            }
+        }
+    }
+
+    Some(Health {
+        status: HealthStatus::Healthy,
+        latency_ms: Some(slowest.as_secs_f64() * 1000.0),
+        error: None,
+    })
+}
+
+/// Check a running service's ports, using the slot assignments in `state`.
+///
+/// Returns `None` when the service has no TCP ports to check, or when its
+/// ports are not open yet within [`STARTUP_GRACE`] of lars starting it.
+pub fn check(service: &Service, state: &RuntimeState) -> Option<Health> {
+    let entry = state.service(&service.id);
+    let assigned = entry.map(|s| s.ports.clone()).unwrap_or_default();
+    let health = probe(&tcp_ports(service, &assigned), DEFAULT_TIMEOUT)?;
+
+    let starting = entry
+        .and_then(|s| s.launch.as_ref())
+        .and_then(|launch| (Utc::now() - launch.started_at).to_std().ok())
+        .is_some_and(|uptime| uptime < STARTUP_GRACE);
+    if health.status == HealthStatus::Unhealthy && starting {
+        return None;
+    }
+    Some(health)
+}
+
+/// Connect to a port on any localhost address, returning how long it took
+fn connect(port: u16, timeout: Duration) -> Option<Duration> {
+    let addrs = ("localhost", port).to_socket_addrs().ok()?;
+    addrs.into_iter().find_map(|addr| {
+        let started = Instant::now();
+        TcpStream::connect_timeout(&addr, timeout)
+            .ok()
+            .map(|_| started.elapsed())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PortSpec;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_tcp_ports_resolve_slots() {
+        let mut service = Service::new("api".to_string(), "serve".to_string());
+        service.ports = vec![
+            PortSpec::tcp(8080),
+            PortSpec::slot("http"),
+            PortSpec::slot("debug"),
+            "5353/udp".parse().unwrap(),
+        ];
+        let assigned = BTreeMap::from([("http".to_string(), 41000)]);
+
+        assert_eq!(tcp_ports(&service, &assigned), vec![8080, 41000]);
+    }
+
+    #[test]
+    fn test_check_ports() {
+        assert!(probe(&[], DEFAULT_TIMEOUT).is_none());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let health = probe(&[port], DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert!(health.latency_ms.is_some());
+
+        drop(listener);
+        let health = probe(&[port], DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(health.status, HealthStatus::Unhealthy);
+        assert!(health.error.unwrap().contains(&port.to_string()));
+    }
+
+    #[test]
+    fn test_check_allows_startup_grace() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let mut service = Service::new("api".to_string(), "serve".to_string());
+        service.ports = vec![PortSpec::tcp(port)];
+        let mut state = RuntimeState::default();
+        assert_eq!(
+            check(&service, &state).unwrap().status,
+            HealthStatus::Unhealthy
+        );
+
+        // Just started: not open yet is not unhealthy
+        let mut launch = crate::state::LaunchRecord::new(&service);
+        state.service_mut(service.id).launch = Some(launch.clone());
+        assert!(check(&service, &state).is_none());
+
+        launch.started_at = Utc::now() - chrono::Duration::minutes(5);
+        state.service_mut(service.id).launch = Some(launch);
+        assert_eq!(
+            check(&service, &state).unwrap().status,
+            HealthStatus::Unhealthy
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Lifecycle hooks for services
+//!
+//! Hooks are shell commands run around a service's start and stop, in the
+//! service's working directory and environment, with a timeout. Their output
+//! is appended to a per-service hook log (see
+//! [`crate::ConfigManager::hook_log_path_for_service`]).
+
+use crate::error::{LarsError, Result};
+use crate::models::{Hook, Service};
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often a running hook is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The point in a service's lifecycle at which a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    PreStart,
+    PostStart,
+    PreStop,
+    PostStop,
+}
+
+impl HookStage {
+    /// Get the hook configured for this stage, if any
+    pub fn hook<'a>(&self, service: &'a Service) -> Option<&'a Hook> {
+        match self {
+            HookStage::PreStart => service.pre_start.as_ref(),
+            HookStage::PostStart => service.post_start.as_ref(),
+            HookStage::PreStop => service.pre_stop.as_ref(),
+            HookStage::PostStop => service.post_stop.as_ref(),
+        }
+    }
+}
+
+impl std::fmt::Display for HookStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookStage::PreStart => write!(f, "pre_start"),
+            HookStage::PostStart => write!(f, "post_start"),
+            HookStage::PreStop => write!(f, "pre_stop"),
+            HookStage::PostStop => write!(f, "post_stop"),
+        }
+    }
+}
+
+/// Result of running a hook
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookOutcome {
+    pub stage: HookStage,
+    /// Exit code, if the hook exited normally
+    pub exit_code: Option<i32>,
+    /// Whether the hook was killed for exceeding its timeout
+    pub timed_out: bool,
+    pub duration: Duration,
+}
+
+impl HookOutcome {
+    /// Whether the hook completed with exit code 0
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+
+    /// Describe how the hook finished, e.g. "exited with status 1"
+    pub fn describe(&self) -> String {
+        if self.timed_out {
+            format!("timed out after {}s", self.duration.as_secs())
+        } else {
+            match self.exit_code {
+                Some(code) => format!("exited with status {}", code),
+                None => "was terminated by a signal".to_string(),
+            }
+        }
+    }
+}
+
+/// Run the hook for `stage`, if the service has one.
+///
+/// Returns `Ok(None)` when no hook is configured. A failing required hook
+/// yields [`LarsError::HookFailed`]; a failing optional hook is returned as
+/// an unsuccessful outcome so the caller can warn about it.
+pub fn run_hook(
+    service: &Service,
+    stage: HookStage,
+    log_path: &Path,
+) -> Result<Option<HookOutcome>> {
+    let Some(hook) = stage.hook(service) else {
+        return Ok(None);
+    };
+
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+    writeln!(
+        log,
+        "[{}] {} {}: {}",
+        Utc::now().to_rfc3339(),
+        service.name,
+        stage,
+        hook.command
+    )?;
+
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", &hook.command])
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log.try_clone()?)
+        .envs(&service.env);
+
+    if let Some(cwd) = &service.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    // Run in its own process group so a timeout can kill the whole pipeline
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let start = Instant::now();
+    let timeout = Duration::from_secs(hook.timeout_secs);
+    let mut child = cmd.spawn()?;
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if start.elapsed() >= timeout {
+            kill_process_group(&mut child);
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let outcome = HookOutcome {
+        stage,
+        exit_code: status.and_then(|s| s.code()),
+        timed_out: status.is_none(),
+        duration: start.elapsed(),
+    };
+
+    writeln!(
+        log,
+        "[{}] {} {} {}",
+        Utc::now().to_rfc3339(),
+        service.name,
+        stage,
+        outcome.describe()
+    )?;
+
+    if !outcome.success() && !hook.optional {
+        return Err(LarsError::HookFailed(format!(
+            "{} hook for '{}' {}",
+            stage,
+            service.name,
+            outcome.describe()
+        )));
+    }
+
+    Ok(Some(outcome))
+}
+
+fn kill_process_group(child: &mut Child) {
+    // The hook leads its own process group, so this also reaches anything
+    // it started
+    #[cfg(unix)]
+    {
+        // SAFETY: killpg only sends a signal to the group
+        let killed = unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) } == 0;
+        if killed {
+            return;
+        }
+    }
+
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn service_with_hook(stage: HookStage, hook: Hook, cwd: &Path) -> Service {
+        let mut service = Service::new("test".to_string(), "sleep 60".to_string());
+        service.cwd = Some(cwd.to_path_buf());
+        service
+            .env
+            .insert("GREETING".to_string(), "hello".to_string());
+        match stage {
+            HookStage::PreStart => service.pre_start = Some(hook),
+            HookStage::PostStart => service.post_start = Some(hook),
+            HookStage::PreStop => service.pre_stop = Some(hook),
+            HookStage::PostStop => service.post_stop = Some(hook),
+        }
+        service
+    }
+
+    #[test]
+    fn test_no_hook_configured() {
+        let temp = TempDir::new().unwrap();
+        let service = Service::new("test".to_string(), "sleep 60".to_string());
+        let log = temp.path().join("hooks.log");
+
+        let outcome = run_hook(&service, HookStage::PreStart, &log).unwrap();
+        assert!(outcome.is_none());
+        assert!(!log.exists());
+    }
+
+    #[test]
+    fn test_hook_runs_in_service_cwd_and_env() {
+        let temp = TempDir::new().unwrap();
+        let hook = Hook::new("echo $GREETING > marker".to_string());
+        let service = service_with_hook(HookStage::PreStart, hook, temp.path());
+        let log = temp.path().join("hooks.log");
+
+        let outcome = run_hook(&service, HookStage::PreStart, &log)
+            .unwrap()
+            .unwrap();
+        assert!(outcome.success());
+
+        let marker = std::fs::read_to_string(temp.path().join("marker")).unwrap();
+        assert_eq!(marker.trim(), "hello");
+        assert!(std::fs::read_to_string(&log).unwrap().contains("pre_start"));
+    }
+
+    #[test]
+    fn test_required_hook_failure_is_error() {
+        let temp = TempDir::new().unwrap();
+        let hook = Hook::new("exit 3".to_string());
+        let service = service_with_hook(HookStage::PreStart, hook, temp.path());
+
+        let err = run_hook(
+            &service,
+            HookStage::PreStart,
+            &temp.path().join("hooks.log"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, LarsError::HookFailed(_)));
+        assert!(err.to_string().contains("exited with status 3"));
+    }
+
+    #[test]
+    fn test_optional_hook_failure_is_outcome() {
+        let temp = TempDir::new().unwrap();
+        let mut hook = Hook::new("exit 1".to_string());
+        hook.optional = true;
+        let service = service_with_hook(HookStage::PostStop, hook, temp.path());
+
+        let outcome = run_hook(
+            &service,
+            HookStage::PostStop,
+            &temp.path().join("hooks.log"),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(!outcome.success());
+        assert_eq!(outcome.exit_code, Some(1));
+    }
+
+    #[test]
+    fn test_hook_timeout() {
+        let temp = TempDir::new().unwrap();
+        let mut hook = Hook::new("sleep 30".to_string());
+        hook.timeout_secs = 1;
+        let service = service_with_hook(HookStage::PreStop, hook, temp.path());
+
+        let start = Instant::now();
+        let err =
+            run_hook(&service, HookStage::PreStop, &temp.path().join("hooks.log")).unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -22,18 +22,67 @@
 //! let log_path = config.log_path_for_service(&service.id);
 //! runner.start(&service, &log_path).unwrap();
 //! ```
+//!
+//! The `async` feature adds `AsyncRunner`, with the same operations as
+//! futures for applications running on tokio. The `testing` feature adds
+//! [`testing::FakeRunner`], which keeps services in memory for tests.
 
+#[cfg(feature = "async")]
+pub mod async_runner;
+pub mod batch;
 pub mod config;
+pub mod control;
+pub mod duration;
 pub mod error;
+pub mod events;
+pub mod health;
+pub mod hooks;
+pub mod logs;
+pub mod manager;
+pub mod metrics;
 pub mod models;
+pub mod notify;
+pub mod ports;
+pub mod relay;
+pub mod rotation;
 pub mod runner;
+pub mod schedule;
+pub mod state;
+pub mod stats;
+pub mod status;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod validation;
+pub mod watch;
 
 // Re-export commonly used types
+#[cfg(feature = "async")]
+pub use async_runner::{create_async_runner, AsyncRunner, AsyncTmuxRunner};
 pub use config::ConfigManager;
 pub use error::{ConfigError, LarsError, Result, ValidationError};
-pub use models::{AppConfig, AppSettings, RunnerType, Service, ShutdownBehavior};
-pub use runner::{create_runner, Runner, TmuxRunner};
+pub use events::{Event, EventFilter, EventKind};
+pub use health::{Health, HealthStatus};
+pub use hooks::{run_hook, HookOutcome, HookStage};
+pub use logs::{LineSearch, LogFollower, LogLine, LogQuery, ReverseLines, Shown};
+pub use manager::{BatchReport, ServiceManager, StartOutcome, StopOutcome};
+pub use metrics::{MetricsCollector, ServiceMetrics};
+pub use models::{
+    AppConfig, AppSettings, Hook, LogFormat, LogMode, OverlapPolicy, PortSpec, Protocol,
+    RunnerType, ScheduleConfig, Service, ServiceKind, ShutdownBehavior, WatchConfig,
+};
+pub use notify::{Notification, Notifier, Trigger};
+pub use ports::{check_ports, ListeningPort, SocketTable};
+pub use relay::{LogFilter, LogRecord, LogStream};
+pub use rotation::RotationPolicy;
+pub use runner::{
+    create_runner, create_runner_with, prepare_launch, CommandExecutor, CommandOutput, Runner,
+    RunnerCapabilities, SystemExecutor, TmuxRunner,
+};
+pub use schedule::{Schedule, ScheduledAction, Scheduler};
+pub use state::{ExitRecord, LaunchFingerprint, LaunchRecord, RuntimeState, ServiceState};
+pub use stats::{ProcessStats, ProcessTable};
+pub use status::{ServiceStatus, StatusChange, StatusReport, StatusTracker};
 pub use validation::{
     generate_service_name, sanitize_for_shell, validate_not_empty, validate_service_name,
 };
+pub use watch::{Debouncer, ServiceWatcher, WatchMatcher};
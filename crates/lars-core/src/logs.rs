@@ -0,0 +1,877 @@
+//! Reading and following service logs
+//!
+//! [`LogFollower`] tails any number of log files natively, coping with logs
+//! that are truncated (rotation, restarts in truncate mode) or replaced.
+//! [`LogLine`] gives every line an optional timestamp so lines from several
+//! services can be interleaved in time order. [`ReverseLines`] reads a log
+//! backwards from its end, so showing the last lines of a large log does not
+//! mean reading all of it.
+//!
+//! Searches stream lines through a [`LineSearch`] one at a time, which keeps
+//! only the few lines needed for context. Besides a regex, lines can be
+//! matched on the fields of services that log JSON objects (`level=error`),
+//! and those fields can be picked out for display (`ts,msg`).
+
+use crate::error::{LarsError, Result};
+use crate::relay::{LogRecord, LogStream};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Longest line returned by [`ReverseLines`]; longer lines are cut short
+pub const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// How much of a log is read at a time when reading backwards
+const SCAN_CHUNK_BYTES: u64 = 8 * 1024;
+
+/// A line from one of several logs
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    /// Index of the log the line came from
+    pub source: usize,
+    /// When the line was logged, for structured logs
+    pub ts: Option<DateTime<Utc>>,
+    /// Which stream the line was written to, for structured logs
+    pub stream: Option<LogStream>,
+    /// The logged text
+    pub text: String,
+}
+
+impl LogLine {
+    /// Create a line from a raw log line, decoding structured records
+    pub fn parse(source: usize, raw: &str) -> Self {
+        match LogRecord::parse(raw) {
+            Some(record) => Self {
+                source,
+                ts: Some(record.ts),
+                stream: Some(record.stream),
+                text: record.line,
+            },
+            None => Self {
+                source,
+                ts: None,
+                stream: None,
+                text: raw.to_string(),
+            },
+        }
+    }
+
+    /// Get the structured record for this line, if it has one
+    pub fn record(&self) -> Option<LogRecord> {
+        Some(LogRecord {
+            ts: self.ts?,
+            stream: self.stream?,
+            line: self.text.clone(),
+        })
+    }
+}
+
+/// Sort lines, or anything holding one, into time order by the timestamp
+/// `ts` returns.
+///
+/// The sort is stable, and lines without a timestamp are ordered as if they
+/// were logged at `fallback` (usually the time they were read), so raw logs
+/// keep their own order.
+pub fn interleave<T>(
+    lines: &mut [T],
+    fallback: DateTime<Utc>,
+    ts: impl Fn(&T) -> Option<DateTime<Utc>>,
+) {
+    lines.sort_by_key(|line| ts(line).unwrap_or(fallback));
+}
+
+/// Reads the lines of a log from last to first.
+///
+/// At most one chunk plus one line is held in memory at a time. Lines longer
+/// than [`MAX_LINE_BYTES`] are cut short, and invalid UTF-8 is replaced with
+/// `U+FFFD`. Lines appended after the log was opened are not returned.
+#[derive(Debug)]
+pub struct ReverseLines {
+    file: File,
+    /// Unread bytes at the end of the unread part of the log
+    buf: Vec<u8>,
+    /// Offset of `buf` in the file
+    buf_start: u64,
+    done: bool,
+}
+
+impl ReverseLines {
+    /// Open a log for reading backwards, returning `None` if it does not exist
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let len = file.metadata()?.len();
+        let mut end = len;
+        if len > 0 {
+            // A trailing newline ends the last line rather than starting another
+            let mut last = [0u8];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] == b'\n' {
+                end -= 1;
+            }
+        }
+
+        Ok(Some(Self {
+            file,
+            buf: Vec::new(),
+            buf_start: end,
+            done: len == 0,
+        }))
+    }
+
+    fn next_line(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(newline) = self.buf.iter().rposition(|&b| b == b'\n') {
+                let line = self.buf.split_off(newline + 1);
+                self.buf.truncate(newline);
+                return Ok(line);
+            }
+            if self.buf_start == 0 {
+                self.done = true;
+                return Ok(std::mem::take(&mut self.buf));
+            }
+            if self.buf.len() >= MAX_LINE_BYTES {
+                return self.long_line();
+            }
+
+            // Prepend the previous chunk
+            let chunk_start = self.buf_start.saturating_sub(SCAN_CHUNK_BYTES);
+            let mut chunk = self.read_range(chunk_start, self.buf_start)?;
+            chunk.append(&mut self.buf);
+            self.buf = chunk;
+            self.buf_start = chunk_start;
+        }
+    }
+
+    /// Return the start of a line too long to buffer, skipping the rest
+    fn long_line(&mut self) -> io::Result<Vec<u8>> {
+        let mut start = self.buf_start;
+        while start > 0 {
+            let scan_start = start.saturating_sub(SCAN_CHUNK_BYTES);
+            let chunk = self.read_range(scan_start, start)?;
+            if let Some(newline) = chunk.iter().rposition(|&b| b == b'\n') {
+                start = scan_start + newline as u64 + 1;
+                break;
+            }
+            start = scan_start;
+        }
+
+        let head_end = (start + MAX_LINE_BYTES as u64).min(self.buf_start);
+        let mut line = self.read_range(start, head_end)?;
+        if head_end - start < MAX_LINE_BYTES as u64 {
+            line.extend_from_slice(&self.buf);
+        }
+
+        self.buf.clear();
+        match start.checked_sub(1) {
+            Some(newline) => self.buf_start = newline,
+            None => self.done = true,
+        }
+        Ok(line)
+    }
+
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity((end - start) as usize);
+        self.file.seek(SeekFrom::Start(start))?;
+        (&mut self.file).take(end - start).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl Iterator for ReverseLines {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_line() {
+            Ok(mut line) => {
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                line.truncate(MAX_LINE_BYTES);
+                Some(Ok(String::from_utf8_lossy(&line).into_owned()))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+/// Get the last `count` lines of a log, oldest first (empty if it is missing)
+pub fn tail_lines(path: &Path, count: usize) -> Result<Vec<String>> {
+    let Some(lines) = ReverseLines::open(path)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut tail = lines.take(count).collect::<Result<Vec<_>>>()?;
+    tail.reverse();
+    Ok(tail)
+}
+
+/// Get the last `count` bytes of a log as text (empty if it is missing).
+///
+/// The first line is usually partial; invalid UTF-8 is replaced with
+/// `U+FFFD`.
+pub fn tail_bytes(path: &Path, count: u64) -> Result<String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(count);
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.take(len - start).read_to_end(&mut buf)?;
+
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// How a `--where` condition compares a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+}
+
+/// A `--where FIELD=VALUE` or `--where FIELD!=VALUE` condition
+#[derive(Debug, Clone)]
+struct Condition {
+    field: String,
+    comparison: Comparison,
+    value: String,
+}
+
+impl Condition {
+    fn parse(input: &str) -> Result<Self> {
+        let (field, comparison, value) = if let Some((field, value)) = input.split_once("!=") {
+            (field, Comparison::NotEqual, value)
+        } else if let Some((field, value)) = input.split_once('=') {
+            (field, Comparison::Equal, value)
+        } else {
+            return Err(LarsError::InvalidQuery(format!(
+                "condition '{}' must be FIELD=VALUE or FIELD!=VALUE",
+                input
+            )));
+        };
+
+        if field.trim().is_empty() {
+            return Err(LarsError::InvalidQuery(format!(
+                "condition '{}' has no field",
+                input
+            )));
+        }
+
+        Ok(Self {
+            field: field.trim().to_string(),
+            comparison,
+            value: value.trim().to_string(),
+        })
+    }
+
+    /// Check the condition against a decoded line (missing fields are unequal)
+    fn matches(&self, object: Option<&Map<String, Value>>) -> bool {
+        let equal = object
+            .and_then(|o| field(o, &self.field))
+            .is_some_and(|v| display_value(v) == self.value);
+
+        match self.comparison {
+            Comparison::Equal => equal,
+            Comparison::NotEqual => !equal,
+        }
+    }
+}
+
+/// What to look for in logs and how much of each match to show
+#[derive(Debug, Default)]
+pub struct LogQuery {
+    grep: Option<Regex>,
+    conditions: Vec<Condition>,
+    fields: Vec<String>,
+    /// Lines of context shown around each match
+    pub context: usize,
+}
+
+impl LogQuery {
+    /// Build a query from a regex, `FIELD=VALUE` / `FIELD!=VALUE`
+    /// conditions, the fields to show and the lines of context to show
+    pub fn new(
+        grep: Option<&str>,
+        conditions: &[String],
+        fields: &[String],
+        context: usize,
+    ) -> Result<Self> {
+        let grep = grep
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| LarsError::InvalidQuery(e.to_string()))?;
+        let conditions = conditions
+            .iter()
+            .map(|c| Condition::parse(c))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            grep,
+            conditions,
+            fields: fields.to_vec(),
+            context,
+        })
+    }
+
+    /// Whether only some fields of each line are shown
+    pub fn projects(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    /// Check whether a line matches the pattern and every condition
+    pub fn matches(&self, line: &LogLine) -> bool {
+        if self
+            .grep
+            .as_ref()
+            .is_some_and(|re| !re.is_match(&line.text))
+        {
+            return false;
+        }
+        if self.conditions.is_empty() {
+            return true;
+        }
+
+        let object = decode(&line.text);
+        self.conditions.iter().all(|c| c.matches(object.as_ref()))
+    }
+
+    /// Pick the requested fields out of a JSON line.
+    ///
+    /// Returns `None` if no fields were requested or the line is not a JSON
+    /// object.
+    pub fn project(&self, line: &LogLine) -> Option<Map<String, Value>> {
+        if self.fields.is_empty() {
+            return None;
+        }
+        let object = decode(&line.text)?;
+
+        Some(
+            self.fields
+                .iter()
+                .map(|name| {
+                    let value = field(&object, name).cloned().unwrap_or(Value::Null);
+                    (name.clone(), value)
+                })
+                .collect(),
+        )
+    }
+
+    /// Render a line for display, showing only the requested fields
+    pub fn render(&self, line: &LogLine) -> String {
+        match self.project(line) {
+            Some(values) => self
+                .fields
+                .iter()
+                .map(|name| match values.get(name) {
+                    Some(Value::Null) | None => "-".to_string(),
+                    Some(v) => display_value(v),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => line.text.clone(),
+        }
+    }
+}
+
+/// A line selected by a search
+#[derive(Debug, Clone)]
+pub struct Shown {
+    pub line: LogLine,
+    /// Lines were skipped between the previously shown line and this one
+    pub gap: bool,
+}
+
+/// Streams the lines of one log through a query, grep-style
+#[derive(Debug)]
+pub struct LineSearch<'a> {
+    query: &'a LogQuery,
+    /// Recent unmatched lines, kept as leading context
+    before: VecDeque<(u64, LogLine)>,
+    /// Trailing context lines still to show
+    after: usize,
+    read: u64,
+    last_shown: Option<u64>,
+}
+
+impl<'a> LineSearch<'a> {
+    pub fn new(query: &'a LogQuery) -> Self {
+        Self {
+            query,
+            before: VecDeque::new(),
+            after: 0,
+            read: 0,
+            last_shown: None,
+        }
+    }
+
+    /// Feed the next line, returning the lines it causes to be shown
+    pub fn push(&mut self, line: LogLine) -> Vec<Shown> {
+        self.read += 1;
+        let n = self.read;
+
+        if self.query.matches(&line) {
+            let mut shown: Vec<Shown> = std::mem::take(&mut self.before)
+                .into_iter()
+                .map(|(n, line)| self.show(n, line))
+                .collect();
+            shown.push(self.show(n, line));
+            self.after = self.query.context;
+            return shown;
+        }
+
+        if self.after > 0 {
+            self.after -= 1;
+            return vec![self.show(n, line)];
+        }
+
+        if self.query.context > 0 {
+            if self.before.len() == self.query.context {
+                self.before.pop_front();
+            }
+            self.before.push_back((n, line));
+        }
+        Vec::new()
+    }
+
+    fn show(&mut self, n: u64, line: LogLine) -> Shown {
+        let gap = self.last_shown.is_some_and(|last| n != last + 1);
+        self.last_shown = Some(n);
+        Shown { line, gap }
+    }
+}
+
+/// Decode a line as a JSON object
+fn decode(text: &str) -> Option<Map<String, Value>> {
+    match serde_json::from_str(text.trim()) {
+        Ok(Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+/// Look up a field, following dots into nested objects (`http.status`)
+fn field<'v>(object: &'v Map<String, Value>, name: &str) -> Option<&'v Value> {
+    if let Some(value) = object.get(name) {
+        return Some(value);
+    }
+
+    let mut parts = name.split('.');
+    let mut value = object.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_object()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// Show a JSON value without quoting strings
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Follows several log files, returning complete new lines as they appear
+#[derive(Debug)]
+pub struct LogFollower {
+    files: Vec<FollowedFile>,
+}
+
+#[derive(Debug)]
+struct FollowedFile {
+    path: PathBuf,
+    file: Option<File>,
+    pos: u64,
+    #[cfg(unix)]
+    ino: u64,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// Start following `paths` from their current ends.
+    ///
+    /// Logs that do not exist yet are followed from their start once they
+    /// are created.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self::open(paths, true)
+    }
+
+    /// Start following `paths` from their start, so the first poll returns
+    /// every line already written
+    pub fn from_start(paths: Vec<PathBuf>) -> Self {
+        Self::open(paths, false)
+    }
+
+    fn open(paths: Vec<PathBuf>, at_end: bool) -> Self {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let mut followed = FollowedFile {
+                    path,
+                    file: None,
+                    pos: 0,
+                    #[cfg(unix)]
+                    ino: 0,
+                    partial: Vec::new(),
+                };
+                if followed.open() && at_end {
+                    followed.pos = followed
+                        .file
+                        .as_ref()
+                        .and_then(|f| f.metadata().ok())
+                        .map_or(0, |m| m.len());
+                }
+                followed
+            })
+            .collect();
+
+        Self { files }
+    }
+
+    /// Read any lines written since the last poll, in per-file order
+    pub fn poll(&mut self) -> Result<Vec<LogLine>> {
+        let mut lines = Vec::new();
+
+        for (source, followed) in self.files.iter_mut().enumerate() {
+            for raw in followed.read_lines()? {
+                lines.push(LogLine::parse(source, &raw));
+            }
+        }
+
+        Ok(lines)
+    }
+}
+
+impl FollowedFile {
+    /// Open the file, returning whether it exists
+    fn open(&mut self) -> bool {
+        let Ok(file) = File::open(&self.path) else {
+            return false;
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.ino = file.metadata().map_or(0, |m| m.ino());
+        }
+
+        self.file = Some(file);
+        self.pos = 0;
+        self.partial.clear();
+        true
+    }
+
+    /// Check whether the path now refers to a different file
+    fn replaced(&self) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            std::fs::metadata(&self.path).is_ok_and(|m| m.ino() != self.ino)
+        }
+
+        #[cfg(not(unix))]
+        false
+    }
+
+    fn read_lines(&mut self) -> Result<Vec<String>> {
+        if (self.file.is_none() || self.replaced()) && !self.open() {
+            return Ok(Vec::new());
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        // Truncated in place: start again from the top
+        let len = file.metadata()?.len();
+        if len < self.pos {
+            self.pos = 0;
+            self.partial.clear();
+        }
+        if len == self.pos {
+            return Ok(Vec::new());
+        }
+
+        file.seek(SeekFrom::Start(self.pos))?;
+        let mut buf = Vec::new();
+        file.take(len - self.pos).read_to_end(&mut buf)?;
+        self.pos += buf.len() as u64;
+        self.partial.extend_from_slice(&buf);
+
+        // Hold back a trailing partial line until it is complete
+        let Some(last_newline) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let rest = self.partial.split_off(last_newline + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn append(path: &std::path::Path, text: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    fn texts(lines: &[LogLine]) -> Vec<(usize, &str)> {
+        lines.iter().map(|l| (l.source, l.text.as_str())).collect()
+    }
+
+    fn lines(texts: &[&str]) -> Vec<LogLine> {
+        texts.iter().map(|t| LogLine::parse(0, t)).collect()
+    }
+
+    /// Run lines through a search, returning the shown texts and gap marks
+    fn search(query: &LogQuery, texts: &[&str]) -> Vec<(String, bool)> {
+        let mut search = LineSearch::new(query);
+        lines(texts)
+            .into_iter()
+            .flat_map(|line| search.push(line))
+            .map(|shown| (shown.line.text, shown.gap))
+            .collect()
+    }
+
+    #[test]
+    fn test_query_parse_errors() {
+        let err = LogQuery::new(Some("(unclosed"), &[], &[], 0).unwrap_err();
+        assert!(matches!(err, LarsError::InvalidQuery(_)));
+
+        let err = LogQuery::new(None, &["level".to_string()], &[], 0).unwrap_err();
+        assert!(err.to_string().contains("FIELD=VALUE or FIELD!=VALUE"));
+
+        let err = LogQuery::new(None, &[" =error".to_string()], &[], 0).unwrap_err();
+        assert!(err.to_string().contains("has no field"));
+
+        assert!(LogQuery::new(None, &["a=b=c".to_string()], &[], 0).is_ok());
+    }
+
+    #[test]
+    fn test_query_conditions() {
+        let texts = [
+            r#"{"level":"error","http":{"status":500}}"#,
+            r#"{"level":"info","http":{"status":200}}"#,
+            "plain text",
+        ];
+        let matching = |conditions: &[&str]| {
+            let conditions: Vec<String> = conditions.iter().map(|c| c.to_string()).collect();
+            let query = LogQuery::new(None, &conditions, &[], 0).unwrap();
+            lines(&texts)
+                .iter()
+                .map(|line| query.matches(line))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching(&["http.status=500"]), [true, false, false]);
+        // Lines without the field never equal it
+        assert_eq!(matching(&["level != info"]), [true, false, true]);
+        assert_eq!(matching(&["level=error", "http.status!=500"]), [false; 3]);
+    }
+
+    #[test]
+    fn test_query_render_fields() {
+        let fields = vec!["ts".to_string(), "msg".to_string()];
+        let query = LogQuery::new(None, &[], &fields, 0).unwrap();
+
+        let rendered: Vec<String> = lines(&[r#"{"ts":1,"msg":"hi"}"#, r#"{"ts":2}"#, "raw"])
+            .iter()
+            .map(|line| query.render(line))
+            .collect();
+        assert_eq!(rendered, ["1 hi", "2 -", "raw"]);
+    }
+
+    #[test]
+    fn test_search_context_and_gaps() {
+        let query = LogQuery::new(Some("match"), &[], &[], 1).unwrap();
+        let texts = [
+            "a", "b", "match 1", "c", "match 2", "d", "e", "f", "match 3",
+        ];
+
+        // Overlapping context is shown once, and a gap is marked only where
+        // lines were skipped
+        assert_eq!(
+            search(&query, &texts),
+            [
+                ("b".to_string(), false),
+                ("match 1".to_string(), false),
+                ("c".to_string(), false),
+                ("match 2".to_string(), false),
+                ("d".to_string(), false),
+                ("f".to_string(), true),
+                ("match 3".to_string(), false),
+            ]
+        );
+
+        let query = LogQuery::new(Some("match"), &[], &[], 0).unwrap();
+        assert_eq!(
+            search(&query, &["match 1", "match 2", "a", "match 3"]),
+            [
+                ("match 1".to_string(), false),
+                ("match 2".to_string(), false),
+                ("match 3".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follow_new_lines_only() {
+        let temp = TempDir::new().unwrap();
+        let a = temp.path().join("a.log");
+        let b = temp.path().join("b.log");
+        append(&a, "old\n");
+
+        let mut follower = LogFollower::new(vec![a.clone(), b.clone()]);
+        assert!(follower.poll().unwrap().is_empty());
+
+        append(&a, "one\ntw");
+        append(&b, "created later\n");
+        assert_eq!(
+            texts(&follower.poll().unwrap()),
+            vec![(0, "one"), (1, "created later")]
+        );
+
+        append(&a, "o\n");
+        assert_eq!(texts(&follower.poll().unwrap()), vec![(0, "two")]);
+    }
+
+    #[test]
+    fn test_follow_from_start() {
+        let temp = TempDir::new().unwrap();
+        let a = temp.path().join("a.log");
+        append(&a, "old\n");
+
+        let mut follower = LogFollower::from_start(vec![a.clone()]);
+        assert_eq!(texts(&follower.poll().unwrap()), vec![(0, "old")]);
+        append(&a, "new\n");
+        assert_eq!(texts(&follower.poll().unwrap()), vec![(0, "new")]);
+    }
+
+    #[test]
+    fn test_follow_truncation_and_replacement() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+        append(&log, "before rotation\n");
+
+        let mut follower = LogFollower::new(vec![log.clone()]);
+
+        // Copy-truncate rotation
+        OpenOptions::new()
+            .write(true)
+            .open(&log)
+            .unwrap()
+            .set_len(0)
+            .unwrap();
+        append(&log, "after\n");
+        assert_eq!(texts(&follower.poll().unwrap()), vec![(0, "after")]);
+
+        // Replaced by a new file
+        std::fs::remove_file(&log).unwrap();
+        append(&log, "new file\n");
+        assert_eq!(texts(&follower.poll().unwrap()), vec![(0, "new file")]);
+    }
+
+    #[test]
+    fn test_reverse_lines() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+
+        assert!(ReverseLines::open(&log).unwrap().is_none());
+        assert!(tail_lines(&log, 5).unwrap().is_empty());
+
+        // Lines spanning several scan chunks, an empty line and CRLF
+        let long = "x".repeat(SCAN_CHUNK_BYTES as usize * 2 + 10);
+        append(&log, &format!("first\n{}\n\nwindows\r\nlast\n", long));
+
+        let lines: Vec<String> = ReverseLines::open(&log)
+            .unwrap()
+            .unwrap()
+            .map(|l| l.unwrap())
+            .collect();
+        assert_eq!(lines, vec!["last", "windows", "", long.as_str(), "first"]);
+
+        assert_eq!(tail_lines(&log, 2).unwrap(), vec!["windows", "last"]);
+        assert_eq!(tail_lines(&log, 0).unwrap(), Vec::<String>::new());
+        assert_eq!(tail_lines(&log, 100).unwrap().len(), 5);
+
+        // No trailing newline
+        append(&log, "partial");
+        assert_eq!(tail_lines(&log, 2).unwrap(), vec!["last", "partial"]);
+    }
+
+    #[test]
+    fn test_reverse_lines_bounds_long_lines_and_invalid_utf8() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+
+        let mut bytes = b"before\n".to_vec();
+        bytes.extend(vec![b'y'; MAX_LINE_BYTES * 2]);
+        bytes.extend_from_slice(b"\nbad \xff byte\n");
+        std::fs::write(&log, bytes).unwrap();
+
+        let lines = tail_lines(&log, 3).unwrap();
+        assert_eq!(lines[0], "before");
+        assert_eq!(lines[1].len(), MAX_LINE_BYTES);
+        assert_eq!(lines[2], "bad \u{FFFD} byte");
+
+        assert_eq!(tail_bytes(&log, 5).unwrap(), "byte\n");
+        assert_eq!(tail_bytes(&temp.path().join("missing"), 5).unwrap(), "");
+    }
+
+    #[test]
+    fn test_interleave_by_timestamp() {
+        let record = |secs, line: &str| {
+            LogRecord {
+                ts: Utc.timestamp_opt(secs, 0).unwrap(),
+                stream: LogStream::Stdout,
+                line: line.to_string(),
+            }
+            .to_line()
+        };
+
+        let mut lines = vec![
+            LogLine::parse(0, &record(20, "second")),
+            LogLine::parse(1, &record(10, "first")),
+            LogLine::parse(1, "raw line"),
+            LogLine::parse(0, &record(30, "third")),
+        ];
+        interleave(&mut lines, Utc.timestamp_opt(25, 0).unwrap(), |l| l.ts);
+
+        let order: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(order, vec!["first", "second", "raw line", "third"]);
+        assert!(lines[0].record().is_some());
+        assert!(lines[2].record().is_none());
+    }
+}
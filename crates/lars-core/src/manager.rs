@@ -0,0 +1,749 @@
+//! Starting, stopping and restarting services
+//!
+//! [`ServiceManager`] wraps the runner calls with port checks, lifecycle
+//! hooks, launch records and the event history, so that every frontend
+//! starting services behaves the same way. Operations return what happened
+//! instead of printing it; optional hooks that failed come back as warnings.
+//!
+//! ```no_run
+//! use lars_core::{ConfigManager, ServiceManager, StartOutcome};
+//!
+//! let manager = ServiceManager::new(ConfigManager::with_defaults()?);
+//! let service = manager.config().get_service("api")?;
+//!
+//! match manager.start(&service)? {
+//!     StartOutcome::AlreadyRunning => println!("api is already running"),
+//!     outcome => outcome.warnings().iter().for_each(|w| eprintln!("{}", w)),
+//! }
+//! # Ok::<(), lars_core::LarsError>(())
+//! ```
+
+use crate::batch::{run_bounded, BatchEvent};
+use crate::config::ConfigManager;
+use crate::error::{LarsError, Result};
+use crate::events::{Event, EventKind};
+use crate::hooks::{run_hook, HookOutcome, HookStage};
+use crate::models::{LogMode, RunnerType, Service, ServiceKind};
+use crate::ports::check_ports;
+use crate::rotation::{self, RotationPolicy};
+use crate::runner::{create_runner, Runner};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often a running task is checked for completion
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What starting (or restarting) a service did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartOutcome {
+    /// The service was already running and was left alone
+    AlreadyRunning,
+    /// The service was started; a task may still be running
+    Started { warnings: Vec<String> },
+    /// The task was waited for and exited successfully
+    Completed { warnings: Vec<String> },
+}
+
+impl StartOutcome {
+    /// Failures of optional hooks
+    pub fn warnings(&self) -> &[String] {
+        match self {
+            StartOutcome::AlreadyRunning => &[],
+            StartOutcome::Started { warnings } | StartOutcome::Completed { warnings } => warnings,
+        }
+    }
+}
+
+impl std::fmt::Display for StartOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartOutcome::AlreadyRunning => write!(f, "already_running"),
+            StartOutcome::Started { .. } => write!(f, "started"),
+            StartOutcome::Completed { .. } => write!(f, "completed"),
+        }
+    }
+}
+
+/// What stopping a service did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// The service was not running
+    NotRunning,
+    /// The service was stopped
+    Stopped { warnings: Vec<String> },
+}
+
+impl StopOutcome {
+    /// Failures of stop hooks
+    pub fn warnings(&self) -> &[String] {
+        match self {
+            StopOutcome::NotRunning => &[],
+            StopOutcome::Stopped { warnings } => warnings,
+        }
+    }
+}
+
+impl std::fmt::Display for StopOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopOutcome::NotRunning => write!(f, "not_running"),
+            StopOutcome::Stopped { .. } => write!(f, "stopped"),
+        }
+    }
+}
+
+/// Results of an operation on several services, in the order they were given
+#[derive(Debug)]
+pub struct BatchReport<O> {
+    pub results: Vec<Result<O>>,
+}
+
+impl<O> BatchReport<O> {
+    /// Number of services whose outcome matches
+    pub fn count(&self, matches: impl Fn(&O) -> bool) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.as_ref().is_ok_and(&matches))
+            .count()
+    }
+
+    /// Number of services the operation failed for
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|r| r.is_err()).count()
+    }
+}
+
+/// Runs the start/stop/restart sequences for services
+pub struct ServiceManager {
+    config: ConfigManager,
+    runner: Option<Arc<dyn Runner>>,
+    /// Runners created so far, one per runner type, so that polling many
+    /// services does not check for the backend every time
+    runners: Mutex<HashMap<RunnerType, Arc<dyn Runner>>>,
+    wait_for_tasks: bool,
+}
+
+impl ServiceManager {
+    /// Create a manager using each service's own runner, which waits for
+    /// tasks to complete when starting them
+    pub fn new(config: ConfigManager) -> Self {
+        Self {
+            config,
+            runner: None,
+            runners: Mutex::new(HashMap::new()),
+            wait_for_tasks: true,
+        }
+    }
+
+    /// Use one runner for every service instead of each service's own
+    pub fn with_runner(mut self, runner: Arc<dyn Runner>) -> Self {
+        self.runner = Some(runner);
+        self
+    }
+
+    /// Whether starting a task waits for it to complete.
+    ///
+    /// When it does, a task exiting with a non-zero status fails with
+    /// [`LarsError::TaskFailed`].
+    pub fn wait_for_tasks(mut self, wait: bool) -> Self {
+        self.wait_for_tasks = wait;
+        self
+    }
+
+    pub fn config(&self) -> &ConfigManager {
+        &self.config
+    }
+
+    /// The runner managing a service.
+    ///
+    /// Runners are created once per runner type and reused. A runner that is
+    /// not available is looked for again on the next call.
+    pub fn runner(&self, service: &Service) -> Result<Arc<dyn Runner>> {
+        if let Some(runner) = &self.runner {
+            return Ok(Arc::clone(runner));
+        }
+
+        let mut runners = self.runners.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(runner) = runners.get(&service.runner_type) {
+            return Ok(Arc::clone(runner));
+        }
+        let runner: Arc<dyn Runner> = create_runner(service.runner_type)?.into();
+        runners.insert(service.runner_type, Arc::clone(&runner));
+        Ok(runner)
+    }
+
+    /// Check if a service is running
+    pub fn is_running(&self, service: &Service) -> Result<bool> {
+        self.runner(service)?.is_running(service)
+    }
+
+    /// Start a service unless it is already running.
+    ///
+    /// Checks its declared ports are free, allocates its port slots, runs
+    /// pre_start, records the launch, starts it and runs post_start. The
+    /// previous run's log is archived first (or, in append mode, rotated if
+    /// it is due). A failing required post_start hook stops the service
+    /// again.
+    pub fn start(&self, service: &Service) -> Result<StartOutcome> {
+        let runner = self.runner(service)?;
+        if runner.is_running(service)? {
+            return Ok(StartOutcome::AlreadyRunning);
+        }
+
+        let warnings = self.start_with(&*runner, service)?;
+        self.finish(&*runner, service, warnings)
+    }
+
+    /// Stop a service if it is running.
+    ///
+    /// Runs pre_stop, stops it and runs post_stop once it has exited. Stop
+    /// hooks never prevent a service from stopping; their failures are
+    /// returned as warnings.
+    pub fn stop(&self, service: &Service) -> Result<StopOutcome> {
+        let runner = self.runner(service)?;
+        if !runner.is_running(service)? {
+            return Ok(StopOutcome::NotRunning);
+        }
+
+        let warnings = self.stop_with(&*runner, service, self.timeout()?)?;
+        Ok(StopOutcome::Stopped { warnings })
+    }
+
+    /// Stop a service if it is running, wait for it to exit, then start it.
+    ///
+    /// Never returns [`StartOutcome::AlreadyRunning`]; warnings of both the
+    /// stop and the start are returned.
+    pub fn restart(&self, service: &Service) -> Result<StartOutcome> {
+        let runner = self.runner(service)?;
+        let timeout = self.timeout()?;
+        let mut warnings = Vec::new();
+
+        if runner.is_running(service)? {
+            warnings = self.stop_with(&*runner, service, timeout)?;
+            runner.wait_stopped(service, timeout)?;
+        }
+
+        warnings.extend(self.start_with(&*runner, service)?);
+        self.finish(&*runner, service, warnings)
+    }
+
+    /// Restart a service only if it is running, returning `None` for a
+    /// service that is stopped so it stays stopped
+    pub fn restart_running(&self, service: &Service) -> Result<Option<StartOutcome>> {
+        match self.is_running(service) {
+            Ok(true) => self.restart(service).map(Some),
+            // A service without an available runner cannot be running
+            Ok(false) | Err(LarsError::RunnerNotAvailable(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stop a service if it is running and remove it from the configuration
+    pub fn remove(&self, service: &Service) -> Result<StopOutcome> {
+        let outcome = match self.stop(service) {
+            // A service without an available runner cannot be running
+            Err(LarsError::RunnerNotAvailable(_)) => StopOutcome::NotRunning,
+            result => result?,
+        };
+
+        self.config.remove_service(&service.name)?;
+        Ok(outcome)
+    }
+
+    /// Wait for a task to run to completion and return its exit code
+    pub fn wait_for_task(&self, service: &Service) -> Result<i32> {
+        self.wait_with(&*self.runner(service)?, service)
+    }
+
+    /// Start services, at most `jobs` at a time, reporting progress to
+    /// `on_event`
+    pub fn start_all(
+        &self,
+        services: &[Service],
+        jobs: usize,
+        on_event: impl FnMut(BatchEvent<'_, Result<StartOutcome>>),
+    ) -> BatchReport<StartOutcome> {
+        BatchReport {
+            results: run_bounded(services, jobs, |service| self.start(service), on_event),
+        }
+    }
+
+    /// Stop services, at most `jobs` at a time, reporting progress to
+    /// `on_event`. Services without an available runner are not running.
+    pub fn stop_all(
+        &self,
+        services: &[Service],
+        jobs: usize,
+        on_event: impl FnMut(BatchEvent<'_, Result<StopOutcome>>),
+    ) -> BatchReport<StopOutcome> {
+        let stop = |service: &Service| match self.stop(service) {
+            Err(LarsError::RunnerNotAvailable(_)) => Ok(StopOutcome::NotRunning),
+            result => result,
+        };
+
+        BatchReport {
+            results: run_bounded(services, jobs, stop, on_event),
+        }
+    }
+
+    /// Restart the running services among `services`, at most `jobs` at a
+    /// time, reporting progress to `on_event`
+    ///
+    /// Services that are not running are left stopped and reported as
+    /// `None`.
+    pub fn restart_all(
+        &self,
+        services: &[Service],
+        jobs: usize,
+        on_event: impl FnMut(BatchEvent<'_, Result<Option<StartOutcome>>>),
+    ) -> BatchReport<Option<StartOutcome>> {
+        let restart = |service: &Service| self.restart_running(service);
+        BatchReport {
+            results: run_bounded(services, jobs, restart, on_event),
+        }
+    }
+
+    fn timeout(&self) -> Result<u64> {
+        Ok(self.config.load()?.settings.restart_timeout_secs)
+    }
+
+    /// Start a service, recording a started or failed event
+    fn start_with(&self, runner: &dyn Runner, service: &Service) -> Result<Vec<String>> {
+        // Record how the previous run exited before launching overwrites it
+        let _ = self.config.record_exit(service);
+
+        let result = self.launch(runner, service);
+        let event = match &result {
+            Ok(_) => Event::for_service(EventKind::Started, service),
+            Err(e) => Event::for_service(EventKind::Failed, service).with_error(e.to_string()),
+        };
+        let _ = self.config.record_event(event);
+
+        result
+    }
+
+    fn launch(&self, runner: &dyn Runner, service: &Service) -> Result<Vec<String>> {
+        check_ports(service)?;
+
+        // Started and recorded with allocated ports in its environment, so
+        // reload notices when the ports it refers to change
+        let mut launched = service.clone();
+        launched.env = self.config.launch_env(service)?;
+
+        let hook_log = self.config.hook_log_path_for_service(&service.id);
+        let mut warnings = Vec::new();
+
+        note_hook(
+            service,
+            run_hook(&launched, HookStage::PreStart, &hook_log)?,
+            &mut warnings,
+        );
+
+        let log_path = self.config.log_path_for_service(&service.id);
+
+        if let Some(parent) = log_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let policy = RotationPolicy::from_settings(&self.config.load()?.settings);
+        if service.log_mode == LogMode::Truncate {
+            rotation::archive_run(&log_path, policy.retain)?;
+        } else if policy.should_rotate(&log_path, SystemTime::now()) {
+            rotation::rotate(&log_path, policy.retain)?;
+        }
+
+        // Recorded first, so that anything following the log sees the launch
+        // before the new run's output
+        self.config.record_launch(&launched)?;
+        if let Err(e) = runner.start(&launched, &log_path) {
+            let _ = self.config.clear_launch(&service.id);
+            return Err(e);
+        }
+
+        match run_hook(&launched, HookStage::PostStart, &hook_log) {
+            Ok(outcome) => note_hook(service, outcome, &mut warnings),
+            Err(e) => {
+                let _ = runner.stop(service);
+                let _ = self.config.clear_launch(&service.id);
+                return Err(e);
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    fn stop_with(
+        &self,
+        runner: &dyn Runner,
+        service: &Service,
+        timeout_secs: u64,
+    ) -> Result<Vec<String>> {
+        // Stop hooks see the environment the service was launched with, so
+        // they can reach the ports it is actually using
+        let mut launched = service.clone();
+        launched.env = self.launched_env(service);
+
+        let hook_log = self.config.hook_log_path_for_service(&service.id);
+        let mut warnings = Vec::new();
+
+        match run_hook(&launched, HookStage::PreStop, &hook_log) {
+            Ok(outcome) => note_hook(service, outcome, &mut warnings),
+            Err(e) => warnings.push(e.to_string()),
+        }
+
+        runner.stop(service)?;
+        self.config.clear_launch(&service.id)?;
+        let _ = self
+            .config
+            .record_event(Event::for_service(EventKind::Stopped, service));
+
+        if service.post_stop.is_some() {
+            runner.wait_stopped(service, timeout_secs)?;
+
+            match run_hook(&launched, HookStage::PostStop, &hook_log) {
+                Ok(outcome) => note_hook(service, outcome, &mut warnings),
+                Err(e) => warnings.push(e.to_string()),
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    /// Environment a running service was launched with, or the one it would
+    /// be launched with if it was started without a launch record
+    fn launched_env(&self, service: &Service) -> HashMap<String, String> {
+        let state = self.config.load_state();
+        match state.service(&service.id).and_then(|s| s.launch.as_ref()) {
+            Some(launch) => launch.fingerprint.env.clone().into_iter().collect(),
+            None => self
+                .config
+                .resolved_env(service)
+                .unwrap_or_else(|_| service.env.clone()),
+        }
+    }
+
+    /// Wait for a task that was just started, if tasks are waited for
+    fn finish(
+        &self,
+        runner: &dyn Runner,
+        service: &Service,
+        warnings: Vec<String>,
+    ) -> Result<StartOutcome> {
+        if service.kind != ServiceKind::Task || !self.wait_for_tasks {
+            return Ok(StartOutcome::Started { warnings });
+        }
+
+        match self.wait_with(runner, service)? {
+            0 => Ok(StartOutcome::Completed { warnings }),
+            code => Err(LarsError::TaskFailed {
+                name: service.name.clone(),
+                code,
+            }),
+        }
+    }
+
+    /// Wait for a task to exit, stopping it once it runs past the task
+    /// timeout
+    fn wait_with(&self, runner: &dyn Runner, service: &Service) -> Result<i32> {
+        let settings = self.config.load()?.settings;
+        let deadline = (settings.task_timeout_secs > 0)
+            .then(|| Instant::now() + Duration::from_secs(settings.task_timeout_secs));
+
+        while runner.is_running(service)? {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.stop_with(runner, service, settings.restart_timeout_secs)?;
+                return Err(LarsError::TaskTimeout {
+                    name: service.name.clone(),
+                    secs: settings.task_timeout_secs,
+                });
+            }
+            std::thread::sleep(TASK_POLL_INTERVAL);
+        }
+
+        self.config
+            .last_exit(&service.id)
+            .map(|exit| exit.code)
+            .ok_or_else(|| LarsError::TaskStopped(service.name.clone()))
+    }
+}
+
+fn note_hook(service: &Service, outcome: Option<HookOutcome>, warnings: &mut Vec<String>) {
+    if let Some(outcome) = outcome {
+        if !outcome.success() {
+            warnings.push(format!(
+                "Optional {} hook for '{}' {}",
+                outcome.stage,
+                service.name,
+                outcome.describe()
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventFilter;
+    use crate::models::{Hook, PortSpec};
+    use crate::testing::{FakeCall, FakeRunner};
+    use tempfile::TempDir;
+
+    fn setup(services: &[Service]) -> (TempDir, Arc<FakeRunner>, ServiceManager) {
+        let temp = TempDir::new().unwrap();
+        let config = ConfigManager::new(temp.path().join("config"), temp.path().join("logs"));
+        for service in services {
+            config.add_service(service.clone()).unwrap();
+        }
+        let runner = Arc::new(FakeRunner::new());
+        let manager = ServiceManager::new(config).with_runner(runner.clone());
+        (temp, runner, manager)
+    }
+
+    fn service(name: &str) -> Service {
+        Service::new(name.to_string(), "serve".to_string())
+    }
+
+    fn task(name: &str) -> Service {
+        let mut task = Service::new(name.to_string(), "make".to_string());
+        task.kind = ServiceKind::Task;
+        task
+    }
+
+    /// Lifecycle events recorded so far, oldest first
+    fn event_kinds(manager: &ServiceManager) -> Vec<EventKind> {
+        let events = manager
+            .config()
+            .events(&EventFilter::default(), 100)
+            .unwrap();
+        events
+            .into_iter()
+            .map(|e| e.kind)
+            .filter(|kind| *kind != EventKind::Added)
+            .collect()
+    }
+
+    fn launched(manager: &ServiceManager, service: &Service) -> bool {
+        let state = manager.config().load_state();
+        state
+            .service(&service.id)
+            .is_some_and(|s| s.launch.is_some())
+    }
+
+    #[test]
+    fn test_start_and_stop_outcomes() {
+        let api = service("api");
+        let (_temp, runner, manager) = setup(std::slice::from_ref(&api));
+
+        let started = manager.start(&api).unwrap();
+        assert_eq!(started, StartOutcome::Started { warnings: vec![] });
+        assert!(launched(&manager, &api));
+        assert_eq!(manager.start(&api).unwrap(), StartOutcome::AlreadyRunning);
+
+        let stopped = manager.stop(&api).unwrap();
+        assert_eq!(stopped, StopOutcome::Stopped { warnings: vec![] });
+        assert!(!launched(&manager, &api));
+        assert_eq!(manager.stop(&api).unwrap(), StopOutcome::NotRunning);
+
+        assert_eq!(
+            runner.calls(),
+            vec![
+                FakeCall::Start("api".to_string()),
+                FakeCall::Stop("api".to_string())
+            ]
+        );
+        assert_eq!(
+            event_kinds(&manager),
+            vec![EventKind::Started, EventKind::Stopped]
+        );
+    }
+
+    #[test]
+    fn test_stop_hooks_see_the_launched_ports() {
+        let temp = TempDir::new().unwrap();
+        let marker = temp.path().join("port");
+        let mut api = service("api");
+        api.ports = vec![PortSpec::slot("http")];
+        api.pre_stop = Some(Hook::new(format!("echo $PORT_HTTP > {}", marker.display())));
+        let (_temp, _runner, manager) = setup(std::slice::from_ref(&api));
+
+        manager.start(&api).unwrap();
+        let port = manager.config().assigned_ports(&api).unwrap()["http"];
+        manager.stop(&api).unwrap();
+        let written = std::fs::read_to_string(&marker).unwrap();
+        assert_eq!(written.trim(), port.to_string());
+    }
+
+    #[test]
+    fn test_restart_stops_then_starts() {
+        let api = service("api");
+        let (_temp, _runner, manager) = setup(std::slice::from_ref(&api));
+
+        manager.start(&api).unwrap();
+        let restarted = manager.restart(&api).unwrap();
+        assert_eq!(restarted, StartOutcome::Started { warnings: vec![] });
+        assert!(manager.is_running(&api).unwrap());
+        assert_eq!(
+            event_kinds(&manager),
+            vec![EventKind::Started, EventKind::Stopped, EventKind::Started]
+        );
+    }
+
+    #[test]
+    fn test_restart_times_out_when_the_service_does_not_stop() {
+        let api = service("api");
+        let (_temp, runner, manager) = setup(std::slice::from_ref(&api));
+        runner.ignore_stop("api");
+
+        manager.start(&api).unwrap();
+        let err = manager.restart(&api).unwrap_err();
+        assert!(matches!(err, LarsError::StopTimeout(name) if name == "api"));
+        assert_eq!(
+            runner.calls(),
+            vec![
+                FakeCall::Start("api".to_string()),
+                FakeCall::Stop("api".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_start_after_crash_records_the_exit() {
+        let api = service("api");
+        let (_temp, runner, manager) = setup(std::slice::from_ref(&api));
+
+        manager.start(&api).unwrap();
+        runner.exit(&api, 1).unwrap();
+        assert_eq!(
+            manager.start(&api).unwrap(),
+            StartOutcome::Started { warnings: vec![] }
+        );
+        assert_eq!(
+            event_kinds(&manager),
+            vec![EventKind::Started, EventKind::Exited, EventKind::Started]
+        );
+    }
+
+    #[test]
+    fn test_tasks_are_waited_for() {
+        let migrate = task("migrate");
+        let seed = task("seed");
+        let (_temp, runner, manager) = setup(&[migrate.clone(), seed.clone()]);
+        runner.exit_on_start("migrate", 0);
+        runner.exit_on_start("seed", 3);
+
+        assert_eq!(
+            manager.start(&migrate).unwrap(),
+            StartOutcome::Completed { warnings: vec![] }
+        );
+        let err = manager.start(&seed).unwrap_err();
+        assert!(matches!(err, LarsError::TaskFailed { code: 3, .. }));
+
+        let manager = manager.wait_for_tasks(false);
+        assert_eq!(
+            manager.start(&seed).unwrap(),
+            StartOutcome::Started { warnings: vec![] }
+        );
+        assert_eq!(manager.wait_for_task(&seed).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_hung_task_times_out() {
+        let build = task("build");
+        let (_temp, runner, manager) = setup(std::slice::from_ref(&build));
+        let mut config = manager.config().load().unwrap();
+        config.settings.task_timeout_secs = 1;
+        manager.config().save(&config).unwrap();
+
+        let err = manager.start(&build).unwrap_err();
+        assert!(matches!(err, LarsError::TaskTimeout { secs: 1, .. }));
+        assert!(!manager.is_running(&build).unwrap());
+        assert_eq!(
+            runner.calls(),
+            vec![
+                FakeCall::Start("build".to_string()),
+                FakeCall::Stop("build".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_start_is_recorded() {
+        let api = service("api");
+        let (_temp, runner, manager) = setup(std::slice::from_ref(&api));
+        runner.fail_start("api", "cannot start");
+
+        assert!(manager.start(&api).is_err());
+        assert!(!launched(&manager, &api));
+
+        let events = manager
+            .config()
+            .events(&EventFilter::default(), 100)
+            .unwrap();
+        let failed = events.last().unwrap();
+        assert_eq!(failed.kind, EventKind::Failed);
+        assert_eq!(
+            failed.error.as_deref(),
+            Some("Process execution failed: cannot start")
+        );
+    }
+
+    #[test]
+    fn test_batch_report() {
+        let api = service("api");
+        let services = [api.clone(), service("broken"), task("migrate")];
+        let (_temp, runner, manager) = setup(&services);
+        runner.fail_start("broken", "cannot start");
+        runner.exit_on_start("migrate", 0);
+        manager.start(&api).unwrap();
+
+        let mut finished = 0;
+        let report = manager.start_all(&services, 2, |event| {
+            if let BatchEvent::Finished(..) = event {
+                finished += 1;
+            }
+        });
+        assert_eq!(finished, 3);
+        assert_eq!(report.count(|o| *o == StartOutcome::AlreadyRunning), 1);
+        assert_eq!(
+            report.count(|o| matches!(o, StartOutcome::Completed { .. })),
+            1
+        );
+        assert_eq!(report.failed(), 1);
+        assert!(report.results[1].is_err());
+
+        let report = manager.stop_all(&services, 2, |_| {});
+        assert_eq!(
+            report.count(|o| matches!(o, StopOutcome::Stopped { .. })),
+            1
+        );
+        assert_eq!(report.count(|o| *o == StopOutcome::NotRunning), 2);
+    }
+
+    #[test]
+    fn test_restart_all_skips_stopped_services() {
+        let api = service("api");
+        let services = [api.clone(), service("web")];
+        let (_temp, runner, manager) = setup(&services);
+        manager.start(&api).unwrap();
+
+        let report = manager.restart_all(&services, 2, |_| {});
+        assert_eq!(report.count(Option::is_some), 1);
+        assert_eq!(report.count(Option::is_none), 1);
+        assert!(!manager.is_running(&services[1]).unwrap());
+        assert!(!runner.calls().contains(&FakeCall::Start("web".to_string())));
+        assert_eq!(manager.restart_running(&services[1]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_remove_stops_the_service() {
+        let api = service("api");
+        let (_temp, _runner, manager) = setup(std::slice::from_ref(&api));
+
+        manager.start(&api).unwrap();
+        let outcome = manager.remove(&api).unwrap();
+        assert_eq!(outcome, StopOutcome::Stopped { warnings: vec![] });
+        assert!(!manager.is_running(&api).unwrap());
+        assert!(manager.config().get_service("api").is_err());
+    }
+}
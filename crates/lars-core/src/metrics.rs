@@ -0,0 +1,308 @@
+//! Prometheus metrics for services
+//!
+//! [`MetricsCollector`] gathers whether each service is up, how often it was
+//! restarted, how it last exited, its resource usage and health, and
+//! [`render`] writes them in the Prometheus text exposition format, one
+//! series per service labelled `service="<name>"`.
+
+use crate::error::Result;
+use crate::events::{Event, EventKind};
+use crate::health::{self, Health, HealthStatus};
+use crate::logs::LogFollower;
+use crate::manager::ServiceManager;
+use crate::stats::ProcessTable;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Content type of [`render`]'s output
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// What is measured about one service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceMetrics {
+    pub name: String,
+    pub up: bool,
+    /// Starts recorded in the event history after the first one
+    pub restarts: u64,
+    /// Exit status of the last time the service exited on its own
+    pub last_exit_code: Option<i32>,
+    /// CPU usage since the previous collection, where 100 is one full core
+    pub cpu_percent: Option<f64>,
+    /// Resident memory of the service's process tree
+    pub memory_bytes: Option<u64>,
+    /// Health of a running service with TCP ports
+    pub health: Option<Health>,
+}
+
+/// Collects [`ServiceMetrics`], measuring CPU usage between collections
+#[derive(Debug, Default)]
+pub struct MetricsCollector {
+    previous: Option<ProcessTable>,
+    /// Reads the event history in full once, then only what is appended
+    events: Option<LogFollower>,
+    /// Starts recorded in the event history so far, per service
+    starts: HashMap<uuid::Uuid, u64>,
+}
+
+impl MetricsCollector {
+    /// Create a collector; the first collection has no CPU usage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure every configured service, asking `manager`'s runners
+    /// whether they are running
+    pub fn collect(&mut self, manager: &ServiceManager) -> Result<Vec<ServiceMetrics>> {
+        let config = manager.config();
+        let services = config.list_services()?;
+        let state = config.load_state();
+        let table = ProcessTable::read();
+
+        let events = self
+            .events
+            .get_or_insert_with(|| LogFollower::from_start(vec![config.events_path()]));
+        for line in events.poll()? {
+            let Ok(event) = serde_json::from_str::<Event>(&line.text) else {
+                continue;
+            };
+            if let (EventKind::Started, Some(id)) = (event.kind, event.service_id) {
+                *self.starts.entry(id).or_default() += 1;
+            }
+        }
+
+        let metrics = services
+            .iter()
+            .map(|service| {
+                let runner = manager.runner(service).ok();
+                let up = runner
+                    .as_ref()
+                    .is_some_and(|r| r.is_running(service).unwrap_or(false));
+                let stats = runner
+                    .filter(|r| r.capabilities().pid)
+                    .and_then(|r| r.get_pid(service).ok().flatten())
+                    .and_then(|pid| table.as_ref()?.stats(pid, self.previous.as_ref()));
+
+                ServiceMetrics {
+                    name: service.name.clone(),
+                    up,
+                    restarts: self
+                        .starts
+                        .get(&service.id)
+                        .map_or(0, |n| n.saturating_sub(1)),
+                    last_exit_code: config.last_exit(&service.id).map(|e| e.code),
+                    cpu_percent: stats.as_ref().and_then(|s| s.cpu_percent),
+                    memory_bytes: stats.map(|s| s.rss_bytes),
+                    health: if up {
+                        health::check(service, &state)
+                    } else {
+                        None
+                    },
+                }
+            })
+            .collect();
+
+        self.previous = table;
+        Ok(metrics)
+    }
+}
+
+/// Render metrics in the Prometheus text exposition format
+pub fn render(services: &[ServiceMetrics]) -> String {
+    let mut out = String::new();
+
+    family(
+        &mut out,
+        "lars_service_up",
+        "gauge",
+        "Whether the service is running",
+        services,
+        |s| Some(if s.up { 1.0 } else { 0.0 }),
+    );
+    family(
+        &mut out,
+        "lars_service_restarts_total",
+        "counter",
+        "Times the service was started after its first recorded start",
+        services,
+        |s| Some(s.restarts as f64),
+    );
+    family(
+        &mut out,
+        "lars_service_last_exit_code",
+        "gauge",
+        "Exit status of the last time the service exited on its own",
+        services,
+        |s| s.last_exit_code.map(f64::from),
+    );
+    family(
+        &mut out,
+        "lars_service_cpu_percent",
+        "gauge",
+        "CPU usage of the service's process tree, where 100 is one core",
+        services,
+        |s| s.cpu_percent,
+    );
+    family(
+        &mut out,
+        "lars_service_memory_bytes",
+        "gauge",
+        "Resident memory of the service's process tree",
+        services,
+        |s| s.memory_bytes.map(|b| b as f64),
+    );
+    family(
+        &mut out,
+        "lars_service_healthy",
+        "gauge",
+        "Whether every TCP port of the running service accepts connections",
+        services,
+        |s| {
+            let health = s.health.as_ref()?;
+            Some(if health.status == HealthStatus::Healthy {
+                1.0
+            } else {
+                0.0
+            })
+        },
+    );
+    family(
+        &mut out,
+        "lars_service_health_latency_seconds",
+        "gauge",
+        "Time the slowest TCP port of the service took to accept a connection",
+        services,
+        |s| Some(s.health.as_ref()?.latency_ms? / 1000.0),
+    );
+
+    out
+}
+
+/// Write one metric family, leaving out services without a value
+fn family(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    services: &[ServiceMetrics],
+    value: impl Fn(&ServiceMetrics) -> Option<f64>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for service in services {
+        if let Some(value) = value(service) {
+            let _ = writeln!(
+                out,
+                "{}{{service=\"{}\"}} {}",
+                name,
+                escape_label(&service.name),
+                value
+            );
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+    use crate::models::Service;
+    use tempfile::TempDir;
+
+    fn metrics(name: &str) -> ServiceMetrics {
+        ServiceMetrics {
+            name: name.to_string(),
+            up: false,
+            restarts: 0,
+            last_exit_code: None,
+            cpu_percent: None,
+            memory_bytes: None,
+            health: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let api = ServiceMetrics {
+            up: true,
+            restarts: 2,
+            cpu_percent: Some(12.5),
+            memory_bytes: Some(1048576),
+            health: Some(Health {
+                status: HealthStatus::Healthy,
+                latency_ms: Some(1.5),
+                error: None,
+            }),
+            ..metrics("api")
+        };
+        let job = ServiceMetrics {
+            last_exit_code: Some(3),
+            ..metrics("job")
+        };
+
+        let text = render(&[api, job]);
+        let lines: Vec<&str> = text.lines().collect();
+        for expected in [
+            "# TYPE lars_service_up gauge",
+            "lars_service_up{service=\"api\"} 1",
+            "lars_service_up{service=\"job\"} 0",
+            "# TYPE lars_service_restarts_total counter",
+            "lars_service_restarts_total{service=\"api\"} 2",
+            "lars_service_last_exit_code{service=\"job\"} 3",
+            "lars_service_cpu_percent{service=\"api\"} 12.5",
+            "lars_service_memory_bytes{service=\"api\"} 1048576",
+            "lars_service_healthy{service=\"api\"} 1",
+            "lars_service_health_latency_seconds{service=\"api\"} 0.0015",
+        ] {
+            assert!(
+                lines.contains(&expected),
+                "missing {:?} in\n{}",
+                expected,
+                text
+            );
+        }
+        assert!(!text.contains("lars_service_last_exit_code{service=\"api\"}"));
+        assert!(!text.contains("lars_service_memory_bytes{service=\"job\"}"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+
+    #[test]
+    fn test_collect_counts_restarts() {
+        let temp = TempDir::new().unwrap();
+        let config = ConfigManager::new(temp.path().join("config"), temp.path().join("logs"));
+        let service = Service::new("job".to_string(), "exit 1".to_string());
+        config.add_service(service.clone()).unwrap();
+        for _ in 0..3 {
+            config
+                .record_event(Event::for_service(EventKind::Started, &service))
+                .unwrap();
+        }
+        std::fs::create_dir_all(config.log_dir()).unwrap();
+        std::fs::write(config.exit_path_for_service(&service.id), "1\n").unwrap();
+
+        let manager = ServiceManager::new(config.clone());
+        let mut collector = MetricsCollector::new();
+        let metrics = collector.collect(&manager).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert!(!metrics[0].up);
+        assert_eq!(metrics[0].restarts, 2);
+        assert_eq!(metrics[0].last_exit_code, Some(1));
+
+        // Later collections only count what was appended since
+        config
+            .record_event(Event::for_service(EventKind::Started, &service))
+            .unwrap();
+        assert_eq!(collector.collect(&manager).unwrap()[0].restarts, 3);
+        assert_eq!(collector.collect(&manager).unwrap()[0].restarts, 3);
+    }
+}
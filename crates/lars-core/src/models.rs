@@ -24,6 +24,13 @@ pub struct Service {
     /// Environment variables
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub env: HashMap<String, String>,
+    /// Tags for selecting groups of services
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Ports the service listens on: fixed ports are checked for conflicts
+    /// before starting, named slots are allocated free ports
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<PortSpec>,
     /// Whether the service is enabled
     #[serde(default = "default_true")]
     pub enabled: bool,
@@ -33,6 +40,33 @@ pub struct Service {
     /// Runner type (tmux, screen, or direct)
     #[serde(default)]
     pub runner_type: RunnerType,
+    /// Whether this is a long-running service or a one-shot task
+    #[serde(default, skip_serializing_if = "ServiceKind::is_service")]
+    pub kind: ServiceKind,
+    /// Hook run before starting (failure aborts the start)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_start: Option<Hook>,
+    /// Hook run after starting (failure stops the service again)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_start: Option<Hook>,
+    /// Hook run before stopping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_stop: Option<Hook>,
+    /// Hook run after the service has stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_stop: Option<Hook>,
+    /// File-watch auto-restart configuration (used by `lars watch`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch: Option<WatchConfig>,
+    /// Periodic schedule (triggered by `lars daemon`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleConfig>,
+    /// Whether the log is truncated or appended to on start
+    #[serde(default, skip_serializing_if = "LogMode::is_truncate")]
+    pub log_mode: LogMode,
+    /// Whether output is logged raw or as timestamped JSON records
+    #[serde(default, skip_serializing_if = "LogFormat::is_raw")]
+    pub log_format: LogFormat,
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
     /// Last update timestamp
@@ -53,9 +87,20 @@ impl Service {
             command,
             cwd: None,
             env: HashMap::new(),
+            tags: Vec::new(),
+            ports: Vec::new(),
             enabled: true,
             autostart: false,
             runner_type: RunnerType::default(),
+            kind: ServiceKind::default(),
+            pre_start: None,
+            post_start: None,
+            pre_stop: None,
+            post_stop: None,
+            watch: None,
+            schedule: None,
+            log_mode: LogMode::default(),
+            log_format: LogFormat::default(),
             created_at: now,
             updated_at: now,
         }
@@ -73,9 +118,485 @@ impl Default for Service {
     }
 }
 
-/// The type of runner to use for a service
+/// Whether a service runs continuously or to completion
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceKind {
+    /// Long-running process (default)
+    #[default]
+    Service,
+    /// One-shot job that runs to completion and records its exit code
+    Task,
+}
+
+impl ServiceKind {
+    fn is_service(&self) -> bool {
+        *self == ServiceKind::Service
+    }
+}
+
+impl std::fmt::Display for ServiceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceKind::Service => write!(f, "service"),
+            ServiceKind::Task => write!(f, "task"),
+        }
+    }
+}
+
+impl std::str::FromStr for ServiceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "service" => Ok(ServiceKind::Service),
+            "task" => Ok(ServiceKind::Task),
+            _ => Err(format!("Invalid service kind: {}", s)),
+        }
+    }
+}
+
+/// How a service's log is treated when it starts
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
+pub enum LogMode {
+    /// Archive the previous run's log and start a fresh one (default)
+    #[default]
+    Truncate,
+    /// Keep appending to the same log across runs
+    Append,
+}
+
+impl LogMode {
+    fn is_truncate(&self) -> bool {
+        *self == LogMode::Truncate
+    }
+}
+
+impl std::fmt::Display for LogMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogMode::Truncate => write!(f, "truncate"),
+            LogMode::Append => write!(f, "append"),
+        }
+    }
+}
+
+impl std::str::FromStr for LogMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "truncate" => Ok(LogMode::Truncate),
+            "append" => Ok(LogMode::Append),
+            _ => Err(format!("Invalid log mode: {}", s)),
+        }
+    }
+}
+
+/// How a service's output is written to its log
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Output bytes as written, stdout and stderr merged (default)
+    #[default]
+    Raw,
+    /// One JSON record per line with a timestamp and stream tag (see [`crate::relay`])
+    Json,
+}
+
+impl LogFormat {
+    fn is_raw(&self) -> bool {
+        *self == LogFormat::Raw
+    }
+}
+
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFormat::Raw => write!(f, "raw"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "raw" => Ok(LogFormat::Raw),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {}", s)),
+        }
+    }
+}
+
+/// Default timeout for hook commands in seconds
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 60;
+
+fn default_hook_timeout() -> u64 {
+    DEFAULT_HOOK_TIMEOUT_SECS
+}
+
+/// A lifecycle hook command, run in the service's working directory and
+/// environment.
+///
+/// In the config file a hook is either a plain command string or an object
+/// with `command`, `timeout_secs` and `optional`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "HookSpec")]
+pub struct Hook {
+    /// Shell command to execute
+    pub command: String,
+    /// Seconds before the hook is killed and treated as failed
+    pub timeout_secs: u64,
+    /// Whether a failure is reported as a warning instead of an error
+    pub optional: bool,
+}
+
+impl Hook {
+    /// Create a required hook with the default timeout
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            timeout_secs: DEFAULT_HOOK_TIMEOUT_SECS,
+            optional: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HookSpec {
+    Command(String),
+    Full {
+        command: String,
+        #[serde(default = "default_hook_timeout")]
+        timeout_secs: u64,
+        #[serde(default)]
+        optional: bool,
+    },
+}
+
+impl From<HookSpec> for Hook {
+    fn from(spec: HookSpec) -> Self {
+        match spec {
+            HookSpec::Command(command) => Hook::new(command),
+            HookSpec::Full {
+                command,
+                timeout_secs,
+                optional,
+            } => Hook {
+                command,
+                timeout_secs,
+                optional,
+            },
+        }
+    }
+}
+
+/// Default quiet period before a watched change triggers a restart
+const DEFAULT_WATCH_DEBOUNCE_MS: u64 = 500;
+
+fn default_watch_debounce_ms() -> u64 {
+    DEFAULT_WATCH_DEBOUNCE_MS
+}
+
+/// File-watch configuration for a service
+///
+/// Patterns are globs relative to the service's working directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchConfig {
+    /// Globs of files that trigger a restart (all files if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Globs of files to ignore, in addition to VCS and build directories
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Milliseconds without further changes before restarting
+    #[serde(default = "default_watch_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            ignore: Vec::new(),
+            debounce_ms: DEFAULT_WATCH_DEBOUNCE_MS,
+        }
+    }
+}
+
+/// Transport protocol of a port
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    fn is_tcp(&self) -> bool {
+        *self == Protocol::Tcp
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            _ => Err(format!("Invalid protocol: {}", s)),
+        }
+    }
+}
+
+/// A port a service declares it listens on.
+///
+/// A port is either fixed (`8080`) or a named slot (`http`) for which a
+/// free port is allocated when the service starts; a slot can also be given
+/// a fixed port (`http=8080`). Named ports are passed to the service as
+/// `PORT_HTTP` (and the first port as `PORT`), and can be referred to from
+/// env values as `${ports.http}`, or `${api.ports.http}` for another
+/// service's.
+///
+/// In the config file a port is a port number, a string in the command-line
+/// form (`8080`, `http`, `http=8080`, `5353/udp`), or an object with
+/// `name`, `port` and `protocol`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "PortSpecRepr")]
+pub struct PortSpec {
+    /// Slot name, for ports referred to by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Fixed port number, or `None` to allocate a free port on start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Protocol (TCP unless stated)
+    #[serde(default, skip_serializing_if = "Protocol::is_tcp")]
+    pub protocol: Protocol,
+}
+
+impl PortSpec {
+    /// Create a fixed TCP port
+    pub fn tcp(port: u16) -> Self {
+        Self {
+            name: None,
+            port: Some(port),
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    /// Create a named TCP slot that is allocated a free port on start
+    pub fn slot(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            port: None,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    fn validate(self) -> Result<Self, String> {
+        if let Some(name) = &self.name {
+            let valid = name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if name.is_empty() || !valid || name.starts_with(|c: char| c.is_ascii_digit()) {
+                return Err(format!("Invalid port name: {}", name));
+            }
+        }
+        match (&self.name, self.port) {
+            (None, None) => Err("A port needs a name or a number".to_string()),
+            (_, Some(0)) => Err("Invalid port: 0".to_string()),
+            _ => Ok(self),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PortSpecRepr {
+    Port(u16),
+    Spec(String),
+    Full {
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        protocol: Protocol,
+    },
+}
+
+impl TryFrom<PortSpecRepr> for PortSpec {
+    type Error = String;
+
+    fn try_from(repr: PortSpecRepr) -> Result<Self, Self::Error> {
+        match repr {
+            PortSpecRepr::Port(port) => PortSpec::tcp(port).validate(),
+            PortSpecRepr::Spec(spec) => spec.parse(),
+            PortSpecRepr::Full {
+                name,
+                port,
+                protocol,
+            } => PortSpec {
+                name,
+                port,
+                protocol,
+            }
+            .validate(),
+        }
+    }
+}
+
+impl std::fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.name, self.port) {
+            (Some(name), Some(port)) => write!(f, "{}={}", name, port)?,
+            (Some(name), None) => write!(f, "{}", name)?,
+            (None, Some(port)) => write!(f, "{}", port)?,
+            (None, None) => {}
+        }
+        match self.protocol {
+            Protocol::Tcp => Ok(()),
+            Protocol::Udp => write!(f, "/udp"),
+        }
+    }
+}
+
+impl std::str::FromStr for PortSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spec, protocol) = match s.trim().split_once('/') {
+            Some((spec, protocol)) => (spec, protocol.parse()?),
+            None => (s.trim(), Protocol::Tcp),
+        };
+        let number = |port: &str| {
+            port.parse::<u16>()
+                .map_err(|_| format!("Invalid port: {}", s))
+        };
+
+        let (name, port) = match spec.split_once('=') {
+            Some((name, port)) => (Some(name.to_string()), Some(number(port)?)),
+            None if spec.starts_with(|c: char| c.is_ascii_digit()) => (None, Some(number(spec)?)),
+            None => (Some(spec.to_string()), None),
+        };
+
+        PortSpec {
+            name,
+            port,
+            protocol,
+        }
+        .validate()
+    }
+}
+
+/// What to do when a scheduled run is due while the previous run is still going
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    /// Skip the new run (default)
+    #[default]
+    Skip,
+    /// Start the new run once the previous one finishes
+    Queue,
+    /// Stop the previous run and start a new one
+    Kill,
+}
+
+impl OverlapPolicy {
+    fn is_skip(&self) -> bool {
+        *self == OverlapPolicy::Skip
+    }
+}
+
+impl std::fmt::Display for OverlapPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverlapPolicy::Skip => write!(f, "skip"),
+            OverlapPolicy::Queue => write!(f, "queue"),
+            OverlapPolicy::Kill => write!(f, "kill"),
+        }
+    }
+}
+
+impl std::str::FromStr for OverlapPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(OverlapPolicy::Skip),
+            "queue" => Ok(OverlapPolicy::Queue),
+            "kill" => Ok(OverlapPolicy::Kill),
+            _ => Err(format!("Invalid overlap policy: {}", s)),
+        }
+    }
+}
+
+/// Periodic schedule for a service
+///
+/// `spec` is a five-field cron expression (`*/15 * * * *`), a macro such as
+/// `@hourly`, or an interval such as `every 10m` (see [`crate::schedule`]).
+/// In the config file a schedule is either the spec string or an object
+/// with `spec` and `overlap`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "ScheduleSpec")]
+pub struct ScheduleConfig {
+    /// Cron expression, macro or interval
+    pub spec: String,
+    /// What to do when a run is due while the previous one is still going
+    #[serde(default, skip_serializing_if = "OverlapPolicy::is_skip")]
+    pub overlap: OverlapPolicy,
+}
+
+impl ScheduleConfig {
+    /// Create a schedule with the default overlap policy
+    pub fn new(spec: String) -> Self {
+        Self {
+            spec,
+            overlap: OverlapPolicy::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScheduleSpec {
+    Spec(String),
+    Full {
+        spec: String,
+        #[serde(default)]
+        overlap: OverlapPolicy,
+    },
+}
+
+impl From<ScheduleSpec> for ScheduleConfig {
+    fn from(spec: ScheduleSpec) -> Self {
+        match spec {
+            ScheduleSpec::Spec(spec) => ScheduleConfig::new(spec),
+            ScheduleSpec::Full { spec, overlap } => ScheduleConfig { spec, overlap },
+        }
+    }
+}
+
+/// The type of runner to use for a service
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
 pub enum RunnerType {
     /// Use tmux for session management (default)
     #[default]
@@ -148,6 +669,27 @@ fn default_restart_timeout() -> u64 {
     DEFAULT_RESTART_TIMEOUT_SECS
 }
 
+/// Default time in seconds a task may run while it is waited for
+const DEFAULT_TASK_TIMEOUT_SECS: u64 = 3600;
+
+fn default_task_timeout() -> u64 {
+    DEFAULT_TASK_TIMEOUT_SECS
+}
+
+/// Default log size in MB at which logs are rotated
+const DEFAULT_LOG_MAX_SIZE_MB: u64 = 10;
+
+fn default_log_max_size_mb() -> u64 {
+    DEFAULT_LOG_MAX_SIZE_MB
+}
+
+/// Default number of rotated logs kept per service
+const DEFAULT_LOG_RETAIN: usize = 5;
+
+fn default_log_retain() -> usize {
+    DEFAULT_LOG_RETAIN
+}
+
 /// Application-wide settings
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AppSettings {
@@ -160,6 +702,29 @@ pub struct AppSettings {
     /// Timeout in seconds when waiting for a service to stop during restart
     #[serde(default = "default_restart_timeout")]
     pub restart_timeout_secs: u64,
+    /// Timeout in seconds when waiting for a task to finish, after which it
+    /// is stopped (0 waits forever)
+    #[serde(default = "default_task_timeout")]
+    pub task_timeout_secs: u64,
+    /// Rotate a service log once it reaches this size in MB (0 disables)
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Rotate a service log once it is this many hours old (0 disables)
+    #[serde(default)]
+    pub log_max_age_hours: u64,
+    /// Number of rotated logs kept per service
+    #[serde(default = "default_log_retain")]
+    pub log_retain: usize,
+    /// Show a desktop notification when a service crashes or turns unhealthy
+    #[serde(default)]
+    pub notify_desktop: bool,
+    /// Shell command run for each notification, with the details in `LARS_*`
+    /// environment variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_command: Option<String>,
+    /// URL each notification is POSTed to as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_webhook: Option<String>,
 }
 
 impl Default for AppSettings {
@@ -168,6 +733,13 @@ impl Default for AppSettings {
             default_runner: RunnerType::default(),
             shutdown_behavior: ShutdownBehavior::default(),
             restart_timeout_secs: DEFAULT_RESTART_TIMEOUT_SECS,
+            task_timeout_secs: DEFAULT_TASK_TIMEOUT_SECS,
+            log_max_size_mb: DEFAULT_LOG_MAX_SIZE_MB,
+            log_max_age_hours: 0,
+            log_retain: DEFAULT_LOG_RETAIN,
+            notify_desktop: false,
+            notify_command: None,
+            notify_webhook: None,
         }
     }
 }
@@ -235,6 +807,13 @@ impl AppConfig {
         }
     }
 
+    /// Iterate over the services carrying a tag
+    pub fn services_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Service> {
+        self.services
+            .iter()
+            .filter(move |s| s.tags.iter().any(|t| t == tag))
+    }
+
     /// Check if a service name already exists
     pub fn service_name_exists(&self, name: &str) -> bool {
         self.services.iter().any(|s| s.name == name)
@@ -287,6 +866,83 @@ mod tests {
         assert!(!config.service_name_exists("test"));
     }
 
+    #[test]
+    fn test_services_with_tag() {
+        let mut config = AppConfig::default();
+        let mut api = Service::new("api".to_string(), "npm start".to_string());
+        api.tags = vec!["web".to_string(), "backend".to_string()];
+        config.add_service(api);
+        config.add_service(Service::new("db".to_string(), "postgres".to_string()));
+
+        let names: Vec<_> = config.services_with_tag("web").map(|s| &s.name).collect();
+        assert_eq!(names, vec!["api"]);
+        assert_eq!(config.services_with_tag("cache").count(), 0);
+    }
+
+    #[test]
+    fn test_service_kind_display_and_parse() {
+        assert_eq!(ServiceKind::Task.to_string(), "task");
+        assert_eq!("Task".parse::<ServiceKind>().unwrap(), ServiceKind::Task);
+        assert!("daemon".parse::<ServiceKind>().is_err());
+    }
+
+    #[test]
+    fn test_hook_from_string_or_object() {
+        let hook: Hook = serde_json::from_str(r#""npm run migrate""#).unwrap();
+        assert_eq!(hook, Hook::new("npm run migrate".to_string()));
+
+        let hook: Hook =
+            serde_json::from_str(r#"{"command": "make clean", "optional": true}"#).unwrap();
+        assert_eq!(hook.command, "make clean");
+        assert_eq!(hook.timeout_secs, DEFAULT_HOOK_TIMEOUT_SECS);
+        assert!(hook.optional);
+    }
+
+    #[test]
+    fn test_watch_config_defaults() {
+        let watch: WatchConfig = serde_json::from_str(r#"{"paths": ["src/**/*.go"]}"#).unwrap();
+        assert_eq!(watch.paths, vec!["src/**/*.go"]);
+        assert!(watch.ignore.is_empty());
+        assert_eq!(watch.debounce_ms, DEFAULT_WATCH_DEBOUNCE_MS);
+    }
+
+    #[test]
+    fn test_schedule_from_string_or_object() {
+        let schedule: ScheduleConfig = serde_json::from_str(r#""every 10m""#).unwrap();
+        assert_eq!(schedule, ScheduleConfig::new("every 10m".to_string()));
+
+        let schedule: ScheduleConfig =
+            serde_json::from_str(r#"{"spec": "0 * * * *", "overlap": "queue"}"#).unwrap();
+        assert_eq!(schedule.spec, "0 * * * *");
+        assert_eq!(schedule.overlap, OverlapPolicy::Queue);
+        assert!("wait".parse::<OverlapPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_port_spec_from_number_string_or_object() {
+        let port: PortSpec = serde_json::from_str("8080").unwrap();
+        assert_eq!(port, PortSpec::tcp(8080));
+        assert_eq!(serde_json::to_string(&port).unwrap(), r#"{"port":8080}"#);
+
+        let port: PortSpec = serde_json::from_str(r#"{"port": 5353, "protocol": "udp"}"#).unwrap();
+        assert_eq!(port.protocol, Protocol::Udp);
+        assert_eq!(port.to_string(), "5353/udp");
+        assert_eq!("5353/udp".parse::<PortSpec>(), Ok(port));
+
+        let ports: Vec<PortSpec> = serde_json::from_str(r#"["http", "debug=9229"]"#).unwrap();
+        assert_eq!(ports[0], PortSpec::slot("http"));
+        assert_eq!(ports[1].name.as_deref(), Some("debug"));
+        assert_eq!(ports[1].port, Some(9229));
+        assert_eq!(ports[1].to_string(), "debug=9229");
+
+        assert_eq!("3000".parse::<PortSpec>(), Ok(PortSpec::tcp(3000)));
+        assert!("0".parse::<PortSpec>().is_err());
+        assert!("70000".parse::<PortSpec>().is_err());
+        assert!("80/sctp".parse::<PortSpec>().is_err());
+        assert!("bad.name".parse::<PortSpec>().is_err());
+        assert!(serde_json::from_str::<PortSpec>(r#"{"protocol": "udp"}"#).is_err());
+    }
+
     #[test]
     fn test_serde_round_trip() {
         let mut config = AppConfig::default();
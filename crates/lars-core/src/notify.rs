@@ -0,0 +1,431 @@
+//! Notifications about services that need attention
+//!
+//! When a service crashes, keeps crashing or stops accepting connections on
+//! its ports, a [`Notification`] is sent to the sinks configured in
+//! [`AppSettings`]: a desktop notification through `notify-send`, a shell
+//! command with the details in `LARS_*` environment variables, and a JSON
+//! POST to a webhook.
+//!
+//! Notifications follow the event history: the daemon picks up `exited`
+//! and `unhealthy` events with a [`Notifier`] whichever command recorded
+//! them. Each event is recorded once, so each notification is sent once.
+
+use crate::config::ConfigManager;
+use crate::events::{Event, EventFilter, EventKind};
+use crate::logs::LogFollower;
+use crate::models::{AppSettings, Service, ServiceKind};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Crashes within [`RESTART_LOOP_WINDOW`] that make a restart loop
+pub const RESTART_LOOP_CRASHES: usize = 3;
+
+/// How far back crashes are counted towards a restart loop
+pub const RESTART_LOOP_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// How long a webhook may take to answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// The service exited on its own
+    Crashed,
+    /// The service crashed [`RESTART_LOOP_CRASHES`] times in a short while
+    RestartLoop,
+    /// The running service stopped accepting connections on its ports
+    Unhealthy,
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trigger::Crashed => write!(f, "crashed"),
+            Trigger::RestartLoop => write!(f, "restart_loop"),
+            Trigger::Unhealthy => write!(f, "unhealthy"),
+        }
+    }
+}
+
+/// A notification, as POSTed to webhooks
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Notification {
+    pub trigger: Trigger,
+    pub service: String,
+    pub service_id: Uuid,
+    /// When the event behind the notification happened
+    pub time: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// One-line summary, which is also what chat webhooks display
+    pub text: String,
+}
+
+impl Notification {
+    /// The notification an event calls for, if any.
+    ///
+    /// `crashes` is the number of crashes of the service within
+    /// [`RESTART_LOOP_WINDOW`], including this one. Once a restart loop has
+    /// been reported, further crashes in the window are not.
+    pub fn for_event(event: &Event, service: &Service, crashes: usize) -> Option<Self> {
+        let (trigger, text) = match event.kind {
+            EventKind::Unhealthy => (
+                Trigger::Unhealthy,
+                match &event.error {
+                    Some(error) => format!("{} is unhealthy: {}", service.name, error),
+                    None => format!("{} is unhealthy", service.name),
+                },
+            ),
+            _ if is_crash(event, service) => match crashes {
+                n if n < RESTART_LOOP_CRASHES => (
+                    Trigger::Crashed,
+                    match event.exit_code {
+                        Some(code) => format!("{} exited with status {}", service.name, code),
+                        None => format!("{} exited", service.name),
+                    },
+                ),
+                RESTART_LOOP_CRASHES => (
+                    Trigger::RestartLoop,
+                    format!(
+                        "{} crashed {} times in {} minutes",
+                        service.name,
+                        crashes,
+                        RESTART_LOOP_WINDOW.as_secs() / 60
+                    ),
+                ),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            trigger,
+            service: service.name.clone(),
+            service_id: service.id,
+            time: event.time,
+            exit_code: event.exit_code,
+            error: event.error.clone(),
+            text,
+        })
+    }
+
+    /// Environment variables describing the notification to a command
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("LARS_TRIGGER", self.trigger.to_string()),
+            ("LARS_SERVICE", self.service.clone()),
+            ("LARS_SERVICE_ID", self.service_id.to_string()),
+            ("LARS_TIME", self.time.to_rfc3339()),
+            ("LARS_MESSAGE", self.text.clone()),
+        ];
+        if let Some(code) = self.exit_code {
+            env.push(("LARS_EXIT_CODE", code.to_string()));
+        }
+        if let Some(error) = &self.error {
+            env.push(("LARS_ERROR", error.clone()));
+        }
+        env
+    }
+}
+
+/// Whether an event is a crash: a long-running service exiting on its own,
+/// or a task exiting with a non-zero status
+pub fn is_crash(event: &Event, service: &Service) -> bool {
+    event.kind == EventKind::Exited
+        && (service.kind == ServiceKind::Service || event.exit_code != Some(0))
+}
+
+/// Whether any notification sink is configured
+pub fn enabled(settings: &AppSettings) -> bool {
+    settings.notify_desktop
+        || settings.notify_command.is_some()
+        || settings.notify_webhook.is_some()
+}
+
+/// Picks up events as they are appended to the event history and works out
+/// the notifications they call for
+pub struct Notifier {
+    follower: LogFollower,
+}
+
+impl Notifier {
+    /// Follow the event history from its current end
+    pub fn new(config: &ConfigManager) -> Self {
+        Self {
+            follower: LogFollower::new(vec![config.events_path()]),
+        }
+    }
+
+    /// Notifications called for by the events recorded since the last poll.
+    ///
+    /// Nothing is returned while no sink is configured.
+    pub fn poll(&mut self, config: &ConfigManager) -> crate::error::Result<Vec<Notification>> {
+        let events: Vec<Event> = self
+            .follower
+            .poll()?
+            .iter()
+            .filter_map(|line| serde_json::from_str(&line.text).ok())
+            .collect();
+        if events.is_empty() {
+            return Ok(Vec::new());
+        }
+        let app_config = config.load()?;
+        if !enabled(&app_config.settings) {
+            return Ok(Vec::new());
+        }
+
+        Ok(events
+            .iter()
+            .filter_map(|event| {
+                let service = app_config.find_service_by_id(event.service_id?)?;
+                let crashes = if is_crash(event, service) {
+                    crashes_until(config, event, service)
+                } else {
+                    0
+                };
+                Notification::for_event(event, service, crashes)
+            })
+            .collect())
+    }
+}
+
+/// Crashes of a service within [`RESTART_LOOP_WINDOW`] up to and including
+/// `event`
+fn crashes_until(config: &ConfigManager, event: &Event, service: &Service) -> usize {
+    let window =
+        chrono::Duration::from_std(RESTART_LOOP_WINDOW).expect("window fits in a chrono duration");
+    let filter = EventFilter {
+        service_id: Some(service.id),
+        since: Some(event.time - window),
+        ..Default::default()
+    };
+    config
+        .events(&filter, 100)
+        .unwrap_or_default()
+        .iter()
+        .filter(|e| e.time <= event.time && is_crash(e, service))
+        .count()
+}
+
+/// Send a notification to every configured sink.
+///
+/// Desktop notifications and commands are started without waiting for them;
+/// webhooks are waited for up to a few seconds. Returns an error message for
+/// each sink that failed.
+pub fn send(settings: &AppSettings, notification: &Notification) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if settings.notify_desktop {
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name=lars", "--urgency=critical"])
+            .arg(format!(
+                "lars: {} {}",
+                notification.service, notification.trigger
+            ))
+            .arg(&notification.text);
+        if let Err(e) = spawn(cmd) {
+            errors.push(format!("notify-send: {}", e));
+        }
+    }
+
+    if let Some(command) = &settings.notify_command {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]).envs(notification.env());
+        if let Err(e) = spawn(cmd) {
+            errors.push(format!("notification command: {}", e));
+        }
+    }
+
+    if let Some(url) = &settings.notify_webhook {
+        if let Err(e) = post(url, notification) {
+            errors.push(format!("webhook {}: {}", url, e));
+        }
+    }
+
+    errors
+}
+
+/// Start a command in the background, reaping it once it finishes
+fn spawn(mut cmd: Command) -> std::io::Result<()> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn post(url: &str, notification: &Notification) -> Result<(), String> {
+    let response = attohttpc::post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(notification)
+        .map_err(|e| e.to_string())?
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !response.is_success() {
+        return Err(format!("answered {}", response.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    fn exited(service: &Service, code: i32) -> Event {
+        Event::for_service(EventKind::Exited, service).with_exit_code(code)
+    }
+
+    #[test]
+    fn test_notifier_reports_restart_loop() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let config = ConfigManager::new(temp.path().join("config"), temp.path().join("logs"));
+        let service = Service::new("api".to_string(), "serve".to_string());
+        let mut app_config = crate::models::AppConfig::default();
+        app_config.settings.notify_command = Some("true".to_string());
+        app_config.add_service(service.clone());
+        config.save(&app_config).unwrap();
+
+        let mut notifier = Notifier::new(&config);
+        std::fs::create_dir_all(config.log_dir()).unwrap();
+        for _ in 0..4 {
+            std::thread::sleep(Duration::from_millis(20));
+            std::fs::write(config.exit_path_for_service(&service.id), "1\n").unwrap();
+            config.record_exit(&service).unwrap();
+        }
+
+        // All four crashes are picked up at once; the fourth is not reported
+        let triggers: Vec<Trigger> = notifier
+            .poll(&config)
+            .unwrap()
+            .iter()
+            .map(|n| n.trigger)
+            .collect();
+        assert_eq!(
+            triggers,
+            [Trigger::Crashed, Trigger::Crashed, Trigger::RestartLoop]
+        );
+        assert!(notifier.poll(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_notification_triggers() {
+        let service = Service::new("api".to_string(), "serve".to_string());
+
+        let crash = Notification::for_event(&exited(&service, 1), &service, 1).unwrap();
+        assert_eq!(crash.trigger, Trigger::Crashed);
+        assert_eq!(crash.text, "api exited with status 1");
+
+        let looping = Notification::for_event(&exited(&service, 1), &service, 3).unwrap();
+        assert_eq!(looping.trigger, Trigger::RestartLoop);
+        assert!(Notification::for_event(&exited(&service, 1), &service, 4).is_none());
+
+        let unhealthy = Event::for_service(EventKind::Unhealthy, &service)
+            .with_error("port 8080 is not accepting connections");
+        let notification = Notification::for_event(&unhealthy, &service, 0).unwrap();
+        assert_eq!(notification.trigger, Trigger::Unhealthy);
+        assert_eq!(
+            notification.text,
+            "api is unhealthy: port 8080 is not accepting connections"
+        );
+
+        let stopped = Event::for_service(EventKind::Stopped, &service);
+        assert!(Notification::for_event(&stopped, &service, 0).is_none());
+    }
+
+    #[test]
+    fn test_task_success_is_not_a_crash() {
+        let mut task = Service::new("migrate".to_string(), "make migrate".to_string());
+        task.kind = ServiceKind::Task;
+
+        assert!(!is_crash(&exited(&task, 0), &task));
+        assert!(is_crash(&exited(&task, 2), &task));
+    }
+
+    #[test]
+    fn test_send_to_command_and_webhook() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let output = temp.path().join("notified");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let settings = AppSettings {
+            notify_command: Some(format!(
+                "echo \"$LARS_TRIGGER $LARS_SERVICE $LARS_EXIT_CODE\" > {}",
+                output.display()
+            )),
+            notify_webhook: Some(format!("http://{}/hook", listener.local_addr().unwrap())),
+            ..Default::default()
+        };
+
+        // A stand-in webhook that answers one request and returns its body
+        let webhook = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_ascii_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        });
+
+        let service = Service::new("api".to_string(), "serve".to_string());
+        let notification = Notification::for_event(&exited(&service, 1), &service, 1).unwrap();
+        assert!(send(&settings, &notification).is_empty());
+
+        let body = webhook.join().unwrap();
+        assert_eq!(body["trigger"], "crashed");
+        assert_eq!(body["service"], "api");
+        assert_eq!(body["exit_code"], 1);
+        assert_eq!(body["text"], "api exited with status 1");
+
+        for _ in 0..100 {
+            if let Ok(written) = std::fs::read_to_string(&output) {
+                if !written.is_empty() {
+                    assert_eq!(written, "crashed api 1\n");
+                    return;
+                }
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("notification command did not run");
+    }
+
+    #[test]
+    fn test_send_reports_failing_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+        let settings = AppSettings {
+            notify_webhook: Some(url.clone()),
+            ..Default::default()
+        };
+
+        let service = Service::new("api".to_string(), "serve".to_string());
+        let notification = Notification::for_event(&exited(&service, 1), &service, 1).unwrap();
+        let errors = send(&settings, &notification);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains(&url));
+    }
+}
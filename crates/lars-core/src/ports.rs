@@ -0,0 +1,455 @@
+//! Listening-port discovery, port conflict detection and port allocation
+//!
+//! Sockets are read from `/proc/net/{tcp,tcp6,udp,udp6}` and matched to
+//! processes through the socket inodes in `/proc/<pid>/fd`, so the ports of
+//! a service are those held by any process in its tree (see
+//! [`crate::stats::ProcessTable::tree`]). Discovery is only available on
+//! Linux; elsewhere conflicts are detected by trying to bind the port.
+//!
+//! Named port slots are allocated free ports by
+//! [`crate::ConfigManager::launch_env`], which passes them to the service
+//! as environment variables (see [`port_env_var`] and [`interpolate`]).
+
+use crate::error::{LarsError, Result};
+use crate::models::{Protocol, Service};
+use crate::stats::ProcessTable;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Attempts at finding a free port before giving up
+const ALLOCATE_ATTEMPTS: usize = 32;
+
+/// TCP socket state `LISTEN` in `/proc/net/tcp`
+const TCP_LISTEN: &str = "0A";
+
+/// UDP socket state of a bound, unconnected socket in `/proc/net/udp`
+const UDP_UNCONNECTED: &str = "07";
+
+/// A port some process is listening on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListeningPort {
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    pub port: u16,
+    /// Owning process, if it could be determined (sockets of other users'
+    /// processes cannot be traced)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+}
+
+impl std::fmt::Display for ListeningPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.protocol {
+            Protocol::Tcp => write!(f, "{}", self.port),
+            Protocol::Udp => write!(f, "{}/udp", self.port),
+        }
+    }
+}
+
+/// A snapshot of the listening sockets on the system
+#[derive(Debug, Clone, Default)]
+pub struct SocketTable {
+    ports: Vec<ListeningPort>,
+}
+
+impl SocketTable {
+    /// Read the listening sockets and their owners.
+    ///
+    /// Returns `None` where `/proc` is not available.
+    #[cfg(target_os = "linux")]
+    pub fn read() -> Option<Self> {
+        let mut sockets = Vec::new();
+        for (file, protocol) in [
+            ("tcp", Protocol::Tcp),
+            ("tcp6", Protocol::Tcp),
+            ("udp", Protocol::Udp),
+            ("udp6", Protocol::Udp),
+        ] {
+            // IPv6 may be disabled
+            if let Ok(table) = std::fs::read_to_string(format!("/proc/net/{}", file)) {
+                sockets.extend(parse_net(&table, protocol));
+            }
+        }
+        if sockets.is_empty() && !std::path::Path::new("/proc/net/tcp").exists() {
+            return None;
+        }
+
+        let owners = socket_owners();
+        let ports = sockets
+            .into_iter()
+            .map(|(mut port, inode)| {
+                port.pid = owners.get(&inode).copied();
+                port
+            })
+            .collect();
+
+        Some(Self { ports })
+    }
+
+    /// Read the listening sockets and their owners.
+    ///
+    /// Returns `None` where `/proc` is not available.
+    #[cfg(not(target_os = "linux"))]
+    pub fn read() -> Option<Self> {
+        None
+    }
+
+    /// All listening ports
+    pub fn ports(&self) -> &[ListeningPort] {
+        &self.ports
+    }
+
+    /// Ports held by any of `pids`, once each, in port order
+    pub fn ports_of(&self, pids: &[u32]) -> Vec<ListeningPort> {
+        let mut ports: Vec<ListeningPort> = self
+            .ports
+            .iter()
+            .filter(|p| p.pid.is_some_and(|pid| pids.contains(&pid)))
+            .cloned()
+            .collect();
+        ports.sort_by_key(|p| (p.port, p.protocol as u8, p.address));
+        // A socket bound to both 0.0.0.0 and :: is listed once
+        ports.dedup_by(|a, b| a.port == b.port && a.protocol == b.protocol);
+        ports
+    }
+
+    /// Ports held by the process tree below `root`
+    pub fn ports_of_tree(&self, procs: &ProcessTable, root: u32) -> Vec<ListeningPort> {
+        self.ports_of(&procs.tree(root))
+    }
+
+    /// The listener already holding a port, if any
+    pub fn holder(&self, port: u16, protocol: Protocol) -> Option<&ListeningPort> {
+        self.ports
+            .iter()
+            .find(|p| p.port == port && p.protocol == protocol)
+    }
+}
+
+/// Check that none of the fixed ports a service declares is taken.
+///
+/// Fails with [`LarsError::PortConflict`] naming the process holding the
+/// first taken port.
+pub fn check_ports(service: &Service) -> Result<()> {
+    if service.ports.iter().all(|p| p.port.is_none()) {
+        return Ok(());
+    }
+
+    let table = SocketTable::read();
+    for spec in &service.ports {
+        let Some(port) = spec.port else {
+            continue;
+        };
+        let holder = match &table {
+            Some(table) => match table.holder(port, spec.protocol) {
+                Some(holder) => describe_holder(holder.pid),
+                None => continue,
+            },
+            None if !is_free(port, spec.protocol) => describe_holder(None),
+            None => continue,
+        };
+
+        return Err(LarsError::PortConflict(format!(
+            "port {} of '{}' is already in use by {}",
+            spec, service.name, holder
+        )));
+    }
+
+    Ok(())
+}
+
+/// Name a process for an error message, e.g. `node (pid 4242)`
+fn describe_holder(pid: Option<u32>) -> String {
+    let Some(pid) = pid else {
+        return "another process".to_string();
+    };
+
+    match std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        Ok(name) => format!("{} (pid {})", name.trim(), pid),
+        Err(_) => format!("pid {}", pid),
+    }
+}
+
+/// Check whether a port is free by briefly binding it on all interfaces
+pub fn is_free(port: u16, protocol: Protocol) -> bool {
+    let addr = (Ipv4Addr::UNSPECIFIED, port);
+    match protocol {
+        Protocol::Tcp => std::net::TcpListener::bind(addr).is_ok(),
+        Protocol::Udp => std::net::UdpSocket::bind(addr).is_ok(),
+    }
+}
+
+/// Find a free port, skipping ports in `reserved`.
+///
+/// The operating system picks the port, so it comes from the ephemeral
+/// range and is unlikely to be taken again before the service binds it.
+pub fn allocate_port(protocol: Protocol, reserved: &HashSet<u16>) -> Result<u16> {
+    let addr = (Ipv4Addr::UNSPECIFIED, 0);
+    for _ in 0..ALLOCATE_ATTEMPTS {
+        let port = match protocol {
+            Protocol::Tcp => std::net::TcpListener::bind(addr)?.local_addr()?.port(),
+            Protocol::Udp => std::net::UdpSocket::bind(addr)?.local_addr()?.port(),
+        };
+        if !reserved.contains(&port) {
+            return Ok(port);
+        }
+    }
+
+    Err(LarsError::PortConflict(
+        "no free port could be allocated".to_string(),
+    ))
+}
+
+/// Name of the environment variable a named port is passed in, e.g.
+/// `PORT_HTTP` for `http` and `PORT_ADMIN_API` for `admin-api`
+pub fn port_env_var(name: &str) -> String {
+    format!("PORT_{}", name.to_uppercase().replace('-', "_"))
+}
+
+/// Replace port references in an env value.
+///
+/// `${ports.NAME}` refers to the service's own port and
+/// `${SERVICE.ports.NAME}` to another service's; `lookup` is called with
+/// the service name (`None` for the service itself) and the port name.
+/// Other `${...}` text is left alone.
+pub fn interpolate<F>(value: &str, mut lookup: F) -> Result<String>
+where
+    F: FnMut(Option<&str>, &str) -> Result<Option<u16>>,
+{
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let reference = &rest[start + 2..start + len];
+        let whole = &rest[start..=start + len];
+        rest = &rest[start + len + 1..];
+
+        let target = match reference.split_once("ports.") {
+            Some(("", name)) => Some((None, name)),
+            Some((service, name)) => service.strip_suffix('.').map(|s| (Some(s), name)),
+            None => None,
+        };
+        let Some((service, name)) = target else {
+            out.push_str(whole);
+            continue;
+        };
+
+        match lookup(service, name)? {
+            Some(port) => out.push_str(&port.to_string()),
+            None => return Err(LarsError::UnknownPort(whole.to_string())),
+        }
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Parse the listening sockets of a `/proc/net` table, with their inodes
+fn parse_net(table: &str, protocol: Protocol) -> Vec<(ListeningPort, u64)> {
+    let listening = match protocol {
+        Protocol::Tcp => TCP_LISTEN,
+        Protocol::Udp => UDP_UNCONNECTED,
+    };
+
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3)? != &listening {
+                return None;
+            }
+            let (address, port) = parse_address(fields.get(1)?)?;
+            let inode = fields.get(9)?.parse().ok()?;
+
+            Some((
+                ListeningPort {
+                    protocol,
+                    address,
+                    port,
+                    pid: None,
+                },
+                inode,
+            ))
+        })
+        .collect()
+}
+
+/// Parse an `ADDRESS:PORT` pair in `/proc/net` hex notation.
+///
+/// Addresses are written as 32-bit words in host byte order.
+fn parse_address(field: &str) -> Option<(IpAddr, u16)> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let mut bytes = Vec::with_capacity(16);
+    for i in (0..address.len()).step_by(8) {
+        let word = u32::from_str_radix(address.get(i..i + 8)?, 16).ok()?;
+        bytes.extend(word.to_ne_bytes());
+    }
+
+    let address = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => return None,
+    };
+    Some((address, port))
+}
+
+/// Map socket inodes to the processes holding them
+#[cfg(target_os = "linux")]
+fn socket_owners() -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(procs) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+
+    for entry in procs.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+            continue;
+        };
+        // Other users' processes cannot be read
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse().ok());
+            if let Some(inode) = inode {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PortSpec;
+
+    const TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 41299 1 0000000000000000 20 4 30 10 -1
+";
+
+    #[test]
+    fn test_parse_net_keeps_listening_sockets() {
+        let sockets = parse_net(TCP, Protocol::Tcp);
+
+        assert_eq!(sockets.len(), 1);
+        let (port, inode) = &sockets[0];
+        assert_eq!(port.port, 8080);
+        assert_eq!(port.address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(*inode, 41234);
+    }
+
+    #[test]
+    fn test_parse_ipv6_address() {
+        let (address, port) = parse_address("00000000000000000000000001000000:0BB8").unwrap();
+        assert_eq!(address, IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(port, 3000);
+
+        assert!(parse_address("garbage").is_none());
+    }
+
+    #[test]
+    fn test_ports_of_and_holder() {
+        let port = |protocol, address: IpAddr, port, pid| ListeningPort {
+            protocol,
+            address,
+            port,
+            pid,
+        };
+        let any4 = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let any6 = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+        let table = SocketTable {
+            ports: vec![
+                port(Protocol::Tcp, any6, 3000, Some(10)),
+                port(Protocol::Tcp, any4, 3000, Some(10)),
+                port(Protocol::Udp, any4, 5353, Some(11)),
+                port(Protocol::Tcp, any4, 22, None),
+            ],
+        };
+
+        let ports = table.ports_of(&[10, 11]);
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[0].to_string(), "3000");
+        assert_eq!(ports[1].to_string(), "5353/udp");
+
+        assert_eq!(table.holder(22, Protocol::Tcp).unwrap().pid, None);
+        assert!(table.holder(22, Protocol::Udp).is_none());
+    }
+
+    #[test]
+    fn test_interpolate_port_references() {
+        let lookup = |service: Option<&str>, name: &str| {
+            Ok(match (service, name) {
+                (None, "http") => Some(41000),
+                (Some("api"), "http") => Some(42000),
+                _ => None,
+            })
+        };
+
+        assert_eq!(
+            interpolate("http://localhost:${api.ports.http}/v1", lookup).unwrap(),
+            "http://localhost:42000/v1"
+        );
+        assert_eq!(
+            interpolate("${ports.http} ${HOME} ${unclosed", lookup).unwrap(),
+            "41000 ${HOME} ${unclosed"
+        );
+        assert!(interpolate("${ports.debug}", lookup)
+            .unwrap_err()
+            .to_string()
+            .contains("${ports.debug}"));
+    }
+
+    #[test]
+    fn test_allocate_port_and_env_var() {
+        let port = allocate_port(Protocol::Tcp, &HashSet::new()).unwrap();
+        assert!(is_free(port, Protocol::Tcp));
+        assert_ne!(
+            allocate_port(Protocol::Tcp, &HashSet::from([port])).unwrap(),
+            port
+        );
+
+        assert_eq!(port_env_var("admin-api"), "PORT_ADMIN_API");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_check_ports_names_the_holder() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken = listener.local_addr().unwrap().port();
+
+        let table = SocketTable::read().unwrap();
+        let ours = table.ports_of(&[std::process::id()]);
+        assert!(ours.iter().any(|p| p.port == taken));
+
+        let mut service = Service::new("api".to_string(), "npm start".to_string());
+        service.ports = vec![PortSpec::tcp(taken)];
+        let err = check_ports(&service).unwrap_err();
+        assert!(matches!(err, LarsError::PortConflict(_)));
+        assert!(err
+            .to_string()
+            .contains(&format!("(pid {})", std::process::id())));
+
+        drop(listener);
+        assert!(check_ports(&service).is_ok());
+    }
+}
@@ -0,0 +1,325 @@
+//! Structured log capture
+//!
+//! Services with the `json` [`LogFormat`](crate::models::LogFormat) are not
+//! redirected straight into their log file. Instead the runner wraps the
+//! command with a small relay process that reads its stdout and stderr
+//! separately and appends one JSON record per line, stamped with the time it
+//! was read:
+//!
+//! ```text
+//! {"ts":"2026-03-10T09:00:00.123Z","stream":"stderr","line":"listening on :3000"}
+//! ```
+//!
+//! The relay is the `lars` binary's hidden `log-relay` subcommand, which calls
+//! [`run_relay`]. Programs embedding lars-core that start structured services
+//! need `lars` on the PATH or a program with the same entry point (see
+//! [`relay_program`]).
+
+use crate::error::{LarsError, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Name of the CLI subcommand that runs the relay
+pub const RELAY_SUBCOMMAND: &str = "log-relay";
+
+/// Environment variable overriding the relay executable
+pub const RELAY_PROGRAM_ENV: &str = "LARS_RELAY_PROGRAM";
+
+/// Longest record the relay writes; longer lines are split across records
+pub const MAX_RECORD_BYTES: usize = 64 * 1024;
+
+/// The output stream a log line was written to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl std::fmt::Display for LogStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogStream::Stdout => write!(f, "stdout"),
+            LogStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// A single captured log line
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogRecord {
+    pub ts: DateTime<Utc>,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+impl LogRecord {
+    /// Parse a line of a structured log, returning `None` for anything else
+    pub fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+
+    /// Serialize the record as a single log line (without the newline)
+    pub fn to_line(&self) -> String {
+        serde_json::json!({
+            "ts": self.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "stream": self.stream,
+            "line": self.line,
+        })
+        .to_string()
+    }
+}
+
+/// Time and stream filter for log records
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogFilter {
+    /// Only records at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only records before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only records from this stream
+    pub stream: Option<LogStream>,
+}
+
+impl LogFilter {
+    /// Whether the filter restricts anything
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check whether a record passes the filter
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        self.since.is_none_or(|since| record.ts >= since)
+            && self.until.is_none_or(|until| record.ts < until)
+            && self.stream.is_none_or(|stream| record.stream == stream)
+    }
+}
+
+/// Get the executable used as the relay.
+///
+/// `LARS_RELAY_PROGRAM` takes precedence. Otherwise this is the current
+/// executable when it is `lars`, as when services are started from the CLI,
+/// or else `lars` from the PATH, so that programs embedding lars-core never
+/// re-run themselves as the relay.
+pub fn relay_program() -> Result<PathBuf> {
+    find_relay(
+        std::env::var_os(RELAY_PROGRAM_ENV),
+        std::env::current_exe().ok(),
+        std::env::var_os("PATH"),
+    )
+}
+
+fn find_relay(
+    program: Option<OsString>,
+    current_exe: Option<PathBuf>,
+    path: Option<OsString>,
+) -> Result<PathBuf> {
+    if let Some(program) = program {
+        return Ok(PathBuf::from(program));
+    }
+
+    let binary = format!("lars{}", std::env::consts::EXE_SUFFIX);
+    if let Some(exe) = current_exe.filter(|exe| exe.file_name() == Some(binary.as_ref())) {
+        return Ok(exe);
+    }
+
+    path.iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(&binary))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| {
+            LarsError::RunnerNotAvailable(format!(
+                "structured logs need the lars log relay, but lars is not in PATH; \
+                 install it or set {} to a program with a '{}' subcommand",
+                RELAY_PROGRAM_ENV, RELAY_SUBCOMMAND
+            ))
+        })
+}
+
+/// Run `command` and append its output to `log_path` as structured records.
+///
+/// Stdin is inherited. Returns the command's exit code, or 128 plus the
+/// signal number if it was killed by a signal.
+pub fn run_relay(log_path: &Path, command: &[String]) -> Result<i32> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| LarsError::ProcessFailed("No command given to relay".to_string()))?;
+
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+    let log = Arc::new(Mutex::new(log));
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdout = child.stdout.take().map(|out| {
+        let log = Arc::clone(&log);
+        std::thread::spawn(move || relay_lines(out, LogStream::Stdout, &log))
+    });
+    let stderr = child.stderr.take().map(|err| {
+        let log = Arc::clone(&log);
+        std::thread::spawn(move || relay_lines(err, LogStream::Stderr, &log))
+    });
+
+    let status = child.wait()?;
+
+    for handle in [stdout, stderr].into_iter().flatten() {
+        let _ = handle.join();
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return Ok(128 + signal);
+        }
+    }
+
+    Ok(status.code().unwrap_or(1))
+}
+
+fn relay_lines(source: impl Read, stream: LogStream, log: &Mutex<std::fs::File>) {
+    let mut reader = BufReader::new(source);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        // Output without newlines (binary data, progress bars) is flushed
+        // in pieces rather than held until the stream ends
+        let mut record = (&mut reader).take(MAX_RECORD_BYTES as u64);
+        match record.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        let text = String::from_utf8_lossy(&buf);
+        let record = LogRecord {
+            ts: Utc::now(),
+            stream,
+            line: text.trim_end_matches(['\n', '\r']).to_string(),
+        };
+
+        if let Ok(mut log) = log.lock() {
+            let _ = writeln!(log, "{}", record.to_line());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn record(secs: i64, stream: LogStream) -> LogRecord {
+        LogRecord {
+            ts: Utc.timestamp_opt(secs, 0).unwrap(),
+            stream,
+            line: "hello".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let record = record(1_700_000_000, LogStream::Stderr);
+        let line = record.to_line();
+
+        assert!(line.contains("\"stream\":\"stderr\""));
+        assert_eq!(LogRecord::parse(&line), Some(record));
+        assert_eq!(LogRecord::parse("plain text"), None);
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = LogFilter {
+            since: Some(Utc.timestamp_opt(100, 0).unwrap()),
+            until: Some(Utc.timestamp_opt(200, 0).unwrap()),
+            stream: Some(LogStream::Stderr),
+        };
+
+        assert!(filter.matches(&record(100, LogStream::Stderr)));
+        assert!(!filter.matches(&record(99, LogStream::Stderr)));
+        assert!(!filter.matches(&record(200, LogStream::Stderr)));
+        assert!(!filter.matches(&record(150, LogStream::Stdout)));
+        assert!(LogFilter::default().matches(&record(0, LogStream::Stdout)));
+    }
+
+    #[test]
+    fn test_find_relay() {
+        let temp = TempDir::new().unwrap();
+        let lars = temp
+            .path()
+            .join(format!("lars{}", std::env::consts::EXE_SUFFIX));
+        let embedder = temp.path().join("my-app");
+        let relay = PathBuf::from("/opt/relay");
+
+        // The override wins, then the current executable if it is lars
+        let found = find_relay(Some(relay.clone().into()), Some(lars.clone()), None);
+        assert_eq!(found.unwrap(), relay);
+        assert_eq!(find_relay(None, Some(lars.clone()), None).unwrap(), lars);
+
+        // An embedder never runs itself; lars is looked up on the PATH
+        let err = find_relay(None, Some(embedder.clone()), None).unwrap_err();
+        assert!(err.to_string().contains(RELAY_PROGRAM_ENV));
+
+        std::fs::write(&lars, "").unwrap();
+        let path = Some(temp.path().as_os_str().to_owned());
+        assert_eq!(find_relay(None, Some(embedder), path).unwrap(), lars);
+    }
+
+    #[test]
+    fn test_relay_splits_long_lines() {
+        let temp = TempDir::new().unwrap();
+        let log_path = temp.path().join("svc.log");
+        let log = Mutex::new(std::fs::File::create(&log_path).unwrap());
+
+        let mut output = vec![b'x'; MAX_RECORD_BYTES * 2 + 10];
+        output.extend_from_slice(b"\ndone\n");
+        relay_lines(output.as_slice(), LogStream::Stdout, &log);
+
+        let lengths: Vec<usize> = std::fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .filter_map(LogRecord::parse)
+            .map(|r| r.line.len())
+            .collect();
+        assert_eq!(lengths, [MAX_RECORD_BYTES, MAX_RECORD_BYTES, 10, 4]);
+    }
+
+    #[test]
+    fn test_relay_captures_streams_and_exit_code() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo out; echo err >&2; exit 3".to_string(),
+        ];
+
+        assert_eq!(run_relay(&log, &command).unwrap(), 3);
+
+        let records: Vec<LogRecord> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .filter_map(LogRecord::parse)
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert!(records
+            .iter()
+            .any(|r| r.stream == LogStream::Stdout && r.line == "out"));
+        assert!(records
+            .iter()
+            .any(|r| r.stream == LogStream::Stderr && r.line == "err"));
+    }
+}
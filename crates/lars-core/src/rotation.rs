@@ -0,0 +1,359 @@
+//! Log rotation for service logs
+//!
+//! Logs are rotated by copying the current file into a gzip archive next to
+//! it (`<id>.log.1.gz`, `<id>.log.2.gz`, ...) and truncating it in place, so
+//! a running service keeps writing to the same file. Runners open logs in
+//! append mode, which makes the truncation safe; lines written while the copy
+//! is in progress can be lost.
+//!
+//! When a new run starts, the log of the run that ended is archived to
+//! `<id>.log.previous.gz` instead, so that it is not mistaken for a chunk
+//! rotated away by size or age. The archive it replaces joins the numbered
+//! ones.
+
+use crate::error::Result;
+use crate::models::AppSettings;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// When logs are rotated and how many archives are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Rotate once the log reaches this size
+    pub max_bytes: Option<u64>,
+    /// Rotate once the log has been written to for this long
+    pub max_age: Option<Duration>,
+    /// Number of archives to keep (0 discards old logs)
+    pub retain: usize,
+}
+
+impl RotationPolicy {
+    /// Build the policy from the application settings
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            max_bytes: (settings.log_max_size_mb > 0)
+                .then(|| settings.log_max_size_mb * 1024 * 1024),
+            max_age: (settings.log_max_age_hours > 0)
+                .then(|| Duration::from_secs(settings.log_max_age_hours * 3600)),
+            retain: settings.log_retain,
+        }
+    }
+
+    /// Check whether the log at `log_path` is due for rotation.
+    ///
+    /// A log's age is measured from its last rotation, or from its creation
+    /// if it has never been rotated.
+    pub fn should_rotate(&self, log_path: &Path, now: SystemTime) -> bool {
+        let Ok(metadata) = fs::metadata(log_path) else {
+            return false;
+        };
+
+        if metadata.len() == 0 {
+            return false;
+        }
+
+        if self.max_bytes.is_some_and(|max| metadata.len() >= max) {
+            return true;
+        }
+
+        let Some(max_age) = self.max_age else {
+            return false;
+        };
+
+        let started = fs::metadata(rotated_path(log_path))
+            .and_then(|m| m.modified())
+            .or_else(|_| metadata.created());
+
+        started
+            .ok()
+            .and_then(|t| now.duration_since(t).ok())
+            .is_some_and(|age| age >= max_age)
+    }
+}
+
+/// Get the path of the `n`th archive of a log (1 is the most recent)
+pub fn archive_path(log_path: &Path, n: usize) -> PathBuf {
+    let mut name = log_path.as_os_str().to_owned();
+    name.push(format!(".{}.gz", n));
+    PathBuf::from(name)
+}
+
+/// Get the path of the archive holding the log of a service's previous run
+pub fn previous_path(log_path: &Path) -> PathBuf {
+    let mut name = log_path.as_os_str().to_owned();
+    name.push(".previous.gz");
+    PathBuf::from(name)
+}
+
+/// Get the path of the marker whose modification time records when a log
+/// was last rotated.
+///
+/// Truncating a log keeps its creation time, and with no archives retained
+/// there is nothing else to date the rotation by.
+pub fn rotated_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("rotated")
+}
+
+/// Rotate a log, keeping at most `retain` archives.
+///
+/// Returns `Ok(false)` without touching anything if the log is missing or
+/// empty.
+pub fn rotate(log_path: &Path, retain: usize) -> Result<bool> {
+    if is_empty(log_path) {
+        return Ok(false);
+    }
+
+    shift(log_path, retain)?;
+    if retain > 0 {
+        compress(log_path, &archive_path(log_path, 1))?;
+    }
+    truncate(log_path)?;
+
+    Ok(true)
+}
+
+/// Archive the log of a run that ended as a new run starts, moving the
+/// archive of the run before into the numbered archives. Nothing is archived
+/// if `retain` is 0.
+///
+/// Returns `Ok(false)` without touching anything if the log is missing or
+/// empty.
+pub fn archive_run(log_path: &Path, retain: usize) -> Result<bool> {
+    if is_empty(log_path) {
+        return Ok(false);
+    }
+
+    let previous = previous_path(log_path);
+    if previous.exists() {
+        shift(log_path, retain)?;
+        if retain > 0 {
+            fs::rename(&previous, archive_path(log_path, 1))?;
+        } else {
+            fs::remove_file(&previous)?;
+        }
+    }
+    if retain > 0 {
+        compress(log_path, &previous)?;
+    }
+    truncate(log_path)?;
+
+    Ok(true)
+}
+
+fn is_empty(log_path: &Path) -> bool {
+    !fs::metadata(log_path).is_ok_and(|metadata| metadata.len() > 0)
+}
+
+/// Drop archives beyond the retention limit, then shift the rest up to free
+/// the first slot
+fn shift(log_path: &Path, retain: usize) -> Result<()> {
+    let mut n = retain.max(1);
+    while archive_path(log_path, n).exists() {
+        fs::remove_file(archive_path(log_path, n))?;
+        n += 1;
+    }
+    for n in (1..retain).rev() {
+        let from = archive_path(log_path, n);
+        if from.exists() {
+            fs::rename(from, archive_path(log_path, n + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// Compress a log into `archive`, replacing it in one step
+fn compress(log_path: &Path, archive: &Path) -> Result<()> {
+    let mut temp_name = archive.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp = PathBuf::from(temp_name);
+
+    let mut encoder = GzEncoder::new(File::create(&temp)?, Compression::default());
+    io::copy(&mut File::open(log_path)?, &mut encoder)?;
+    encoder.finish()?;
+    fs::rename(&temp, archive)?;
+    Ok(())
+}
+
+/// Empty a log in place and note when it was rotated
+fn truncate(log_path: &Path) -> Result<()> {
+    OpenOptions::new().write(true).open(log_path)?.set_len(0)?;
+    File::create(rotated_path(log_path))?.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Open the `n`th archive of a log for reading, if it exists
+pub fn open_archive(log_path: &Path, n: usize) -> Result<Option<Box<dyn BufRead>>> {
+    open_gz(&archive_path(log_path, n))
+}
+
+/// Open the archived log of the previous run for reading, if it exists
+pub fn open_previous(log_path: &Path) -> Result<Option<Box<dyn BufRead>>> {
+    open_gz(&previous_path(log_path))
+}
+
+fn open_gz(path: &Path) -> Result<Option<Box<dyn BufRead>>> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(Box::new(BufReader::new(GzDecoder::new(file))))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn read_archive(log_path: &Path, n: usize) -> String {
+        let mut out = String::new();
+        open_archive(log_path, n)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_archive_path() {
+        assert_eq!(
+            archive_path(Path::new("/logs/abc.log"), 2),
+            PathBuf::from("/logs/abc.log.2.gz")
+        );
+    }
+
+    #[test]
+    fn test_rotate_shifts_and_retains() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+
+        for run in 1..=4 {
+            fs::write(&log, format!("run {}\n", run)).unwrap();
+            assert!(rotate(&log, 2).unwrap());
+        }
+
+        assert_eq!(fs::metadata(&log).unwrap().len(), 0);
+        assert_eq!(read_archive(&log, 1), "run 4\n");
+        assert_eq!(read_archive(&log, 2), "run 3\n");
+        assert!(!archive_path(&log, 3).exists());
+    }
+
+    #[test]
+    fn test_archive_run_keeps_chunks_apart() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+
+        fs::write(&log, "run 1\n").unwrap();
+        assert!(archive_run(&log, 3).unwrap());
+        fs::write(&log, "run 2, part 1\n").unwrap();
+        assert!(rotate(&log, 3).unwrap());
+        fs::write(&log, "run 2, part 2\n").unwrap();
+        assert!(archive_run(&log, 3).unwrap());
+
+        let mut previous = String::new();
+        open_previous(&log)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut previous)
+            .unwrap();
+        assert_eq!(previous, "run 2, part 2\n");
+        assert_eq!(read_archive(&log, 1), "run 1\n");
+        assert_eq!(read_archive(&log, 2), "run 2, part 1\n");
+        assert_eq!(fs::metadata(&log).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_rotate_skips_empty_or_missing() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+
+        assert!(!rotate(&log, 3).unwrap());
+        fs::write(&log, "").unwrap();
+        assert!(!rotate(&log, 3).unwrap());
+        assert!(open_archive(&log, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rotate_without_retention_truncates() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+        fs::write(&log, "old output\n").unwrap();
+
+        assert!(rotate(&log, 0).unwrap());
+        assert_eq!(fs::metadata(&log).unwrap().len(), 0);
+        assert!(!archive_path(&log, 1).exists());
+    }
+
+    #[test]
+    fn test_should_rotate_by_size() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+        let policy = RotationPolicy {
+            max_bytes: Some(10),
+            max_age: None,
+            retain: 1,
+        };
+
+        assert!(!policy.should_rotate(&log, SystemTime::now()));
+        fs::write(&log, "short").unwrap();
+        assert!(!policy.should_rotate(&log, SystemTime::now()));
+        fs::write(&log, "much longer line").unwrap();
+        assert!(policy.should_rotate(&log, SystemTime::now()));
+    }
+
+    #[test]
+    fn test_should_rotate_by_age() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+        fs::write(&log, "output\n").unwrap();
+        rotate(&log, 1).unwrap();
+        fs::write(&log, "more output\n").unwrap();
+
+        let policy = RotationPolicy {
+            max_bytes: None,
+            max_age: Some(Duration::from_secs(3600)),
+            retain: 1,
+        };
+
+        let now = SystemTime::now();
+        assert!(!policy.should_rotate(&log, now));
+        assert!(policy.should_rotate(&log, now + Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn test_should_rotate_by_age_without_retention() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("svc.log");
+        let policy = RotationPolicy {
+            max_bytes: None,
+            max_age: Some(Duration::from_secs(3600)),
+            retain: 0,
+        };
+
+        fs::write(&log, "output\n").unwrap();
+        rotate(&log, 0).unwrap();
+        fs::write(&log, "more output\n").unwrap();
+        assert!(!archive_path(&log, 1).exists());
+
+        // Age the last rotation past the limit
+        let now = SystemTime::now();
+        File::options()
+            .write(true)
+            .open(rotated_path(&log))
+            .unwrap()
+            .set_modified(now - Duration::from_secs(7200))
+            .unwrap();
+        assert!(policy.should_rotate(&log, now));
+
+        // Rotating again restarts the log's age, though no archive is kept
+        rotate(&log, 0).unwrap();
+        fs::write(&log, "latest output\n").unwrap();
+        assert!(!policy.should_rotate(&log, SystemTime::now()));
+    }
+}
@@ -15,9 +15,11 @@
 //! privileges as the LARS process itself.
 
 use crate::error::{LarsError, Result};
-use crate::models::{RunnerType, Service};
-use std::path::Path;
-use std::process::Command;
+use crate::models::{LogFormat, LogMode, RunnerType, Service};
+use crate::relay;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// Trait for process runners that manage service lifecycle
@@ -36,17 +38,26 @@ pub trait Runner: Send + Sync {
     fn restart(&self, service: &Service, log_path: &Path, timeout_secs: u64) -> Result<()> {
         if self.is_running(service)? {
             self.stop(service)?;
+            self.wait_stopped(service, timeout_secs)?;
+        }
 
-            let start = Instant::now();
-            while self.is_running(service)? {
-                if start.elapsed() > Duration::from_secs(timeout_secs) {
-                    return Err(LarsError::StopTimeout(service.name.clone()));
-                }
-                std::thread::sleep(Duration::from_millis(100));
+        self.start(service, log_path)
+    }
+
+    /// Wait until a service is no longer running
+    ///
+    /// Returns [`LarsError::StopTimeout`] if it is still running after
+    /// `timeout_secs`.
+    fn wait_stopped(&self, service: &Service, timeout_secs: u64) -> Result<()> {
+        let start = Instant::now();
+        while self.is_running(service)? {
+            if start.elapsed() > Duration::from_secs(timeout_secs) {
+                return Err(LarsError::StopTimeout(service.name.clone()));
             }
+            std::thread::sleep(Duration::from_millis(100));
         }
 
-        self.start(service, log_path)
+        Ok(())
     }
 
     /// Check if a service is running
@@ -61,82 +72,314 @@ pub trait Runner: Send + Sync {
 
     /// Get the runner type
     fn runner_type(&self) -> RunnerType;
+
+    /// What the runner can do beyond starting and stopping services, so
+    /// callers can check before relying on it. Those of its
+    /// [`RunnerType`], unless the runner says otherwise.
+    fn capabilities(&self) -> RunnerCapabilities {
+        self.runner_type().capabilities()
+    }
 }
 
-/// Tmux-based runner for session management
+/// What a runner can do beyond starting and stopping services
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunnerCapabilities {
+    /// Attach a terminal to the service ([`Runner::attach_command`])
+    pub attach: bool,
+    /// Report the PID of the service's process ([`Runner::get_pid`]), which
+    /// resource usage and metrics are measured from
+    pub pid: bool,
+    /// Run the service in its own process group, so stopping it also stops
+    /// the processes it started
+    pub process_group: bool,
+    /// Keep services running after lars exits
+    pub persistent: bool,
+    /// Type input into the service's terminal without attaching to it
+    pub send_input: bool,
+    /// Keep the service's recent output for display on attach, beyond
+    /// what its log holds
+    pub scrollback: bool,
+    /// Resize the service's terminal
+    pub resize: bool,
+}
+
+impl RunnerCapabilities {
+    /// Capabilities of tmux sessions
+    pub const TMUX: Self = Self {
+        attach: true,
+        pid: true,
+        process_group: true,
+        persistent: true,
+        send_input: true,
+        scrollback: true,
+        resize: true,
+    };
+
+    /// Names of the capabilities the runner has
+    pub fn supported(&self) -> Vec<&'static str> {
+        self.named()
+            .into_iter()
+            .filter_map(|(name, has)| has.then_some(name))
+            .collect()
+    }
+
+    /// Names of the capabilities the runner lacks
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.named()
+            .into_iter()
+            .filter_map(|(name, has)| (!has).then_some(name))
+            .collect()
+    }
+
+    fn named(&self) -> [(&'static str, bool); 7] {
+        [
+            ("attach", self.attach),
+            ("pid", self.pid),
+            ("process group", self.process_group),
+            ("persistence across lars exit", self.persistent),
+            ("send input", self.send_input),
+            ("scrollback", self.scrollback),
+            ("resize", self.resize),
+        ]
+    }
+}
+
+impl RunnerType {
+    /// What runners of this type can do, known without creating one, so
+    /// it is also known when the runner is unavailable
+    pub fn capabilities(self) -> RunnerCapabilities {
+        match self {
+            RunnerType::Tmux => RunnerCapabilities::TMUX,
+            // Not implemented yet, so they can do nothing beyond the basics
+            RunnerType::Screen | RunnerType::Direct => RunnerCapabilities::default(),
+        }
+    }
+}
+
+/// Get the path a service's exit status is written to, given its log path.
+///
+/// Runners write the command's exit code to this file when it finishes on
+/// its own, so one-shot tasks and crashes can be told apart from stops.
+pub fn exit_path_for_log(log_path: &Path) -> PathBuf {
+    log_path.with_extension("exit")
+}
+
+/// Get a service's files ready for a new run, right before it is launched:
+/// clears the previous run's exit status and, in truncate mode, the log.
+pub fn prepare_launch(service: &Service, log_path: &Path) -> Result<()> {
+    match std::fs::remove_file(exit_path_for_log(log_path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+
+    // Services always append, so that log rotation can truncate the file
+    // while they run; truncate mode empties it up front instead.
+    if service.log_mode == LogMode::Truncate {
+        std::fs::File::create(log_path)?;
+    }
+    Ok(())
+}
+
+/// Oldest tmux that can set a session's environment (`new-session -e`)
+pub const MIN_TMUX_VERSION: (u32, u32) = (3, 0);
+
+/// Parse the version out of `tmux -V` output such as `tmux 3.3a` or
+/// `tmux next-3.4`; builds without a version number yield `None`
+pub fn parse_tmux_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().rsplit([' ', '-']).next()?;
+    let (major, minor) = version.split_once('.')?;
+    let minor: String = minor.chars().take_while(char::is_ascii_digit).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// What a command run by a [`CommandExecutor`] reported
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub success: bool,
+    /// Exit status as shown in error messages, e.g. `exit status: 1`
+    pub status: String,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Error for a command that failed, with what it printed to stderr
+    fn failure(&self, command: &str) -> LarsError {
+        let mut message = format!("{} failed with status: {}", command, self.status);
+        if !self.stderr.trim().is_empty() {
+            message.push_str(&format!(" ({})", self.stderr.trim()));
+        }
+        LarsError::ProcessFailed(message)
+    }
+}
+
+/// Runs the external commands a runner is built on, such as `tmux`.
+///
+/// [`TmuxRunner`] runs every command through one, so tests can substitute
+/// canned answers for a real tmux server.
+pub trait CommandExecutor: Send + Sync {
+    /// Run `program` with `args` to completion, capturing its output
+    fn run(&self, program: &str, args: &[String]) -> Result<CommandOutput>;
+}
+
+/// Runs commands as child processes
 #[derive(Debug, Default)]
-pub struct TmuxRunner;
+pub struct SystemExecutor;
+
+impl CommandExecutor for SystemExecutor {
+    fn run(&self, program: &str, args: &[String]) -> Result<CommandOutput> {
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()?;
+
+        Ok(CommandOutput {
+            success: output.status.success(),
+            status: output.status.to_string(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// Tmux-based runner for session management
+#[derive(Clone)]
+pub struct TmuxRunner {
+    executor: Arc<dyn CommandExecutor>,
+}
+
+impl std::fmt::Debug for TmuxRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TmuxRunner").finish_non_exhaustive()
+    }
+}
+
+impl Default for TmuxRunner {
+    fn default() -> Self {
+        Self::with_executor(Arc::new(SystemExecutor))
+    }
+}
 
 impl TmuxRunner {
     /// Create a new TmuxRunner
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Create a TmuxRunner that runs `tmux` through `executor`
+    pub fn with_executor(executor: Arc<dyn CommandExecutor>) -> Self {
+        Self { executor }
+    }
+
+    /// Run a tmux subcommand
+    fn tmux<S: AsRef<str>>(&self, args: &[S]) -> Result<CommandOutput> {
+        let args: Vec<String> = args.iter().map(|a| a.as_ref().to_string()).collect();
+        self.executor.run("tmux", &args)
     }
 
     /// Generate the tmux session name for a service
-    fn session_name(service: &Service) -> String {
+    pub(crate) fn session_name(service: &Service) -> String {
         format!("lar_{}", service.id)
     }
 
+    /// Arguments to `tmux` that start a service in a new detached session.
+    ///
+    /// Only builds the command; [`prepare_launch`] readies the service's
+    /// files.
+    pub(crate) fn new_session_args(service: &Service, log_path: &Path) -> Result<Vec<String>> {
+        let log_path_str = log_path.to_str().ok_or(LarsError::InvalidPath)?;
+        let escaped_log_path = shell_escape::escape(log_path_str.into());
+
+        // Run the command in a subshell (or under the log relay, for
+        // structured logs) so an `exit` in it cannot skip
+        // recording the exit status, which is written once the command
+        // finishes on its own. Newlines keep a trailing comment contained.
+        let exit_path = exit_path_for_log(log_path);
+        let exit_path_str = exit_path.to_str().ok_or(LarsError::InvalidPath)?;
+        let escaped_exit_path = shell_escape::escape(exit_path_str.into());
+
+        let shell_cmd = match service.log_format {
+            LogFormat::Raw => format!(
+                "(\n{}\n) >> {} 2>&1\necho $? > {}",
+                &service.command, escaped_log_path, escaped_exit_path
+            ),
+            LogFormat::Json => {
+                let relay = relay::relay_program()?;
+                let relay_str = relay.to_str().ok_or(LarsError::InvalidPath)?;
+                format!(
+                    "{} {} {} -- sh -c {}\necho $? > {}",
+                    shell_escape::escape(relay_str.into()),
+                    relay::RELAY_SUBCOMMAND,
+                    escaped_log_path,
+                    shell_escape::escape(service.command.as_str().into()),
+                    escaped_exit_path
+                )
+            }
+        };
+
+        let mut args = vec![
+            "new-session".to_string(),
+            "-d".to_string(),
+            "-s".to_string(),
+            Self::session_name(service),
+        ];
+
+        if let Some(cwd) = &service.cwd {
+            let cwd_str = cwd.to_str().ok_or(LarsError::InvalidPath)?;
+            args.extend(["-c".to_string(), cwd_str.to_string()]);
+        }
+
+        // Passed with -e rather than to the tmux client, whose environment
+        // only reaches the session when it starts a new tmux server
+        for (key, value) in &service.env {
+            args.extend(["-e".to_string(), format!("{}={}", key, value)]);
+        }
+
+        args.extend(["sh".to_string(), "-c".to_string(), shell_cmd]);
+        Ok(args)
+    }
+
     /// Check if tmux is available
-    pub fn is_available() -> bool {
-        Command::new("tmux")
-            .arg("-V")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
+    pub fn is_available(&self) -> bool {
+        self.version().is_some()
     }
 
     /// Get tmux version string
-    pub fn version() -> Option<String> {
-        Command::new("tmux")
-            .arg("-V")
-            .output()
+    pub fn version(&self) -> Option<String> {
+        self.tmux(&["-V"])
             .ok()
-            .filter(|o| o.status.success())
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .filter(|o| o.success)
+            .map(|o| o.stdout.trim().to_string())
     }
 }
 
 impl Runner for TmuxRunner {
     fn start(&self, service: &Service, log_path: &Path) -> Result<()> {
-        if !Self::is_available() {
+        let Some(version) = self.version() else {
             return Err(LarsError::RunnerNotAvailable(
                 "tmux is not installed or not in PATH".to_string(),
             ));
+        };
+        let too_old = parse_tmux_version(&version).is_some_and(|v| v < MIN_TMUX_VERSION);
+        if too_old && !service.env.is_empty() {
+            return Err(LarsError::RunnerNotAvailable(format!(
+                "{} cannot pass environment variables to a session; tmux {}.{} or later is required",
+                version,
+                MIN_TMUX_VERSION.0,
+                MIN_TMUX_VERSION.1
+            )));
         }
 
-        let session_name = Self::session_name(service);
-
         if self.is_running(service)? {
             return Ok(());
         }
 
-        let log_path_str = log_path.to_str().ok_or(LarsError::InvalidPath)?;
-        let escaped_log_path = shell_escape::escape(log_path_str.into());
-        let shell_cmd = format!("{} > {} 2>&1", &service.command, escaped_log_path);
-
-        let mut cmd = Command::new("tmux");
-        cmd.args(["new-session", "-d", "-s", &session_name]);
-
-        if let Some(cwd) = &service.cwd {
-            let cwd_str = cwd.to_str().ok_or(LarsError::InvalidPath)?;
-            cmd.args(["-c", cwd_str]);
-        }
-
-        cmd.args(["sh", "-c", &shell_cmd]);
+        let args = Self::new_session_args(service, log_path)?;
+        prepare_launch(service, log_path)?;
+        let output = self.tmux(&args)?;
 
-        for (key, value) in &service.env {
-            cmd.env(key, value);
-        }
-
-        let status = cmd.status()?;
-
-        if !status.success() {
-            return Err(LarsError::ProcessFailed(format!(
-                "tmux new-session failed with status: {}",
-                status
-            )));
+        if !output.success {
+            return Err(output.failure("tmux new-session"));
         }
 
         Ok(())
@@ -145,16 +388,11 @@ impl Runner for TmuxRunner {
     fn stop(&self, service: &Service) -> Result<()> {
         let session_name = Self::session_name(service);
 
-        let status = Command::new("tmux")
-            .args(["kill-session", "-t", &session_name])
-            .status()?;
+        let output = self.tmux(&["kill-session", "-t", &session_name])?;
 
         // It's okay if the session doesn't exist (might have already exited)
-        if !status.success() && self.is_running(service)? {
-            return Err(LarsError::ProcessFailed(format!(
-                "tmux kill-session failed with status: {}",
-                status
-            )));
+        if !output.success && self.is_running(service)? {
+            return Err(output.failure("tmux kill-session"));
         }
 
         Ok(())
@@ -163,34 +401,21 @@ impl Runner for TmuxRunner {
     fn is_running(&self, service: &Service) -> Result<bool> {
         let session_name = Self::session_name(service);
 
-        let status = Command::new("tmux")
-            .args(["has-session", "-t", &session_name])
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()?;
+        let output = self.tmux(&["has-session", "-t", &session_name])?;
 
-        Ok(status.success())
+        Ok(output.success)
     }
 
     fn get_pid(&self, service: &Service) -> Result<Option<u32>> {
         let session_name = Self::session_name(service);
 
-        let output = Command::new("tmux")
-            .args([
-                "list-panes",
-                "-t",
-                &session_name,
-                "-F",
-                "#{pane_pid}",
-            ])
-            .output()?;
+        let output = self.tmux(&["list-panes", "-t", &session_name, "-F", "#{pane_pid}"])?;
 
-        if !output.status.success() {
+        if !output.success {
             return Ok(None);
         }
 
-        let pid_str = String::from_utf8_lossy(&output.stdout);
-        let pid = pid_str.trim().parse::<u32>().ok();
+        let pid = output.stdout.trim().parse::<u32>().ok();
 
         Ok(pid)
     }
@@ -213,14 +438,24 @@ impl Runner for TmuxRunner {
 
 /// Create a runner for the specified type
 pub fn create_runner(runner_type: RunnerType) -> Result<Box<dyn Runner>> {
+    create_runner_with(runner_type, Arc::new(SystemExecutor))
+}
+
+/// Create a runner for the specified type that runs its commands through
+/// `executor`
+pub fn create_runner_with(
+    runner_type: RunnerType,
+    executor: Arc<dyn CommandExecutor>,
+) -> Result<Box<dyn Runner>> {
     match runner_type {
         RunnerType::Tmux => {
-            if !TmuxRunner::is_available() {
+            let runner = TmuxRunner::with_executor(executor);
+            if !runner.is_available() {
                 return Err(LarsError::RunnerNotAvailable(
                     "tmux is not installed or not in PATH".to_string(),
                 ));
             }
-            Ok(Box::new(TmuxRunner::new()))
+            Ok(Box::new(runner))
         }
         RunnerType::Screen => Err(LarsError::RunnerNotAvailable(
             "screen runner is not yet implemented".to_string(),
@@ -234,6 +469,7 @@ pub fn create_runner(runner_type: RunnerType) -> Result<Box<dyn Runner>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::ScriptedExecutor;
 
     #[test]
     fn test_session_name() {
@@ -244,12 +480,165 @@ mod tests {
         assert!(session_name.contains(&service.id.to_string()));
     }
 
+    #[test]
+    fn test_exit_path_for_log() {
+        let exit_path = exit_path_for_log(Path::new("/logs/abc.log"));
+        assert_eq!(exit_path, PathBuf::from("/logs/abc.exit"));
+    }
+
     #[test]
     fn test_tmux_runner_type() {
         let runner = TmuxRunner::new();
         assert_eq!(runner.runner_type(), RunnerType::Tmux);
     }
 
+    #[test]
+    fn test_capability_names() {
+        assert!(RunnerCapabilities::TMUX.missing().is_empty());
+        assert_eq!(RunnerCapabilities::TMUX.supported().len(), 7);
+
+        let caps = RunnerCapabilities {
+            pid: true,
+            persistent: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            caps.supported(),
+            vec!["pid", "persistence across lars exit"]
+        );
+        assert_eq!(
+            caps.missing(),
+            vec![
+                "attach",
+                "process group",
+                "send input",
+                "scrollback",
+                "resize"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runner_type_capabilities() {
+        assert_eq!(RunnerType::Tmux.capabilities(), RunnerCapabilities::TMUX);
+        assert_eq!(TmuxRunner::new().capabilities(), RunnerCapabilities::TMUX);
+        // Known even though no screen or direct runner can be created
+        assert!(create_runner(RunnerType::Screen).is_err());
+        assert_eq!(RunnerType::Screen.capabilities().supported().len(), 0);
+        assert!(!RunnerType::Direct.capabilities().attach);
+    }
+
+    #[test]
+    fn test_tmux_runner_commands() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let log_path = temp.path().join("api.log");
+        let tmux = Arc::new(ScriptedExecutor::new());
+        let runner = TmuxRunner::with_executor(tmux.clone());
+        let service = Service::new("api".to_string(), "serve".to_string());
+
+        assert!(runner.is_available());
+        assert_eq!(runner.version().as_deref(), Some("tmux 3.4"));
+        assert!(!runner.is_running(&service).unwrap());
+        runner.start(&service, &log_path).unwrap();
+        assert!(runner.is_running(&service).unwrap());
+        assert_eq!(runner.get_pid(&service).unwrap(), Some(4242));
+
+        // Starting a running service leaves its session alone
+        runner.start(&service, &log_path).unwrap();
+        assert_eq!(tmux.count("new-session"), 1);
+
+        let calls = tmux.calls();
+        let new_session = calls.iter().find(|c| c[0] == "new-session").unwrap();
+        assert_eq!(new_session[3], TmuxRunner::session_name(&service));
+        assert!(new_session.last().unwrap().contains("serve"));
+
+        runner.stop(&service).unwrap();
+        assert!(!runner.is_running(&service).unwrap());
+        assert_eq!(runner.get_pid(&service).unwrap(), None);
+        runner.wait_stopped(&service, 1).unwrap();
+    }
+
+    #[test]
+    fn test_tmux_runner_errors() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let log_path = temp.path().join("api.log");
+        let service = Service::new("api".to_string(), "serve".to_string());
+
+        let tmux = Arc::new(ScriptedExecutor::new());
+        tmux.uninstall();
+        let runner = TmuxRunner::with_executor(tmux.clone());
+        assert!(!runner.is_available());
+        assert_eq!(runner.version(), None);
+        let err = runner.start(&service, &log_path).unwrap_err();
+        assert!(matches!(err, LarsError::RunnerNotAvailable(_)));
+        let result = create_runner_with(RunnerType::Tmux, tmux);
+        assert!(matches!(result, Err(LarsError::RunnerNotAvailable(_))));
+
+        let tmux = Arc::new(ScriptedExecutor::new());
+        tmux.refuse_kill();
+        let runner = TmuxRunner::with_executor(tmux);
+        runner.start(&service, &log_path).unwrap();
+        let err = runner.stop(&service).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Process execution failed: tmux kill-session failed with status: exit status: 1 \
+             (no server running)"
+        );
+    }
+
+    #[test]
+    fn test_old_tmux_refuses_env() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let log_path = temp.path().join("api.log");
+        let mut service = Service::new("api".to_string(), "serve".to_string());
+        let tmux = Arc::new(ScriptedExecutor::new());
+        tmux.set_version("tmux 2.9a");
+        let runner = TmuxRunner::with_executor(tmux);
+
+        runner.start(&service, &log_path).unwrap();
+        runner.stop(&service).unwrap();
+
+        service.env.insert("PORT".to_string(), "3000".to_string());
+        let err = runner.start(&service, &log_path).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("tmux 2.9a cannot pass environment"));
+    }
+
+    #[test]
+    fn test_launch_preparation_is_separate() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let log_path = temp.path().join("api.log");
+        let exit_path = exit_path_for_log(&log_path);
+        let mut service = Service::new("api".to_string(), "serve".to_string());
+        service.log_mode = LogMode::Truncate;
+        std::fs::write(&log_path, "previous run\n").unwrap();
+        std::fs::write(&exit_path, "1\n").unwrap();
+
+        // Building the command leaves the files alone
+        let args = TmuxRunner::new_session_args(&service, &log_path).unwrap();
+        assert_eq!(args[0], "new-session");
+        assert!(exit_path.exists());
+        assert_eq!(
+            std::fs::read_to_string(&log_path).unwrap(),
+            "previous run\n"
+        );
+
+        prepare_launch(&service, &log_path).unwrap();
+        assert!(!exit_path.exists());
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "");
+        // Nothing to clear the second time
+        prepare_launch(&service, &log_path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_tmux_version() {
+        assert_eq!(parse_tmux_version("tmux 3.3a\n"), Some((3, 3)));
+        assert_eq!(parse_tmux_version("tmux next-3.5"), Some((3, 5)));
+        assert_eq!(parse_tmux_version("tmux 2.9"), Some((2, 9)));
+        assert_eq!(parse_tmux_version("tmux master"), None);
+    }
+
     // Integration tests that require tmux are marked with #[ignore]
     // Run with: cargo test -- --ignored
 
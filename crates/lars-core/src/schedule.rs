@@ -0,0 +1,526 @@
+//! Scheduling for periodic services
+//!
+//! A schedule is either a standard five-field cron expression
+//! (`minute hour day-of-month month day-of-week`, evaluated in local time),
+//! a macro such as `@hourly`, or a fixed interval such as `every 10m`.
+//! [`Scheduler`] turns schedules into start decisions for the daemon,
+//! applying each service's [`OverlapPolicy`].
+
+use crate::duration::parse_duration;
+use crate::models::{OverlapPolicy, Service};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone,
+    Timelike, Utc,
+};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Upper bound on search steps when looking for the next cron match, which
+/// stops expressions that can never match (e.g. `0 0 30 2 *`) from looping
+const MAX_SEARCH_STEPS: usize = 100_000;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed schedule
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    Cron(CronExpr),
+    Every(Duration),
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let interval = s
+            .strip_prefix("@every ")
+            .or_else(|| s.strip_prefix("every "));
+        if let Some(interval) = interval {
+            let duration = parse_duration(interval)?;
+            if duration.is_zero() {
+                return Err("Schedule interval must be greater than 0".to_string());
+            }
+            return Ok(Schedule::Every(duration));
+        }
+
+        let expanded = match s {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        CronExpr::parse(expanded).map(Schedule::Cron)
+    }
+}
+
+impl Schedule {
+    /// Get the first time after `after` at which the schedule fires
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => Some(after + to_chrono(*interval)),
+            Schedule::Cron(expr) => expr
+                .next_after_in(after.with_timezone(&Local))
+                .map(|t| t.with_timezone(&Utc)),
+        }
+    }
+
+    /// Get the next run time given when the service last started.
+    ///
+    /// Intervals count from the last start and are due immediately if the
+    /// service has never run; cron expressions fire at their next match.
+    pub fn next_run(
+        &self,
+        last_started: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => {
+                Some(last_started.map_or(now, |last| last + to_chrono(*interval)))
+            }
+            Schedule::Cron(_) => self.next_after(now),
+        }
+    }
+}
+
+fn to_chrono(duration: Duration) -> ChronoDuration {
+    ChronoDuration::from_std(duration).unwrap_or(ChronoDuration::MAX)
+}
+
+/// A five-field cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether day-of-month starts with `*` (`*` or a step like `*/2`);
+    /// cron matches either day field only when both are restricted
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronExpr {
+    /// Parse `minute hour day-of-month month day-of-week`
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid schedule '{}': expected 5 cron fields, an @macro, or 'every <duration>'",
+                expr
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, DAY_NAMES)?;
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days_of_month: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, MONTH_NAMES)?,
+            days_of_week,
+            any_day_of_month: fields[2].starts_with('*'),
+            any_day_of_week: fields[4].starts_with('*'),
+        })
+    }
+
+    /// Get the first matching minute strictly after `after`, in its time zone
+    pub fn next_after_in<Tz: TimeZone>(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local() + ChronoDuration::minutes(1);
+        let mut t = start.with_second(0)?.with_nanosecond(0)?;
+
+        for _ in 0..MAX_SEARCH_STEPS {
+            if !has_bit(self.months, t.month()) {
+                t = first_of_next_month(t.date())?;
+            } else if !self.day_matches(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has_bit(self.hours, t.hour()) {
+                t = t.with_minute(0)? + ChronoDuration::hours(1);
+            } else if !has_bit(self.minutes, t.minute()) {
+                t += ChronoDuration::minutes(1);
+            } else {
+                // Skip local times that don't exist (DST gaps)
+                match tz.from_local_datetime(&t).earliest() {
+                    Some(found) => return Some(found),
+                    None => t += ChronoDuration::minutes(1),
+                }
+            }
+        }
+
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = has_bit(self.days_of_month, date.day());
+        let dow = has_bit(self.days_of_week, date.weekday().num_days_from_sunday());
+
+        if self.any_day_of_month || self.any_day_of_week {
+            dom && dow
+        } else {
+            dom || dow
+        }
+    }
+}
+
+fn has_bit(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDateTime> {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+/// Parse one cron field (`*`, `5`, `1-5`, `*/15`, `mon-fri`, `1,15`) into a bitset
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let invalid = || format!("Invalid cron field: {}", field);
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_lowercase();
+        let v = match names.iter().position(|n| *n == lower) {
+            // Month names start at 1, day names at 0
+            Some(idx) => idx as u32 + if names.len() == 12 { 1 } else { 0 },
+            None => s.parse().map_err(|_| invalid())?,
+        };
+        if v < min || v > max {
+            return Err(format!(
+                "Cron value {} out of range {}-{} in '{}'",
+                v, min, max, field
+            ));
+        }
+        Ok(v)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| invalid())?;
+                if step == 0 {
+                    return Err(invalid());
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (value(a)?, value(b)?)
+        } else {
+            let v = value(range)?;
+            // `5/10` means "from 5, every 10"
+            (v, if step.is_some() { max } else { v })
+        };
+
+        if lo > hi {
+            return Err(invalid());
+        }
+
+        let step = step.unwrap_or(1) as usize;
+        for v in (lo..=hi).step_by(step) {
+            bits |= 1 << v;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// What the daemon should do for a scheduled service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledAction {
+    /// Start the service now
+    Start,
+    /// Stop the still-running previous run and start again (overlap: kill)
+    Restart,
+    /// A run was due but the previous one is still going (overlap: skip)
+    Skip,
+    /// A run was due and will start once the previous one finishes (overlap: queue)
+    Queue,
+}
+
+/// Tracks when scheduled services are next due.
+///
+/// Due times are kept in memory and computed when a service is first seen,
+/// so runs missed while the daemon was not running are not caught up.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    due: HashMap<Uuid, (String, DateTime<Utc>)>,
+    queued: HashSet<Uuid>,
+}
+
+impl Scheduler {
+    /// Create an empty scheduler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decide what to do for `service` at `now`.
+    ///
+    /// Returns `None` when nothing is due, and an error if the service's
+    /// schedule is invalid. Services without a schedule are ignored.
+    pub fn poll(
+        &mut self,
+        service: &Service,
+        running: bool,
+        last_started: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<ScheduledAction>, String> {
+        let Some(config) = &service.schedule else {
+            self.forget(&service.id);
+            return Ok(None);
+        };
+        let schedule: Schedule = config.spec.parse()?;
+
+        // (Re)compute the due time when first seen or when the spec changed
+        let due = match self.due.get(&service.id) {
+            Some((spec, due)) if *spec == config.spec => *due,
+            _ => {
+                let due = schedule
+                    .next_run(last_started, now)
+                    .ok_or_else(|| format!("Schedule '{}' never fires", config.spec))?;
+                self.due.insert(service.id, (config.spec.clone(), due));
+                due
+            }
+        };
+
+        if self.queued.contains(&service.id) && !running {
+            self.queued.remove(&service.id);
+            return Ok(Some(ScheduledAction::Start));
+        }
+
+        if now < due {
+            return Ok(None);
+        }
+
+        if let Some(next) = schedule.next_after(now) {
+            self.due.insert(service.id, (config.spec.clone(), next));
+        } else {
+            self.due.remove(&service.id);
+        }
+
+        if !running {
+            return Ok(Some(ScheduledAction::Start));
+        }
+
+        Ok(Some(match config.overlap {
+            OverlapPolicy::Skip => ScheduledAction::Skip,
+            OverlapPolicy::Kill => ScheduledAction::Restart,
+            OverlapPolicy::Queue => {
+                self.queued.insert(service.id);
+                ScheduledAction::Queue
+            }
+        }))
+    }
+
+    /// Get the in-memory next due time of a service, if it is scheduled
+    pub fn next_due(&self, service_id: &Uuid) -> Option<DateTime<Utc>> {
+        self.due.get(service_id).map(|(_, due)| *due)
+    }
+
+    /// Drop all scheduling state for a service
+    pub fn forget(&mut self, service_id: &Uuid) {
+        self.due.remove(service_id);
+        self.queued.remove(service_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ScheduleConfig;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn next(expr: &str, after: DateTime<Utc>) -> DateTime<Utc> {
+        CronExpr::parse(expr).unwrap().next_after_in(after).unwrap()
+    }
+
+    #[test]
+    fn test_cron_every_fifteen_minutes() {
+        assert_eq!(
+            next("*/15 * * * *", utc(2026, 3, 10, 9, 7)),
+            utc(2026, 3, 10, 9, 15)
+        );
+        assert_eq!(
+            next("*/15 * * * *", utc(2026, 3, 10, 9, 45)),
+            utc(2026, 3, 10, 10, 0)
+        );
+    }
+
+    #[test]
+    fn test_cron_weekdays_with_names() {
+        // 2026-03-14 is a Saturday
+        assert_eq!(
+            next("30 8 * * mon-fri", utc(2026, 3, 13, 9, 0)),
+            utc(2026, 3, 16, 8, 30)
+        );
+        assert_eq!(
+            next("0 0 1 jan *", utc(2026, 3, 1, 0, 0)),
+            utc(2027, 1, 1, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_cron_day_of_month_or_week() {
+        // Both restricted: fires on the 1st or on Sundays
+        // (2026-03-01 is a Sunday, 2026-03-08 the next one)
+        assert_eq!(
+            next("0 12 1 * 0", utc(2026, 3, 1, 13, 0)),
+            utc(2026, 3, 8, 12, 0)
+        );
+        assert_eq!(
+            next("0 12 1 * 7", utc(2026, 3, 9, 0, 0)),
+            utc(2026, 3, 15, 12, 0)
+        );
+    }
+
+    #[test]
+    fn test_cron_step_day_field_is_not_a_restriction() {
+        // A day field starting with `*` keeps both fields required: odd days
+        // that are Sundays, not odd days or Sundays
+        assert_eq!(
+            next("0 12 */2 * 0", utc(2026, 3, 1, 13, 0)),
+            utc(2026, 3, 15, 12, 0)
+        );
+        // The first 1st on a Sunday, Tuesday, Thursday or Saturday
+        assert_eq!(
+            next("0 12 1 * */2", utc(2026, 3, 1, 13, 0)),
+            utc(2026, 8, 1, 12, 0)
+        );
+    }
+
+    #[test]
+    fn test_cron_impossible_date_never_fires() {
+        let expr = CronExpr::parse("0 0 30 2 *").unwrap();
+        assert!(expr.next_after_in(utc(2026, 1, 1, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("5-1 * * * *").is_err());
+        assert!(CronExpr::parse("* * * foo *").is_err());
+    }
+
+    #[test]
+    fn test_schedule_parse() {
+        assert_eq!(
+            "every 10m".parse::<Schedule>(),
+            Ok(Schedule::Every(Duration::from_secs(600)))
+        );
+        assert_eq!(
+            "@every 1h".parse::<Schedule>(),
+            Ok(Schedule::Every(Duration::from_secs(3600)))
+        );
+        assert!(matches!(
+            "@hourly".parse::<Schedule>(),
+            Ok(Schedule::Cron(_))
+        ));
+        assert!("every 0s".parse::<Schedule>().is_err());
+        assert!("whenever".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn test_interval_next_run() {
+        let schedule = Schedule::Every(Duration::from_secs(600));
+        let now = utc(2026, 3, 10, 9, 0);
+
+        assert_eq!(schedule.next_run(None, now), Some(now));
+        assert_eq!(
+            schedule.next_run(Some(utc(2026, 3, 10, 8, 55)), now),
+            Some(utc(2026, 3, 10, 9, 5))
+        );
+    }
+
+    fn scheduled_service(overlap: OverlapPolicy) -> Service {
+        let mut service = Service::new("sync".to_string(), "./sync.sh".to_string());
+        service.schedule = Some(ScheduleConfig {
+            spec: "every 10m".to_string(),
+            overlap,
+        });
+        service
+    }
+
+    #[test]
+    fn test_scheduler_starts_when_due() {
+        let service = scheduled_service(OverlapPolicy::Skip);
+        let mut scheduler = Scheduler::new();
+        let now = utc(2026, 3, 10, 9, 0);
+
+        // Never run before: due immediately
+        assert_eq!(
+            scheduler.poll(&service, false, None, now),
+            Ok(Some(ScheduledAction::Start))
+        );
+        assert_eq!(
+            scheduler.next_due(&service.id),
+            Some(utc(2026, 3, 10, 9, 10))
+        );
+
+        let later = utc(2026, 3, 10, 9, 5);
+        assert_eq!(scheduler.poll(&service, false, Some(now), later), Ok(None));
+    }
+
+    #[test]
+    fn test_scheduler_overlap_policies() {
+        let now = utc(2026, 3, 10, 9, 0);
+
+        let mut scheduler = Scheduler::new();
+        let skip = scheduled_service(OverlapPolicy::Skip);
+        assert_eq!(
+            scheduler.poll(&skip, true, None, now),
+            Ok(Some(ScheduledAction::Skip))
+        );
+
+        let kill = scheduled_service(OverlapPolicy::Kill);
+        assert_eq!(
+            scheduler.poll(&kill, true, None, now),
+            Ok(Some(ScheduledAction::Restart))
+        );
+
+        let queue = scheduled_service(OverlapPolicy::Queue);
+        assert_eq!(
+            scheduler.poll(&queue, true, None, now),
+            Ok(Some(ScheduledAction::Queue))
+        );
+        // Still running: keep waiting
+        assert_eq!(scheduler.poll(&queue, true, None, now), Ok(None));
+        // Previous run finished: start the queued run
+        assert_eq!(
+            scheduler.poll(&queue, false, None, now),
+            Ok(Some(ScheduledAction::Start))
+        );
+    }
+
+    #[test]
+    fn test_scheduler_invalid_schedule() {
+        let mut service = scheduled_service(OverlapPolicy::Skip);
+        service.schedule.as_mut().unwrap().spec = "whenever".to_string();
+
+        let mut scheduler = Scheduler::new();
+        assert!(scheduler.poll(&service, false, None, Utc::now()).is_err());
+    }
+}
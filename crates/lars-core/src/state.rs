@@ -0,0 +1,206 @@
+//! Runtime state for LARS
+//!
+//! Records facts about services that are not part of the user's configuration,
+//! such as the definition a service was launched with. The state file lives in
+//! the state directory (see [`crate::ConfigManager::state_dir`]) and is safe to
+//! delete; it is rebuilt as services are started.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::health::HealthStatus;
+use crate::models::Service;
+
+/// State file name
+pub const STATE_FILE_NAME: &str = "state.json";
+
+/// Lock file held while the state file is read, changed and written
+pub const STATE_LOCK_FILE_NAME: &str = "state.lock";
+
+/// Runtime state for all services, keyed by service ID
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeState {
+    #[serde(default)]
+    pub services: HashMap<Uuid, ServiceState>,
+}
+
+impl RuntimeState {
+    /// Get the state for a service, if any has been recorded
+    pub fn service(&self, id: &Uuid) -> Option<&ServiceState> {
+        self.services.get(id)
+    }
+
+    /// Get the state for a service, creating an empty entry if needed
+    pub fn service_mut(&mut self, id: Uuid) -> &mut ServiceState {
+        self.services.entry(id).or_default()
+    }
+}
+
+/// Runtime state for a single service
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ServiceState {
+    /// How the service was launched, if it was started by lars
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch: Option<LaunchRecord>,
+    /// When the service was last started by lars, kept after it stops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_started_at: Option<DateTime<Utc>>,
+    /// When the last exit recorded in the event history happened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_recorded_at: Option<DateTime<Utc>>,
+    /// Ports allocated to the service's named slots, reused while still free
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ports: BTreeMap<String, u16>,
+    /// Health last recorded in the event history, while the service runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthStatus>,
+}
+
+/// Record of a service launch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LaunchRecord {
+    /// When the service was started
+    pub started_at: DateTime<Utc>,
+    /// The definition the service was started with
+    pub fingerprint: LaunchFingerprint,
+}
+
+impl LaunchRecord {
+    /// Create a launch record for a service started now
+    pub fn new(service: &Service) -> Self {
+        Self {
+            started_at: Utc::now(),
+            fingerprint: LaunchFingerprint::of(service),
+        }
+    }
+}
+
+/// How a service's process last exited on its own
+///
+/// Written by the runner's wrapper shell when the command finishes; services
+/// that are stopped by lars do not get an exit record.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ExitRecord {
+    pub code: i32,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl ExitRecord {
+    /// Read an exit record from an exit-status file
+    pub fn read(path: &Path) -> Option<Self> {
+        let code = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+        let finished_at = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        Some(Self { code, finished_at })
+    }
+}
+
+/// The parts of a service definition that affect the running process
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LaunchFingerprint {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Environment the service was launched with, ports interpolated
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl LaunchFingerprint {
+    /// Capture the fingerprint of a service definition
+    pub fn of(service: &Service) -> Self {
+        Self {
+            command: service.command.clone(),
+            cwd: service.cwd.clone(),
+            env: service
+                .env
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+
+    /// List the fields that differ between this fingerprint and the
+    /// current definition of `service`.
+    ///
+    /// Returns field names (`command`, `cwd`, `env`) in a stable order;
+    /// an empty list means the service is running its current definition.
+    pub fn changed_fields(&self, service: &Service) -> Vec<&'static str> {
+        let current = Self::of(service);
+        let mut changed = Vec::new();
+
+        if self.command != current.command {
+            changed.push("command");
+        }
+        if self.cwd != current.cwd {
+            changed.push("cwd");
+        }
+        if self.env != current.env {
+            changed.push("env");
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_unchanged() {
+        let mut service = Service::new("test".to_string(), "echo hello".to_string());
+        service.env.insert("PORT".to_string(), "3000".to_string());
+
+        let fingerprint = LaunchFingerprint::of(&service);
+        assert!(fingerprint.changed_fields(&service).is_empty());
+    }
+
+    #[test]
+    fn test_fingerprint_changed_fields() {
+        let mut service = Service::new("test".to_string(), "echo hello".to_string());
+        let fingerprint = LaunchFingerprint::of(&service);
+
+        service.command = "echo world".to_string();
+        assert_eq!(fingerprint.changed_fields(&service), vec!["command"]);
+
+        service.cwd = Some(PathBuf::from("/tmp"));
+        service.env.insert("PORT".to_string(), "3000".to_string());
+        assert_eq!(
+            fingerprint.changed_fields(&service),
+            vec!["command", "cwd", "env"]
+        );
+    }
+
+    #[test]
+    fn test_exit_record_read() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("svc.exit");
+
+        assert!(ExitRecord::read(&path).is_none());
+
+        std::fs::write(&path, "3\n").unwrap();
+        assert_eq!(ExitRecord::read(&path).unwrap().code, 3);
+
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(ExitRecord::read(&path).is_none());
+    }
+
+    #[test]
+    fn test_runtime_state_serde_round_trip() {
+        let service = Service::new("test".to_string(), "echo hello".to_string());
+        let mut state = RuntimeState::default();
+        state.service_mut(service.id).launch = Some(LaunchRecord::new(&service));
+
+        let json = serde_json::to_string(&state).unwrap();
+        let loaded: RuntimeState = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(state, loaded);
+    }
+}
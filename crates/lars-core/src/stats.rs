@@ -0,0 +1,302 @@
+//! Resource usage of running services
+//!
+//! A service is rarely a single process: the tmux pane runs a shell, which
+//! runs the log relay, which runs the command, which may fork workers. Usage
+//! is therefore summed over the whole process tree below the service's root
+//! PID. Figures are read from `/proc`, so they are only available on Linux.
+//!
+//! CPU usage is the CPU time spent between two snapshots, so it needs a
+//! previous [`ProcessTable`] to compare against:
+//!
+//! ```no_run
+//! use lars_core::stats::ProcessTable;
+//!
+//! let before = ProcessTable::read();
+//! std::thread::sleep(std::time::Duration::from_millis(500));
+//! let now = ProcessTable::read();
+//!
+//! if let Some(stats) = now.and_then(|t| t.stats(1234, before.as_ref())) {
+//!     println!("{:.1}% CPU, {} bytes", stats.cpu_percent.unwrap_or(0.0), stats.rss_bytes);
+//! }
+//! ```
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Clock ticks per second used by `/proc` (`USER_HZ`, 100 on every Linux
+/// architecture lars runs on)
+#[cfg(target_os = "linux")]
+const TICKS_PER_SEC: f64 = 100.0;
+
+/// Resource usage of a service's process tree
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessStats {
+    /// Root process of the tree
+    pub pid: u32,
+    /// Number of processes in the tree
+    pub processes: usize,
+    /// CPU usage since the previous snapshot, where 100 is one full core
+    pub cpu_percent: Option<f64>,
+    /// Resident memory in bytes
+    pub rss_bytes: u64,
+    /// Open file descriptors
+    pub open_fds: u64,
+    /// Threads across all processes
+    pub threads: u64,
+    /// Seconds since the root process started
+    pub uptime_secs: u64,
+}
+
+/// What is known about one process from its `stat` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProcInfo {
+    ppid: u32,
+    /// User plus system CPU time, in clock ticks
+    cpu_ticks: u64,
+    threads: u64,
+    /// Start time in clock ticks after boot
+    start_ticks: u64,
+}
+
+/// A snapshot of the processes on the system
+#[derive(Debug, Clone)]
+pub struct ProcessTable {
+    procs: HashMap<u32, ProcInfo>,
+    taken: Instant,
+    /// Seconds since boot when the snapshot was taken
+    uptime: f64,
+}
+
+impl ProcessTable {
+    /// Take a snapshot of all processes.
+    ///
+    /// Returns `None` where `/proc` is not available.
+    #[cfg(target_os = "linux")]
+    pub fn read() -> Option<Self> {
+        let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+        let uptime = uptime.split_whitespace().next()?.parse().ok()?;
+
+        let mut procs = HashMap::new();
+        for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+            // Processes may exit while the table is being read
+            if let Some(info) = std::fs::read_to_string(entry.path().join("stat"))
+                .ok()
+                .and_then(|stat| parse_stat(&stat))
+            {
+                procs.insert(pid, info);
+            }
+        }
+
+        Some(Self {
+            procs,
+            taken: Instant::now(),
+            uptime,
+        })
+    }
+
+    /// Take a snapshot of all processes.
+    ///
+    /// Returns `None` where `/proc` is not available.
+    #[cfg(not(target_os = "linux"))]
+    pub fn read() -> Option<Self> {
+        None
+    }
+
+    /// Sum the usage of `root` and all of its descendants.
+    ///
+    /// CPU usage is only known when given an earlier snapshot. Returns `None`
+    /// if `root` is not running.
+    #[cfg(target_os = "linux")]
+    pub fn stats(&self, root: u32, previous: Option<&ProcessTable>) -> Option<ProcessStats> {
+        let root_info = self.procs.get(&root)?;
+        let tree = self.tree(root);
+
+        let mut stats = ProcessStats {
+            pid: root,
+            processes: tree.len(),
+            cpu_percent: None,
+            rss_bytes: 0,
+            open_fds: 0,
+            threads: 0,
+            uptime_secs: (self.uptime - root_info.start_ticks as f64 / TICKS_PER_SEC).max(0.0)
+                as u64,
+        };
+
+        let mut cpu_ticks = 0;
+        for pid in &tree {
+            let info = &self.procs[pid];
+            stats.threads += info.threads;
+            stats.rss_bytes += rss_bytes(*pid).unwrap_or(0);
+            stats.open_fds += open_fds(*pid).unwrap_or(0);
+
+            // Processes started since the previous snapshot used all of
+            // their CPU time in between
+            let before = previous
+                .and_then(|p| p.procs.get(pid))
+                .filter(|p| p.start_ticks == info.start_ticks)
+                .map_or(0, |p| p.cpu_ticks);
+            cpu_ticks += info.cpu_ticks.saturating_sub(before);
+        }
+
+        if let Some(previous) = previous {
+            let elapsed = self.taken.duration_since(previous.taken).as_secs_f64();
+            if elapsed > 0.0 {
+                stats.cpu_percent = Some(cpu_ticks as f64 / TICKS_PER_SEC / elapsed * 100.0);
+            }
+        }
+
+        Some(stats)
+    }
+
+    /// Sum the usage of `root` and all of its descendants.
+    ///
+    /// CPU usage is only known when given an earlier snapshot. Returns `None`
+    /// if `root` is not running.
+    #[cfg(not(target_os = "linux"))]
+    pub fn stats(&self, _root: u32, _previous: Option<&ProcessTable>) -> Option<ProcessStats> {
+        None
+    }
+
+    /// PIDs of `root` and all of its descendants
+    pub fn tree(&self, root: u32) -> Vec<u32> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (pid, info) in &self.procs {
+            children.entry(info.ppid).or_default().push(*pid);
+        }
+
+        let mut tree = vec![root];
+        let mut idx = 0;
+        while idx < tree.len() {
+            if let Some(kids) = children.get(&tree[idx]) {
+                tree.extend(kids);
+            }
+            idx += 1;
+        }
+        tree
+    }
+}
+
+/// Parse the fields lars needs from `/proc/<pid>/stat`.
+///
+/// The command name in parentheses may itself contain spaces and
+/// parentheses, so fields are counted from the last `)`.
+fn parse_stat(stat: &str) -> Option<ProcInfo> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // Fields after the name, starting with the state (field 3 in proc(5))
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+
+    Some(ProcInfo {
+        ppid: field(4)? as u32,
+        cpu_ticks: field(14)? + field(15)?,
+        threads: field(20)?,
+        start_ticks: field(22)?,
+    })
+}
+
+/// Resident memory of a process, from `VmRSS` in `/proc/<pid>/status`
+#[cfg(target_os = "linux")]
+fn rss_bytes(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Number of open file descriptors of a process
+#[cfg(target_os = "linux")]
+fn open_fds(pid: u32) -> Option<u64> {
+    let fds = std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    Some(fds.count() as u64)
+}
+
+/// Format a byte count compactly, e.g. `12.5M`
+///
+/// # Examples
+///
+/// ```
+/// use lars_core::stats::format_bytes;
+///
+/// assert_eq!(format_bytes(512), "512B");
+/// assert_eq!(format_bytes(1536), "1.5K");
+/// assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0G");
+/// ```
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "4242 (my (odd) name) S 17 4242 4242 0 -1 4194560 1234 0 0 0 \
+                    250 50 0 0 20 0 3 0 98765 10485760 2560 18446744073709551615";
+        let info = parse_stat(stat).unwrap();
+
+        assert_eq!(info.ppid, 17);
+        assert_eq!(info.cpu_ticks, 300);
+        assert_eq!(info.threads, 3);
+        assert_eq!(info.start_ticks, 98765);
+
+        assert!(parse_stat("garbage").is_none());
+    }
+
+    #[test]
+    fn test_tree_includes_descendants_only() {
+        let proc_info = |ppid| ProcInfo {
+            ppid,
+            cpu_ticks: 0,
+            threads: 1,
+            start_ticks: 0,
+        };
+        let table = ProcessTable {
+            procs: HashMap::from([
+                (1, proc_info(0)),
+                (10, proc_info(1)),
+                (11, proc_info(10)),
+                (12, proc_info(11)),
+                (20, proc_info(1)),
+            ]),
+            taken: Instant::now(),
+            uptime: 0.0,
+        };
+
+        let mut tree = table.tree(10);
+        tree.sort();
+        assert_eq!(tree, vec![10, 11, 12]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stats_of_current_process() {
+        let before = ProcessTable::read().unwrap();
+        let now = ProcessTable::read().unwrap();
+        let stats = now.stats(std::process::id(), Some(&before)).unwrap();
+
+        assert!(stats.processes >= 1);
+        assert!(stats.threads >= 1);
+        assert!(stats.rss_bytes > 0);
+        assert!(stats.open_fds > 0);
+        assert!(stats.cpu_percent.is_some());
+
+        assert!(now.stats(u32::MAX, None).is_none());
+    }
+}
@@ -0,0 +1,361 @@
+//! Status of services and changes to it
+//!
+//! A service is running, unhealthy (running, but its ports do not accept
+//! connections; see [`crate::health`]), exited (its process ended on its
+//! own) or stopped. [`StatusTracker`] polls services and reports only the
+//! ones whose status changed or that were started again, for commands that
+//! follow the stack over time.
+//! Polling only reads; the daemon's tracker also records what it notices
+//! (see [`StatusTracker::recording`]).
+
+use crate::health::{self, Health, HealthStatus};
+use crate::manager::ServiceManager;
+use crate::models::Service;
+use crate::state::RuntimeState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// How long a service may take to start before it is taken to have exited
+const STARTUP_GRACE: chrono::Duration = chrono::Duration::seconds(2);
+
+/// What a service is doing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceStatus {
+    Running,
+    Unhealthy,
+    Exited,
+    Stopped,
+}
+
+impl ServiceStatus {
+    /// Status of a service from whether it is running, its health and how it
+    /// last exited on its own
+    pub fn of(running: bool, health: Option<&Health>, exit_code: Option<i32>) -> Self {
+        match (running, health, exit_code) {
+            (true, Some(health), _) if health.status == HealthStatus::Unhealthy => {
+                ServiceStatus::Unhealthy
+            }
+            (true, _, _) => ServiceStatus::Running,
+            (false, _, Some(_)) => ServiceStatus::Exited,
+            (false, _, None) => ServiceStatus::Stopped,
+        }
+    }
+
+    /// Whether the service's process is running, healthy or not
+    pub fn is_running(self) -> bool {
+        matches!(self, ServiceStatus::Running | ServiceStatus::Unhealthy)
+    }
+}
+
+impl std::fmt::Display for ServiceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceStatus::Running => write!(f, "running"),
+            ServiceStatus::Unhealthy => write!(f, "unhealthy"),
+            ServiceStatus::Exited => write!(f, "exited"),
+            ServiceStatus::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// Current status of one service
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StatusReport {
+    pub name: String,
+    pub status: ServiceStatus,
+    /// Exit status, if the service exited on its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Result of checking the ports of a running service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<Health>,
+    /// When lars last started the service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+impl StatusReport {
+    /// Check whether a service is running and, if so, whether it is healthy
+    pub fn check(manager: &ServiceManager, service: &Service, state: &RuntimeState) -> Self {
+        let running = manager.is_running(service).unwrap_or(false);
+        let started_at = state.service(&service.id).and_then(|s| s.last_started_at);
+        let (health, exit_code) = if running {
+            (health::check(service, state), None)
+        } else {
+            // An exit from before the latest start belongs to an earlier run
+            let exit = manager
+                .config()
+                .last_exit(&service.id)
+                .filter(|e| started_at.is_none_or(|started| e.finished_at >= started));
+            (None, exit.map(|e| e.code))
+        };
+
+        Self {
+            name: service.name.clone(),
+            status: ServiceStatus::of(running, health.as_ref(), exit_code),
+            exit_code,
+            health,
+            started_at,
+        }
+    }
+}
+
+/// A service whose status changed
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StatusChange {
+    /// When the change was noticed
+    pub time: DateTime<Utc>,
+    pub name: String,
+    pub status: ServiceStatus,
+    /// Status before the change, `None` the first time a service is seen
+    pub previous: Option<ServiceStatus>,
+    /// Exit status of a service that exited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Why an unhealthy service is unhealthy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the service was started, if it was started (again) since the
+    /// last poll
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Remembers the last status of each service to report changes
+#[derive(Debug, Default)]
+pub struct StatusTracker {
+    /// Status of each service and when it was last started
+    last: HashMap<Uuid, (ServiceStatus, Option<DateTime<Utc>>)>,
+    record: bool,
+}
+
+impl StatusTracker {
+    /// Create a tracker that has not seen any service yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record exits and health changes in the event history while polling
+    /// (see [`crate::ConfigManager::record_exit`] and
+    /// [`crate::ConfigManager::record_health`]).
+    ///
+    /// Off by default, so that watching never writes the state or the
+    /// history; the daemon turns it on.
+    pub fn recording(mut self, record: bool) -> Self {
+        self.record = record;
+        self
+    }
+
+    /// Check `services` and return the ones whose status changed or that
+    /// were started since the last poll. Every service is reported the first
+    /// time it is polled.
+    pub fn poll(&mut self, manager: &ServiceManager, services: &[Service]) -> Vec<StatusChange> {
+        let config = manager.config();
+        let state = config.load_state();
+        self.last
+            .retain(|id, _| services.iter().any(|service| service.id == *id));
+
+        let mut changes = Vec::new();
+        for service in services {
+            let report = StatusReport::check(manager, service, &state);
+            if self.record {
+                let _ = config.record_exit(service);
+                let _ = config.record_health(service, report.health.as_ref());
+            }
+            changes.extend(self.observe(service.id, &report));
+        }
+        changes
+    }
+
+    /// Note the current status of a service, returning the change if it
+    /// differs from the status noted last or the service was started since
+    pub fn observe(&mut self, service_id: Uuid, report: &StatusReport) -> Option<StatusChange> {
+        let previous = self.last.get(&service_id).copied();
+        let started = previous.is_some_and(|(_, started_at)| started_at != report.started_at);
+
+        // The start is recorded just before the process starts, so a new
+        // run is not taken to have ended until it had time to start
+        let starting = started
+            && !report.status.is_running()
+            && report.exit_code.is_none()
+            && report
+                .started_at
+                .is_some_and(|started_at| Utc::now() - started_at < STARTUP_GRACE);
+        if starting {
+            return None;
+        }
+
+        self.last
+            .insert(service_id, (report.status, report.started_at));
+        let previous = previous.map(|(status, _)| status);
+        if previous == Some(report.status) && !started {
+            return None;
+        }
+
+        Some(StatusChange {
+            time: Utc::now(),
+            name: report.name.clone(),
+            status: report.status,
+            previous,
+            exit_code: report.exit_code,
+            error: report.health.as_ref().and_then(|h| h.error.clone()),
+            started_at: report.started_at.filter(|_| started),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+    use crate::events::{EventFilter, EventKind};
+    use tempfile::TempDir;
+
+    fn report(status: ServiceStatus, exit_code: Option<i32>) -> StatusReport {
+        StatusReport {
+            name: "api".to_string(),
+            status,
+            exit_code,
+            health: None,
+            started_at: None,
+        }
+    }
+
+    #[test]
+    fn test_status_of() {
+        let healthy = Health {
+            status: HealthStatus::Healthy,
+            latency_ms: Some(0.3),
+            error: None,
+        };
+        let unhealthy = Health {
+            status: HealthStatus::Unhealthy,
+            latency_ms: None,
+            error: None,
+        };
+
+        assert_eq!(ServiceStatus::of(true, None, None), ServiceStatus::Running);
+        assert_eq!(
+            ServiceStatus::of(true, Some(&healthy), None),
+            ServiceStatus::Running
+        );
+        assert_eq!(
+            ServiceStatus::of(true, Some(&unhealthy), None),
+            ServiceStatus::Unhealthy
+        );
+        assert_eq!(
+            ServiceStatus::of(false, None, Some(1)),
+            ServiceStatus::Exited
+        );
+        assert_eq!(ServiceStatus::of(false, None, None), ServiceStatus::Stopped);
+    }
+
+    #[test]
+    fn test_tracker_reports_changes_only() {
+        let mut tracker = StatusTracker::new();
+        let id = Uuid::new_v4();
+
+        let first = tracker
+            .observe(id, &report(ServiceStatus::Running, None))
+            .unwrap();
+        assert_eq!(first.status, ServiceStatus::Running);
+        assert_eq!(first.previous, None);
+
+        assert!(tracker
+            .observe(id, &report(ServiceStatus::Running, None))
+            .is_none());
+
+        let exited = tracker
+            .observe(id, &report(ServiceStatus::Exited, Some(1)))
+            .unwrap();
+        assert_eq!(exited.previous, Some(ServiceStatus::Running));
+        assert_eq!(exited.exit_code, Some(1));
+    }
+
+    #[test]
+    fn test_tracker_reports_restarts() {
+        let mut tracker = StatusTracker::new();
+        let id = Uuid::new_v4();
+        let at = |status, started_at| StatusReport {
+            started_at: Some(started_at),
+            ..report(status, None)
+        };
+        let first = Utc::now() - chrono::Duration::minutes(5);
+        let second = Utc::now();
+
+        assert!(tracker
+            .observe(id, &at(ServiceStatus::Running, first))
+            .is_some());
+
+        // Started again between polls
+        let restarted = tracker
+            .observe(id, &at(ServiceStatus::Running, second))
+            .unwrap();
+        assert_eq!(restarted.previous, Some(ServiceStatus::Running));
+        assert_eq!(restarted.started_at, Some(second));
+        assert!(tracker
+            .observe(id, &at(ServiceStatus::Running, second))
+            .is_none());
+
+        // A run that was just started is given time to start
+        let third = Utc::now();
+        assert!(tracker
+            .observe(id, &at(ServiceStatus::Stopped, third))
+            .is_none());
+        let started = tracker
+            .observe(id, &at(ServiceStatus::Running, third))
+            .unwrap();
+        assert_eq!(started.started_at, Some(third));
+
+        let stopped = tracker
+            .observe(id, &at(ServiceStatus::Stopped, third))
+            .unwrap();
+        assert_eq!(stopped.previous, Some(ServiceStatus::Running));
+        assert_eq!(stopped.started_at, None);
+    }
+
+    #[test]
+    fn test_poll_only_reads() {
+        let temp = TempDir::new().unwrap();
+        let config = ConfigManager::new(temp.path().join("config"), temp.path().join("logs"));
+        let service = Service::new("job".to_string(), "exit 2".to_string());
+
+        std::fs::create_dir_all(config.log_dir()).unwrap();
+        std::fs::write(config.exit_path_for_service(&service.id), "2\n").unwrap();
+
+        let manager = ServiceManager::new(config.clone());
+        let mut tracker = StatusTracker::new();
+        let changes = tracker.poll(&manager, std::slice::from_ref(&service));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].status, ServiceStatus::Exited);
+        assert!(!config.state_path().exists());
+        assert!(!config.events_path().exists());
+    }
+
+    #[test]
+    fn test_poll_records_exits() {
+        let temp = TempDir::new().unwrap();
+        let config = ConfigManager::new(temp.path().join("config"), temp.path().join("logs"));
+        let service = Service::new("job".to_string(), "exit 2".to_string());
+
+        std::fs::create_dir_all(config.log_dir()).unwrap();
+        std::fs::write(config.exit_path_for_service(&service.id), "2\n").unwrap();
+
+        let manager = ServiceManager::new(config.clone());
+        let mut tracker = StatusTracker::new().recording(true);
+        let changes = tracker.poll(&manager, std::slice::from_ref(&service));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].status, ServiceStatus::Exited);
+        assert_eq!(changes[0].exit_code, Some(2));
+        assert!(tracker
+            .poll(&manager, std::slice::from_ref(&service))
+            .is_empty());
+
+        let events = config.events(&EventFilter::default(), 10).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Exited);
+    }
+}
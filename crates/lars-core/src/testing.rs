@@ -0,0 +1,386 @@
+//! Test doubles for code built on lars-core
+//!
+//! [`FakeRunner`] keeps services "running" in memory instead of starting
+//! processes, so start, stop and restart flows, including failures and stop
+//! timeouts, can be tested deterministically without tmux. Pass it to
+//! [`crate::ServiceManager::with_runner`] to exercise the full lifecycle.
+//! [`FakeRunner::persistent`] keeps its services in a file, so that separate
+//! processes share them.
+//!
+//! [`ScriptedExecutor`] answers the commands of a real
+//! [`crate::TmuxRunner`] from a table of sessions.
+//!
+//! Enabled by the `testing` feature.
+//!
+//! ```
+//! use lars_core::testing::{FakeCall, FakeRunner};
+//! use lars_core::{Runner, Service};
+//!
+//! let runner = FakeRunner::new();
+//! let service = Service::new("api".to_string(), "serve".to_string());
+//! let log = std::env::temp_dir().join("api.log");
+//!
+//! runner.start(&service, &log)?;
+//! assert!(runner.is_running(&service)?);
+//! runner.stop(&service)?;
+//! assert_eq!(
+//!     runner.calls(),
+//!     vec![FakeCall::Start("api".to_string()), FakeCall::Stop("api".to_string())]
+//! );
+//! # Ok::<(), lars_core::LarsError>(())
+//! ```
+
+use crate::error::{ConfigError, LarsError, Result};
+use crate::models::{RunnerType, Service};
+use crate::runner::{exit_path_for_log, CommandExecutor, CommandOutput, Runner};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// A call made to a [`FakeRunner`], with the service's name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FakeCall {
+    Start(String),
+    Stop(String),
+}
+
+/// In-memory [`Runner`] whose behaviour is scripted per service name
+#[derive(Debug, Default)]
+pub struct FakeRunner {
+    state: Mutex<FakeState>,
+    /// File the running services are kept in, if shared between processes
+    store: Option<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    /// Log path of each running service
+    running: HashMap<Uuid, PathBuf>,
+    calls: Vec<FakeCall>,
+    failing_starts: HashMap<String, String>,
+    ignored_stops: HashSet<String>,
+    exits_on_start: HashMap<String, i32>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A FakeRunner that keeps its running services in the file at `path`,
+    /// so that runners in separate processes see the same services
+    pub fn persistent(path: impl Into<PathBuf>) -> Self {
+        Self {
+            store: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Make starting the named service fail with `error`
+    pub fn fail_start(&self, name: &str, error: &str) {
+        let mut state = self.state();
+        state
+            .failing_starts
+            .insert(name.to_string(), error.to_string());
+    }
+
+    /// Make the named service ignore stops, so waiting for it to stop times
+    /// out
+    pub fn ignore_stop(&self, name: &str) {
+        self.state().ignored_stops.insert(name.to_string());
+    }
+
+    /// Make the named service exit with `code` as soon as it is started, as
+    /// a task that completes (or a service that crashes) right away
+    pub fn exit_on_start(&self, name: &str, code: i32) {
+        self.state().exits_on_start.insert(name.to_string(), code);
+    }
+
+    /// Make a running service exit on its own with `code`
+    pub fn exit(&self, service: &Service, code: i32) -> Result<()> {
+        let mut state = self.state();
+        let log_path = state.running.remove(&service.id);
+        self.save(&state)?;
+        match log_path {
+            Some(log_path) => write_exit(&log_path, code),
+            None => Err(LarsError::ProcessFailed(format!(
+                "{} is not running",
+                service.name
+            ))),
+        }
+    }
+
+    /// Calls made so far, oldest first
+    pub fn calls(&self) -> Vec<FakeCall> {
+        self.state().calls.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeState> {
+        // A test that panicked while holding the lock has failed already
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(store) = &self.store {
+            state.running = std::fs::read(store)
+                .ok()
+                .and_then(|data| serde_json::from_slice(&data).ok())
+                .unwrap_or_default();
+        }
+        state
+    }
+
+    /// Write the running services to the store, if there is one
+    fn save(&self, state: &FakeState) -> Result<()> {
+        if let Some(store) = &self.store {
+            let data = serde_json::to_vec(&state.running).map_err(ConfigError::from)?;
+            std::fs::write(store, data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write an exit status where runners write it when a command finishes
+fn write_exit(log_path: &Path, code: i32) -> Result<()> {
+    std::fs::write(exit_path_for_log(log_path), format!("{}\n", code))?;
+    Ok(())
+}
+
+impl Runner for FakeRunner {
+    fn start(&self, service: &Service, log_path: &Path) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(FakeCall::Start(service.name.clone()));
+
+        if let Some(error) = state.failing_starts.get(&service.name) {
+            return Err(LarsError::ProcessFailed(error.clone()));
+        }
+        if state.running.contains_key(&service.id) {
+            return Ok(());
+        }
+
+        let _ = std::fs::remove_file(exit_path_for_log(log_path));
+        match state.exits_on_start.get(&service.name) {
+            Some(code) => write_exit(log_path, *code),
+            None => {
+                state.running.insert(service.id, log_path.to_path_buf());
+                self.save(&state)
+            }
+        }
+    }
+
+    fn stop(&self, service: &Service) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(FakeCall::Stop(service.name.clone()));
+
+        if !state.ignored_stops.contains(&service.name) {
+            state.running.remove(&service.id);
+        }
+        self.save(&state)
+    }
+
+    /// Fake services never stop later on their own, so a service that is
+    /// still running times out right away instead of after `timeout_secs`
+    fn wait_stopped(&self, service: &Service, _timeout_secs: u64) -> Result<()> {
+        if self.is_running(service)? {
+            return Err(LarsError::StopTimeout(service.name.clone()));
+        }
+        Ok(())
+    }
+
+    fn is_running(&self, service: &Service) -> Result<bool> {
+        Ok(self.state().running.contains_key(&service.id))
+    }
+
+    /// Fake services have no process
+    fn get_pid(&self, _service: &Service) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    fn attach_command(&self, _service: &Service) -> Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    fn runner_type(&self) -> RunnerType {
+        RunnerType::Direct
+    }
+}
+
+/// [`CommandExecutor`] that answers tmux commands from a table of sessions,
+/// recording them, so [`crate::TmuxRunner`] can be tested without a tmux
+/// server
+#[derive(Debug)]
+pub struct ScriptedExecutor {
+    state: Mutex<ScriptedState>,
+}
+
+#[derive(Debug)]
+struct ScriptedState {
+    sessions: HashSet<String>,
+    calls: Vec<Vec<String>>,
+    installed: bool,
+    refuse_kill: bool,
+    /// What `tmux -V` prints
+    version: String,
+}
+
+impl Default for ScriptedExecutor {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(ScriptedState {
+                sessions: HashSet::new(),
+                calls: Vec::new(),
+                installed: true,
+                refuse_kill: false,
+                version: "tmux 3.4".to_string(),
+            }),
+        }
+    }
+}
+
+impl ScriptedExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every command fail as if tmux were not installed
+    pub fn uninstall(&self) {
+        self.state().installed = false;
+    }
+
+    /// Make `tmux kill-session` fail and leave the session running
+    pub fn refuse_kill(&self) {
+        self.state().refuse_kill = true;
+    }
+
+    /// Set what `tmux -V` prints, e.g. `tmux 2.9a`
+    pub fn set_version(&self, version: &str) {
+        self.state().version = version.to_string();
+    }
+
+    /// Arguments of the commands run so far, oldest first
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.state().calls.clone()
+    }
+
+    /// How often a tmux subcommand was run
+    pub fn count(&self, subcommand: &str) -> usize {
+        let state = self.state();
+        state
+            .calls
+            .iter()
+            .filter(|args| args[0] == subcommand)
+            .count()
+    }
+
+    /// Whether the named session exists
+    pub fn has_session(&self, name: &str) -> bool {
+        self.state().sessions.contains(name)
+    }
+
+    fn state(&self) -> MutexGuard<'_, ScriptedState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CommandExecutor for ScriptedExecutor {
+    fn run(&self, program: &str, args: &[String]) -> Result<CommandOutput> {
+        assert_eq!(program, "tmux");
+        let mut state = self.state();
+        state.calls.push(args.to_vec());
+        if !state.installed {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+
+        let success = match args[0].as_str() {
+            "-V" => true,
+            "new-session" => state.sessions.insert(args[3].clone()),
+            "has-session" | "list-panes" => state.sessions.contains(&args[2]),
+            "kill-session" => !state.refuse_kill && state.sessions.remove(&args[2]),
+            other => panic!("unexpected tmux {}", other),
+        };
+
+        Ok(CommandOutput {
+            success,
+            status: format!("exit status: {}", if success { 0 } else { 1 }),
+            stdout: match args[0].as_str() {
+                "-V" => format!("{}\n", state.version),
+                "list-panes" if success => "4242\n".to_string(),
+                _ => String::new(),
+            },
+            stderr: if success {
+                String::new()
+            } else {
+                "no server running".to_string()
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fake_runner_scripting() {
+        let temp = TempDir::new().unwrap();
+        let log_path = temp.path().join("api.log");
+        let runner = FakeRunner::new();
+        let api = Service::new("api".to_string(), "serve".to_string());
+        let broken = Service::new("broken".to_string(), "serve".to_string());
+
+        runner.fail_start("broken", "no such command");
+        let err = runner.start(&broken, &log_path).unwrap_err();
+        assert!(matches!(err, LarsError::ProcessFailed(msg) if msg == "no such command"));
+
+        runner.ignore_stop("api");
+        runner.start(&api, &log_path).unwrap();
+        runner.stop(&api).unwrap();
+        assert!(runner.is_running(&api).unwrap());
+        let err = runner.wait_stopped(&api, 10).unwrap_err();
+        assert!(matches!(err, LarsError::StopTimeout(name) if name == "api"));
+
+        runner.exit(&api, 137).unwrap();
+        assert!(!runner.is_running(&api).unwrap());
+        let exit = std::fs::read_to_string(exit_path_for_log(&log_path)).unwrap();
+        assert_eq!(exit, "137\n");
+        assert!(runner.exit(&api, 0).is_err());
+
+        assert_eq!(
+            runner.calls(),
+            vec![
+                FakeCall::Start("broken".to_string()),
+                FakeCall::Start("api".to_string()),
+                FakeCall::Stop("api".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fake_runner_exit_on_start() {
+        let temp = TempDir::new().unwrap();
+        let log_path = temp.path().join("seed.log");
+        let runner = FakeRunner::new();
+        let seed = Service::new("seed".to_string(), "make seed".to_string());
+
+        runner.exit_on_start("seed", 2);
+        runner.start(&seed, &log_path).unwrap();
+        assert!(!runner.is_running(&seed).unwrap());
+        let exit = std::fs::read_to_string(exit_path_for_log(&log_path)).unwrap();
+        assert_eq!(exit, "2\n");
+    }
+
+    #[test]
+    fn test_persistent_fake_runner_is_shared() {
+        let temp = TempDir::new().unwrap();
+        let store = temp.path().join("runner.json");
+        let log_path = temp.path().join("api.log");
+        let api = Service::new("api".to_string(), "serve".to_string());
+
+        FakeRunner::persistent(&store)
+            .start(&api, &log_path)
+            .unwrap();
+        let runner = FakeRunner::persistent(&store);
+        assert!(runner.is_running(&api).unwrap());
+        runner.stop(&api).unwrap();
+        assert!(!FakeRunner::persistent(&store).is_running(&api).unwrap());
+    }
+}
@@ -106,7 +106,7 @@ pub fn generate_service_name(command: &str) -> String {
                     && before_eq
                         .chars()
                         .next()
-                        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+                        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
                     && before_eq
                         .chars()
                         .all(|c| c.is_ascii_alphanumeric() || c == '_');
@@ -122,7 +122,7 @@ pub fn generate_service_name(command: &str) -> String {
     }
 
     // Extract just the binary name from path
-    let executable = words[0].split('/').last().unwrap_or("service");
+    let executable = words[0].split('/').next_back().unwrap_or("service");
 
     // For npx/bunx/pnpx commands, use the package name (second argument)
     let name_source = if (executable == "npx" || executable == "bunx" || executable == "pnpx")
@@ -145,7 +145,7 @@ pub fn generate_service_name(command: &str) -> String {
         // Scoped package like @org/package - get the part after @
         name_source
             .split('/')
-            .last()
+            .next_back()
             .and_then(|s| s.split('@').next())
             .unwrap_or(name_source)
     } else {
@@ -0,0 +1,267 @@
+//! File watching for auto-restart
+//!
+//! Provides the pieces `lars watch` is built from: a [`WatchMatcher`] that
+//! decides whether a changed path is relevant to a service, a [`Debouncer`]
+//! that coalesces bursts of changes, and a [`ServiceWatcher`] that reports
+//! relevant changes under a service's working directory.
+
+use crate::error::{LarsError, Result};
+use crate::models::{Service, WatchConfig};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+/// Directories that are never watched, since tools write to them constantly
+const DEFAULT_IGNORES: &[&str] = &["**/.git/**", "**/target/**", "**/node_modules/**"];
+
+/// Decides which changed paths under a root directory are relevant
+#[derive(Debug, Clone)]
+pub struct WatchMatcher {
+    root: PathBuf,
+    include: Option<GlobSet>,
+    ignore: GlobSet,
+}
+
+impl WatchMatcher {
+    /// Build a matcher for paths under `root` from a watch configuration
+    pub fn new(root: &Path, config: &WatchConfig) -> Result<Self> {
+        let include = if config.paths.is_empty() {
+            None
+        } else {
+            Some(build_globset(config.paths.iter().map(String::as_str))?)
+        };
+
+        let ignore = build_globset(
+            DEFAULT_IGNORES
+                .iter()
+                .copied()
+                .chain(config.ignore.iter().map(String::as_str)),
+        )?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            include,
+            ignore,
+        })
+    }
+
+    /// Check whether a change to `path` should trigger a restart
+    pub fn is_match(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+
+        if self.ignore.is_match(relative) {
+            return false;
+        }
+
+        match &self.include {
+            Some(include) => include.is_match(relative),
+            None => true,
+        }
+    }
+}
+
+fn build_globset<'a>(patterns: impl Iterator<Item = &'a str>) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| LarsError::Watch(format!("invalid pattern '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| LarsError::Watch(e.to_string()))
+}
+
+/// Coalesces a burst of changes into a single trigger.
+///
+/// A trigger fires once no new change has been recorded for the debounce
+/// delay, and reports the first path that changed in the burst.
+#[derive(Debug)]
+pub struct Debouncer {
+    delay: Duration,
+    pending: Option<(PathBuf, Instant)>,
+}
+
+impl Debouncer {
+    /// Create a debouncer with the given quiet period
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: None,
+        }
+    }
+
+    /// Record a change at `now`
+    pub fn record(&mut self, path: PathBuf, now: Instant) {
+        match &mut self.pending {
+            Some((_, last)) => *last = now,
+            None => self.pending = Some((path, now)),
+        }
+    }
+
+    /// Return the triggering path if the quiet period has elapsed at `now`
+    pub fn poll(&mut self, now: Instant) -> Option<PathBuf> {
+        match &self.pending {
+            Some((_, last)) if now.duration_since(*last) >= self.delay => {
+                self.pending.take().map(|(path, _)| path)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Watches a service's working directory and reports relevant changes.
+///
+/// Each relevant change is sent on the channel as `(tag, path)`, so several
+/// watchers can share one receiver. Watching stops when this is dropped.
+pub struct ServiceWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl ServiceWatcher {
+    /// Start watching `service`, which must have a watch configuration and
+    /// a working directory
+    pub fn new<T>(service: &Service, tag: T, tx: Sender<(T, PathBuf)>) -> Result<Self>
+    where
+        T: Clone + Send + 'static,
+    {
+        let config = service.watch.as_ref().ok_or_else(|| {
+            LarsError::Watch(format!(
+                "service '{}' has no watch configuration",
+                service.name
+            ))
+        })?;
+        let root = service.cwd.as_ref().ok_or_else(|| {
+            LarsError::Watch(format!(
+                "service '{}' has no working directory to watch",
+                service.name
+            ))
+        })?;
+        // Changes are reported at canonical paths, so the root they are
+        // matched against must be canonical too
+        let root = root
+            .canonicalize()
+            .map_err(|e| LarsError::Watch(format!("{}: {}", root.display(), e)))?;
+
+        let matcher = WatchMatcher::new(&root, config)?;
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                return;
+            }
+
+            for path in event.paths {
+                if matcher.is_match(&path) {
+                    let _ = tx.send((tag.clone(), path));
+                }
+            }
+        })
+        .map_err(|e| LarsError::Watch(e.to_string()))?;
+
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| LarsError::Watch(format!("{}: {}", root.display(), e)))?;
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_matcher_include_and_ignore() {
+        let config = WatchConfig {
+            paths: vec!["src/**/*.go".to_string()],
+            ignore: vec!["**/*_test.go".to_string()],
+            ..Default::default()
+        };
+        let matcher = WatchMatcher::new(Path::new("/app"), &config).unwrap();
+
+        assert!(matcher.is_match(Path::new("/app/src/main.go")));
+        assert!(matcher.is_match(Path::new("/app/src/http/server.go")));
+        assert!(!matcher.is_match(Path::new("/app/src/main_test.go")));
+        assert!(!matcher.is_match(Path::new("/app/README.md")));
+    }
+
+    #[test]
+    fn test_matcher_default_ignores() {
+        let matcher = WatchMatcher::new(Path::new("/app"), &WatchConfig::default()).unwrap();
+
+        assert!(matcher.is_match(Path::new("/app/src/main.rs")));
+        assert!(!matcher.is_match(Path::new("/app/target/debug/app")));
+        assert!(!matcher.is_match(Path::new("/app/.git/index")));
+        assert!(!matcher.is_match(Path::new("/app/web/node_modules/x/index.js")));
+    }
+
+    #[test]
+    fn test_matcher_invalid_pattern() {
+        let config = WatchConfig {
+            paths: vec!["src/[".to_string()],
+            ..Default::default()
+        };
+        let result = WatchMatcher::new(Path::new("/app"), &config);
+        assert!(matches!(result, Err(LarsError::Watch(_))));
+    }
+
+    #[test]
+    fn test_debouncer_waits_for_quiet_period() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        debouncer.record(PathBuf::from("a.go"), start);
+        debouncer.record(PathBuf::from("b.go"), start + Duration::from_millis(80));
+
+        assert!(debouncer.poll(start + Duration::from_millis(150)).is_none());
+        assert_eq!(
+            debouncer.poll(start + Duration::from_millis(180)),
+            Some(PathBuf::from("a.go"))
+        );
+        assert!(debouncer.poll(start + Duration::from_millis(500)).is_none());
+    }
+
+    #[test]
+    fn test_service_watcher_reports_matching_changes() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+
+        // A working directory that is not canonical still matches
+        let mut service = Service::new("test".to_string(), "echo hello".to_string());
+        service.cwd = Some(root.join("src").join(".."));
+        service.watch = Some(WatchConfig {
+            paths: vec!["src/**".to_string()],
+            ..Default::default()
+        });
+
+        let (tx, rx) = mpsc::channel();
+        let _watcher = ServiceWatcher::new(&service, "test", tx).unwrap();
+
+        std::fs::write(root.join("notes.txt"), "ignored").unwrap();
+        std::fs::write(root.join("src").join("main.go"), "package main").unwrap();
+
+        let (tag, path) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(tag, "test");
+        assert_eq!(path, root.join("src").join("main.go"));
+    }
+
+    #[test]
+    fn test_service_watcher_requires_cwd() {
+        let mut service = Service::new("test".to_string(), "echo hello".to_string());
+        service.watch = Some(WatchConfig::default());
+
+        let (tx, _rx) = mpsc::channel::<(u8, PathBuf)>();
+        let result = ServiceWatcher::new(&service, 0, tx);
+        assert!(matches!(result, Err(LarsError::Watch(_))));
+    }
+}